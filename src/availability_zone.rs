@@ -1,28 +1,59 @@
+use crate::provider::Zone;
+use crate::statistics::SpotStatistics;
+use aws_sdk_ec2::primitives::DateTime;
+
+#[derive(Debug, Clone)]
 pub(super) struct AvailabilityZone {
-    min: f64,
-    avg: f64,
-    max: f64,
-    last: f64,
+    zone: Zone,
+    statistics: SpotStatistics,
+    history: Vec<(DateTime, f64)>,
 }
 
 impl AvailabilityZone {
-    pub(super) fn new(min: f64, avg: f64, max: f64, last: f64) -> Self {
-        Self { min, avg, max, last }
+    pub(super) fn new(zone: &Zone, statistics: SpotStatistics) -> Self {
+        Self {
+            zone: zone.clone(),
+            statistics,
+            history: Vec::new(),
+        }
+    }
+
+    /// keep the spot prices the statistics were computed from
+    pub(super) fn with_history(self, history: &[(DateTime, f64)]) -> Self {
+        Self {
+            history: history.to_vec(),
+            ..self
+        }
+    }
+
+    /// all prices divided by the units of the instance
+    pub(super) fn per(self, amount: f64) -> Self {
+        Self {
+            statistics: self.statistics.per(amount),
+            history: self
+                .history
+                .iter()
+                .map(|(time, price)| (*time, price / amount))
+                .collect(),
+            ..self
+        }
     }
 
-    pub(super) fn get_min(&self) -> f64 {
-        self.min
+    pub(super) fn get_name(&self) -> &str {
+        self.zone.name()
     }
 
-    pub(super) fn get_max(&self) -> f64 {
-        self.max
+    /// the zone ID, the same location in all accounts
+    pub(super) fn get_zone_id(&self) -> Option<&str> {
+        self.zone.id()
     }
 
-    pub(super) fn get_average(&self) -> f64 {
-        self.avg
+    /// min, avg, max, last, percentiles and volatility of the spot prices
+    pub(super) fn get_statistics(&self) -> &SpotStatistics {
+        &self.statistics
     }
 
-    pub(super) fn get_last(&self) -> f64 {
-        self.last
+    pub(super) fn get_history(&self) -> &[(DateTime, f64)] {
+        &self.history
     }
 }
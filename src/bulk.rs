@@ -0,0 +1,775 @@
+use crate::error::{Error, Result};
+use crate::price_list::{OfferTerm, PriceDimension, PriceListDocument, Product};
+use crate::provider::{ProductFilter, ProductPrices};
+use crate::savings_plans::{SavingsPlanFilter, SavingsPlanRate};
+use rusqlite::{params, Connection};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS products (
+    sku TEXT PRIMARY KEY,
+    region TEXT NOT NULL,
+    instance_type TEXT NOT NULL,
+    document TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS products_by_instance ON products (instance_type, region);
+";
+
+// an offer file is read as a stream into these tables and joined by SKU afterwards,
+// products and terms are far apart in the file
+const STAGING: &str = "
+CREATE TEMP TABLE IF NOT EXISTS staged_products (sku TEXT PRIMARY KEY, product TEXT NOT NULL);
+CREATE TEMP TABLE IF NOT EXISTS staged_terms (sku TEXT NOT NULL, kind TEXT NOT NULL, terms TEXT NOT NULL);
+CREATE INDEX IF NOT EXISTS temp.staged_terms_by_sku ON staged_terms (sku);
+CREATE TEMP TABLE IF NOT EXISTS staged_rows (sku TEXT NOT NULL, row TEXT NOT NULL);
+CREATE INDEX IF NOT EXISTS temp.staged_rows_by_sku ON staged_rows (sku);
+";
+const DROP_STAGING: &str = "
+DROP TABLE IF EXISTS temp.staged_products;
+DROP TABLE IF EXISTS temp.staged_terms;
+DROP TABLE IF EXISTS temp.staged_rows;
+";
+
+// the kinds of terms the analyzer uses
+const ON_DEMAND: &str = "OnDemand";
+const RESERVED: &str = "Reserved";
+
+// the columns of a CSV offer file which are not product attributes
+const SKU: &str = "SKU";
+const OFFER_TERM_CODE: &str = "OfferTermCode";
+const RATE_CODE: &str = "RateCode";
+const TERM_TYPE: &str = "TermType";
+const PRICE_DESCRIPTION: &str = "PriceDescription";
+const EFFECTIVE_DATE: &str = "EffectiveDate";
+const STARTING_RANGE: &str = "StartingRange";
+const ENDING_RANGE: &str = "EndingRange";
+const UNIT: &str = "Unit";
+const PRICE_PER_UNIT: &str = "PricePerUnit";
+const CURRENCY: &str = "Currency";
+const RELATED_TO: &str = "RelatedTo";
+const PRODUCT_FAMILY: &str = "Product Family";
+const SERVICE_CODE: &str = "serviceCode";
+const TERM_ATTRIBUTES: &[&str] = &["LeaseContractLength", "PurchaseOption", "OfferingClass"];
+
+// the terms of a product by offer term code
+type Terms = BTreeMap<String, OfferTerm>;
+
+#[derive(Debug, Default)]
+// the fields of a JSON offer file besides the products and terms
+struct OfferMetadata {
+    offer_code: Option<String>,
+    version: Option<String>,
+    publication_date: Option<String>,
+}
+
+// a JSON offer file, staging the products and terms while they are read
+struct OfferFileSeed<'c>(&'c Connection);
+
+impl<'de> DeserializeSeed<'de> for OfferFileSeed<'_> {
+    type Value = OfferMetadata;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for OfferFileSeed<'_> {
+    type Value = OfferMetadata;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an offer file")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut metadata = OfferMetadata::default();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "offerCode" => metadata.offer_code = Some(map.next_value()?),
+                "version" => metadata.version = map.next_value()?,
+                "publicationDate" => metadata.publication_date = map.next_value()?,
+                "products" => map.next_value_seed(ProductsSeed(self.0))?,
+                "terms" => map.next_value_seed(TermsSeed(self.0))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(metadata)
+    }
+}
+
+// the products of a JSON offer file by SKU
+struct ProductsSeed<'c>(&'c Connection);
+
+impl<'de> DeserializeSeed<'de> for ProductsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ProductsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("products by SKU")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some((_, product)) = map.next_entry::<IgnoredAny, Product>()? {
+            stage_product(self.0, &product).map_err(<A::Error as de::Error>::custom)?;
+        }
+        Ok(())
+    }
+}
+
+// the terms of a JSON offer file by kind, e.g. `OnDemand`
+struct TermsSeed<'c>(&'c Connection);
+
+impl<'de> DeserializeSeed<'de> for TermsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TermsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("terms by kind")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(kind) = map.next_key::<String>()? {
+            if kind == ON_DEMAND || kind == RESERVED {
+                map.next_value_seed(TermKindSeed(self.0, &kind))?;
+            } else {
+                // e.g. spot or capacity block terms the analyzer does not use
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// the terms of one kind of a JSON offer file by SKU
+struct TermKindSeed<'c, 'k>(&'c Connection, &'k str);
+
+impl<'de> DeserializeSeed<'de> for TermKindSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TermKindSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("terms by SKU")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some((sku, terms)) = map.next_entry::<String, Terms>()? {
+            stage_terms(self.0, &sku, self.1, &terms).map_err(<A::Error as de::Error>::custom)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+// the rows of a term in a CSV offer file
+struct TermRows {
+    effective_date: Option<String>,
+    price_dimensions: BTreeMap<String, PriceDimension>,
+    term_attributes: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+// the rows of a product in a CSV offer file
+struct ProductRows {
+    product_family: Option<String>,
+    attributes: BTreeMap<String, String>,
+    on_demand: BTreeMap<String, TermRows>,
+    reserved: BTreeMap<String, TermRows>,
+}
+
+/// the URL of the EC2 offer file (JSON) of a region
+pub fn offer_file_url(region: &str) -> String {
+    format!("https://pricing.us-east-1.amazonaws.com/offers/v1.0/aws/AmazonEC2/current/{region}/index.json")
+}
+
+/// download the EC2 offer file of a region into `dir`
+///
+/// Returns the path of the file, e.g. `<dir>/AmazonEC2-us-east-1.json`.
+pub async fn download(region: &str, dir: &Path) -> Result<PathBuf> {
+    let download_error = |err: reqwest::Error| Error::Download(err.to_string());
+
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("AmazonEC2-{region}.json"));
+    // a partial download never replaces a complete file
+    let partial = path.with_extension("json.partial");
+
+    let mut response = reqwest::get(offer_file_url(region))
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(download_error)?;
+
+    let mut file = std::fs::File::create(&partial)?;
+    while let Some(chunk) = response.chunk().await.map_err(download_error)? {
+        file.write_all(&chunk)?;
+    }
+    file.flush()?;
+    std::fs::rename(&partial, &path)?;
+
+    Ok(path)
+}
+
+#[derive(Debug)]
+/// a local index of EC2 offer files, answering price queries offline
+///
+/// Only the instance products are kept, as price list documents of the Pricing API.
+pub struct PriceListIndex {
+    connection: Mutex<Connection>,
+}
+
+impl PriceListIndex {
+    /// open or create the SQLite index at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// an index only living in memory
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// add an offer file, JSON or CSV by its extension
+    ///
+    /// Returns the number of indexed products.
+    pub fn ingest(&self, path: &Path) -> Result<usize> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => self.ingest_json(reader),
+            Some("csv") => self.ingest_csv(reader),
+            _ => Err(Error::Unsupported(format!(
+                "offer file {}, expected .json or .csv",
+                path.display()
+            ))),
+        }
+    }
+
+    /// add a JSON offer file
+    ///
+    /// The file is read as a stream, only one product and its terms are in memory at a time.
+    pub fn ingest_json<R: Read>(&self, reader: R) -> Result<usize> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(STAGING)?;
+
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let offer = OfferFileSeed(&transaction)
+            .deserialize(&mut deserializer)
+            .and_then(|offer| deserializer.end().map(|()| offer))
+            .map_err(|err| Error::MalformedPriceList(err.to_string()))?;
+        let offer_code = offer.offer_code.ok_or_else(|| {
+            Error::MalformedPriceList("no offerCode in JSON offer file".to_string())
+        })?;
+
+        let mut count = 0;
+        {
+            let mut statement =
+                transaction.prepare("SELECT product FROM staged_products ORDER BY sku")?;
+            let mut rows = statement.query([])?;
+            while let Some(row) = rows.next()? {
+                let product: Product = serde_json::from_str(&row.get::<_, String>(0)?)?;
+                let (on_demand, reserved) = staged_terms(&transaction, product.sku())?;
+                let document = PriceListDocument::new(
+                    product,
+                    &offer_code,
+                    on_demand,
+                    reserved,
+                    offer.version.clone(),
+                    offer.publication_date.clone(),
+                );
+                count += store(&transaction, &document)?;
+            }
+        }
+
+        transaction.execute_batch(DROP_STAGING)?;
+        transaction.commit()?;
+
+        Ok(count)
+    }
+
+    /// add a CSV offer file: a few lines of metadata, a header and a row per price
+    ///
+    /// The file is read as a stream, only the rows of one SKU are in memory at a time.
+    pub fn ingest_csv<R: Read>(&self, reader: R) -> Result<usize> {
+        let malformed = |err: csv::Error| Error::MalformedPriceList(err.to_string());
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+        let mut records = reader.records();
+
+        // `"Version","20241011201005"` and alike up to the header
+        let mut metadata = HashMap::new();
+        let header = loop {
+            let record = records
+                .next()
+                .ok_or_else(|| {
+                    Error::MalformedPriceList("no header in CSV offer file".to_string())
+                })?
+                .map_err(malformed)?;
+            if record.get(0) == Some(SKU) {
+                break record.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+            }
+            if let (Some(key), Some(value)) = (record.get(0), record.get(1)) {
+                metadata.insert(key.to_string(), value.to_string());
+            }
+        };
+
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(STAGING)?;
+
+        {
+            let mut statement =
+                transaction.prepare("INSERT INTO staged_rows (sku, row) VALUES (?1, ?2)")?;
+            for record in records {
+                let record = record.map_err(malformed)?;
+                let row = header
+                    .iter()
+                    .map(String::as_str)
+                    .zip(record.iter())
+                    .collect::<HashMap<_, _>>();
+                let sku = row.get(SKU).copied().unwrap_or_default();
+                statement.execute(params![sku, serde_json::to_string(&row)?])?;
+            }
+        }
+
+        let service_code = metadata
+            .get("OfferCode")
+            .map_or("AmazonEC2", |s| s.as_str());
+        let store_products = |products: BTreeMap<String, ProductRows>| {
+            products
+                .into_iter()
+                .map(|(sku, rows)| {
+                    let document = csv_document(&sku, rows, service_code, &metadata);
+                    store(&transaction, &document)
+                })
+                .sum::<Result<usize>>()
+        };
+
+        let mut count = 0;
+        {
+            // the rows of a product are adjacent in this order
+            let mut statement =
+                transaction.prepare("SELECT sku, row FROM staged_rows ORDER BY sku, rowid")?;
+            let mut rows = statement.query([])?;
+            let mut products: BTreeMap<String, ProductRows> = BTreeMap::new();
+            while let Some(row) = rows.next()? {
+                let sku: String = row.get(0)?;
+                if !products.is_empty() && !products.contains_key(&sku) {
+                    count += store_products(std::mem::take(&mut products))?;
+                }
+
+                let row: HashMap<String, String> = serde_json::from_str(&row.get::<_, String>(1)?)?;
+                let row = row
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect::<HashMap<_, _>>();
+                add_row(&mut products, &row)?;
+            }
+            count += store_products(products)?;
+        }
+
+        transaction.execute_batch(DROP_STAGING)?;
+        transaction.commit()?;
+
+        Ok(count)
+    }
+
+    // the documents of an instance type and a region, any if not given
+    fn select(&self, instance: Option<&str>, region: Option<&str>) -> Result<Vec<String>> {
+        let connection = self.connection.lock().unwrap();
+
+        let mut statement = connection.prepare(
+            "SELECT document FROM products
+             WHERE (?1 IS NULL OR instance_type = ?1) AND (?2 IS NULL OR region = ?2)
+             ORDER BY region, instance_type, sku",
+        )?;
+        let rows = statement.query_map(params![instance, region], |row| row.get(0))?;
+
+        Ok(rows.collect::<Result<Vec<String>, _>>()?)
+    }
+}
+
+impl ProductPrices for PriceListIndex {
+    async fn get_products(&self, filters: &[ProductFilter]) -> Result<Vec<String>> {
+        let value = |field: &str| {
+            filters
+                .iter()
+                .find(|filter| filter.field() == field)
+                .map(ProductFilter::value)
+        };
+
+        let mut products = Vec::new();
+        for document in self.select(value("instanceType"), value("regionCode"))? {
+            let parsed = PriceListDocument::parse(&document)?;
+            if filters.iter().all(|filter| parsed.matches(filter)) {
+                products.push(document);
+            }
+        }
+
+        Ok(products)
+    }
+
+    async fn get_savings_plan_rates(
+        &self,
+        _filter: &SavingsPlanFilter,
+    ) -> Result<Vec<SavingsPlanRate>> {
+        Err(Error::Unsupported(
+            "Savings Plan rates are not part of the EC2 price list".to_string(),
+        ))
+    }
+}
+
+// stage an instance product of a JSON offer file
+fn stage_product(connection: &Connection, product: &Product) -> Result<()> {
+    if product.attribute("regionCode").is_none() || product.attribute("instanceType").is_none() {
+        // e.g. EBS volumes or data transfer
+        return Ok(());
+    }
+
+    connection
+        .prepare_cached("INSERT OR REPLACE INTO staged_products (sku, product) VALUES (?1, ?2)")?
+        .execute(params![product.sku(), serde_json::to_string(product)?])?;
+    Ok(())
+}
+
+// stage the terms of one kind of a product of a JSON offer file
+fn stage_terms(connection: &Connection, sku: &str, kind: &str, terms: &Terms) -> Result<()> {
+    connection
+        .prepare_cached("INSERT INTO staged_terms (sku, kind, terms) VALUES (?1, ?2, ?3)")?
+        .execute(params![sku, kind, serde_json::to_string(terms)?])?;
+    Ok(())
+}
+
+// the staged OnDemand and Reserved terms of a product
+fn staged_terms(connection: &Connection, sku: &str) -> Result<(Terms, Terms)> {
+    let mut on_demand = Terms::new();
+    let mut reserved = Terms::new();
+
+    let mut statement =
+        connection.prepare_cached("SELECT kind, terms FROM staged_terms WHERE sku = ?1")?;
+    let mut rows = statement.query(params![sku])?;
+    while let Some(row) = rows.next()? {
+        let kind: String = row.get(0)?;
+        let terms: Terms = serde_json::from_str(&row.get::<_, String>(1)?)?;
+        if kind == ON_DEMAND {
+            on_demand.extend(terms);
+        } else {
+            reserved.extend(terms);
+        }
+    }
+
+    Ok((on_demand, reserved))
+}
+
+// store an instance product, replacing older versions; 1 if stored
+fn store(connection: &Connection, document: &PriceListDocument) -> Result<usize> {
+    let product = document.product();
+    let (Some(region), Some(instance)) = (
+        product.attribute("regionCode"),
+        product.attribute("instanceType"),
+    ) else {
+        // e.g. EBS volumes or data transfer
+        return Ok(0);
+    };
+
+    connection
+        .prepare_cached(
+            "INSERT OR REPLACE INTO products (sku, region, instance_type, document)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?
+        .execute(params![
+            product.sku(),
+            region,
+            instance,
+            document.to_json()?
+        ])?;
+    Ok(1)
+}
+
+// the price list document of the rows of a product in a CSV offer file
+fn csv_document(
+    sku: &str,
+    rows: ProductRows,
+    service_code: &str,
+    metadata: &HashMap<String, String>,
+) -> PriceListDocument {
+    let terms = |terms: BTreeMap<String, TermRows>| {
+        terms
+            .into_iter()
+            .map(|(code, term)| {
+                let term = OfferTerm::new(
+                    &code,
+                    sku,
+                    term.effective_date,
+                    term.price_dimensions,
+                    term.term_attributes,
+                );
+                (format!("{sku}.{code}"), term)
+            })
+            .collect()
+    };
+
+    PriceListDocument::new(
+        Product::new(sku, rows.product_family, rows.attributes),
+        service_code,
+        terms(rows.on_demand),
+        terms(rows.reserved),
+        metadata.get("Version").cloned(),
+        metadata.get("Publication Date").cloned(),
+    )
+}
+
+// add a price row of a CSV offer file to its product and term
+fn add_row(products: &mut BTreeMap<String, ProductRows>, row: &HashMap<&str, &str>) -> Result<()> {
+    let field = |name: &str| row.get(name).copied().unwrap_or_default();
+    let optional = |name: &str| {
+        Some(field(name))
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+    };
+
+    let sku = field(SKU);
+    let code = field(OFFER_TERM_CODE);
+    if sku.is_empty() || code.is_empty() {
+        return Err(Error::MalformedPriceList(format!(
+            "CSV row without SKU or term: {row:?}"
+        )));
+    }
+
+    let product = products.entry(sku.to_string()).or_default();
+    if product.attributes.is_empty() {
+        product.product_family = optional(PRODUCT_FAMILY);
+        product.attributes = row
+            .iter()
+            .filter(|(name, value)| !value.is_empty() && is_attribute(name))
+            .map(|(name, value)| (attribute_name(name), value.to_string()))
+            .collect();
+    }
+
+    let terms = match field(TERM_TYPE) {
+        ON_DEMAND => &mut product.on_demand,
+        RESERVED => &mut product.reserved,
+        // e.g. spot or capacity block terms the analyzer does not use
+        _ => return Ok(()),
+    };
+    let term = terms.entry(code.to_string()).or_default();
+    term.effective_date = optional(EFFECTIVE_DATE);
+    for &name in TERM_ATTRIBUTES {
+        if let Some(value) = optional(name) {
+            term.term_attributes.insert(name.to_string(), value);
+        }
+    }
+
+    let rate_code = field(RATE_CODE);
+    term.price_dimensions.insert(
+        rate_code.to_string(),
+        PriceDimension::new(
+            rate_code,
+            field(PRICE_DESCRIPTION),
+            field(UNIT),
+            (optional(STARTING_RANGE), optional(ENDING_RANGE)),
+            field(CURRENCY),
+            field(PRICE_PER_UNIT),
+        ),
+    );
+
+    Ok(())
+}
+
+// whether a CSV column is an attribute of the product
+fn is_attribute(column: &str) -> bool {
+    ![
+        SKU,
+        OFFER_TERM_CODE,
+        RATE_CODE,
+        TERM_TYPE,
+        PRICE_DESCRIPTION,
+        EFFECTIVE_DATE,
+        STARTING_RANGE,
+        ENDING_RANGE,
+        UNIT,
+        PRICE_PER_UNIT,
+        CURRENCY,
+        RELATED_TO,
+        PRODUCT_FAMILY,
+        SERVICE_CODE,
+    ]
+    .contains(&column)
+        && !TERM_ATTRIBUTES.contains(&column)
+}
+
+// the attribute name of the JSON offer files, e.g. `Instance Type` becomes `instanceType`
+fn attribute_name(column: &str) -> String {
+    match column {
+        "Pre Installed S/W" => return "preInstalledSw".to_string(),
+        "CapacityStatus" => return "capacitystatus".to_string(),
+        _ => {}
+    }
+
+    let mut words = column.split_whitespace();
+    let mut name = words.next().unwrap_or_default().to_lowercase();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            name.extend(first.to_uppercase());
+            name.push_str(&chars.as_str().to_lowercase());
+        }
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pricing::{get_catalog_filters, PriceQuery};
+    use crate::reserved::PurchaseOption;
+    use pretty_assertions::assert_eq;
+
+    const OFFER_FILE: &str = include_str!("../testdata/offer_file/AmazonEC2-us-east-2.json");
+    const OFFER_FILE_CSV: &str = include_str!("../testdata/offer_file/AmazonEC2-us-east-2.csv");
+
+    #[test]
+    fn test_attribute_name() {
+        assert_eq!(attribute_name("Instance Type"), "instanceType");
+        assert_eq!(attribute_name("vCPU"), "vcpu");
+        assert_eq!(
+            attribute_name("Dedicated EBS Throughput"),
+            "dedicatedEbsThroughput"
+        );
+        assert_eq!(attribute_name("Pre Installed S/W"), "preInstalledSw");
+        assert_eq!(attribute_name("operation"), "operation");
+    }
+
+    #[tokio::test]
+    async fn test_json() -> Result<()> {
+        let index = PriceListIndex::in_memory()?;
+        // the EBS volume is skipped
+        assert_eq!(index.ingest_json(OFFER_FILE.as_bytes())?, 3);
+
+        assert_eq!(
+            index
+                .get_ondemand_price("c7g.16xlarge", "us-east-2")
+                .await?,
+            2.312
+        );
+        assert!(matches!(
+            index.get_ondemand_price("c7g.16xlarge", "eu-north-1").await,
+            Err(Error::NotOffered(_))
+        ));
+
+        let offerings = index
+            .get_reserved_offerings("c7g.16xlarge", "us-east-2", &PriceQuery::default())
+            .await?;
+        assert_eq!(offerings.len(), 1);
+        assert_eq!(offerings[0].purchase_option(), PurchaseOption::AllUpfront);
+        assert_eq!(offerings[0].upfront_fee(), 30756.0);
+
+        let catalog = index
+            .get_products(&get_catalog_filters(&PriceQuery::default())?)
+            .await?;
+        assert_eq!(catalog.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_csv() -> Result<()> {
+        let index = PriceListIndex::in_memory()?;
+        assert_eq!(index.ingest_csv(OFFER_FILE_CSV.as_bytes())?, 3);
+
+        assert_eq!(
+            index
+                .get_ondemand_price("c7g.16xlarge", "us-east-2")
+                .await?,
+            2.312
+        );
+        assert_eq!(
+            index.get_ondemand_price("c7g.8xlarge", "us-east-2").await?,
+            1.156
+        );
+
+        let offerings = index
+            .get_reserved_offerings("c7g.16xlarge", "us-east-2", &PriceQuery::default())
+            .await?;
+        assert_eq!(offerings.len(), 1);
+        assert_eq!(offerings[0].upfront_fee(), 30756.0);
+
+        let document =
+            PriceListDocument::parse(&index.select(Some("c7g.16xlarge"), Some("us-east-2"))?[0])?;
+        assert_eq!(document.version(), Some("20241011201005"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_order() -> Result<()> {
+        // the terms before the products, the version last
+        let offer: serde_json::Value = serde_json::from_str(OFFER_FILE)?;
+        let reordered = format!(
+            "{{\"terms\": {}, \"products\": {}, \"offerCode\": {}, \"version\": {}}}",
+            offer["terms"], offer["products"], offer["offerCode"], offer["version"]
+        );
+
+        let index = PriceListIndex::in_memory()?;
+        assert_eq!(index.ingest_json(reordered.as_bytes())?, 3);
+        // a second ingest replaces the products
+        assert_eq!(index.ingest_json(OFFER_FILE.as_bytes())?, 3);
+
+        let documents = index.select(None, None)?;
+        assert_eq!(documents.len(), 3);
+        let document = PriceListDocument::parse(&documents[0])?;
+        assert_eq!(document.version(), Some("20241011201005"));
+        assert_eq!(document.on_demand_terms().count(), 1);
+
+        assert!(matches!(
+            index.ingest_json("{\"products\": {}}".as_bytes()),
+            Err(Error::MalformedPriceList(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use crate::error::Result;
+use crate::provider::{ProductFilter, ProductPrices};
+use crate::recording::{products_path, savings_plan_rates_path};
+use crate::savings_plans::{SavingsPlanFilter, SavingsPlanRate};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Serialize, Deserialize)]
+// a response and the request it answers
+struct CacheEntry<K, V> {
+    request: K,
+    // seconds since the epoch
+    fetched_at: u64,
+    response: V,
+}
+
+/// the default cache directory, e.g. `~/.cache/aws_ec2_analyzer`
+pub fn default_cache_dir() -> PathBuf {
+    if let Some(cache) = std::env::var_os("XDG_CACHE_HOME") {
+        return PathBuf::from(cache).join("aws_ec2_analyzer");
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home).join(".cache").join("aws_ec2_analyzer");
+    }
+    std::env::temp_dir().join("aws_ec2_analyzer")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Debug)]
+/// an on-disk cache in front of a slow price list
+pub struct PriceCache<P> {
+    inner: P,
+    dir: PathBuf,
+    ttl: Duration,
+    refresh: bool,
+}
+
+impl<P> PriceCache<P> {
+    /// cache the responses of `inner` in `dir` for `ttl`
+    pub fn new(inner: P, dir: &Path, ttl: Duration) -> Self {
+        Self {
+            inner,
+            dir: dir.to_path_buf(),
+            ttl,
+            refresh: false,
+        }
+    }
+
+    /// ignore cached responses, but still update the cache
+    pub fn with_refresh(self, refresh: bool) -> Self {
+        Self { refresh, ..self }
+    }
+
+    fn lookup<K, V>(&self, path: &Path, request: &K) -> Option<V>
+    where
+        K: PartialEq + DeserializeOwned,
+        V: DeserializeOwned,
+    {
+        if self.refresh {
+            return None;
+        }
+
+        // an unreadable entry is a miss
+        let content = std::fs::read_to_string(path).ok()?;
+        let entry: CacheEntry<K, V> = serde_json::from_str(&content).ok()?;
+
+        let age = now().saturating_sub(entry.fetched_at);
+        if entry.request != *request || age >= self.ttl.as_secs() {
+            return None;
+        }
+
+        Some(entry.response)
+    }
+
+    fn store<K: Serialize, V: Serialize>(
+        &self,
+        path: &Path,
+        request: K,
+        response: V,
+    ) -> Result<()> {
+        let entry = CacheEntry {
+            request,
+            fetched_at: now(),
+            response,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(&entry)?)?;
+
+        Ok(())
+    }
+}
+
+impl<P: ProductPrices> ProductPrices for PriceCache<P> {
+    async fn get_products(&self, filters: &[ProductFilter]) -> Result<Vec<String>> {
+        let path = products_path(&self.dir, filters);
+
+        if let Some(products) = self.lookup(&path, &filters.to_vec()) {
+            return Ok(products);
+        }
+
+        let products = self.inner.get_products(filters).await?;
+        if let Err(err) = self.store(&path, filters, &products) {
+            // the next lookup only takes longer
+            tracing::warn!("caching products in {}: {err}", path.display());
+        }
+
+        Ok(products)
+    }
+
+    async fn get_savings_plan_rates(
+        &self,
+        filter: &SavingsPlanFilter,
+    ) -> Result<Vec<SavingsPlanRate>> {
+        let path = savings_plan_rates_path(&self.dir, filter);
+
+        if let Some(rates) = self.lookup(&path, filter) {
+            return Ok(rates);
+        }
+
+        let rates = self.inner.get_savings_plan_rates(filter).await?;
+        if let Err(err) = self.store(&path, filter, &rates) {
+            tracing::warn!("caching Savings Plan rates in {}: {err}", path.display());
+        }
+
+        Ok(rates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_cache() -> Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("aws_ec2_analyzer_cache_{}", std::process::id()));
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let old = Fixture::new().with_ondemand_price("c7g.16xlarge", "us-east-2", 1.0);
+        let new = Fixture::new().with_ondemand_price("c7g.16xlarge", "us-east-2", 2.0);
+
+        let cache = PriceCache::new(old, &dir, day);
+        assert_eq!(
+            cache
+                .get_ondemand_price("c7g.16xlarge", "us-east-2")
+                .await?,
+            1.0
+        );
+
+        // served from the cache
+        let cache = PriceCache::new(new.clone(), &dir, day);
+        assert_eq!(
+            cache
+                .get_ondemand_price("c7g.16xlarge", "us-east-2")
+                .await?,
+            1.0
+        );
+
+        // expired
+        let cache = PriceCache::new(new.clone(), &dir, Duration::ZERO);
+        assert_eq!(
+            cache
+                .get_ondemand_price("c7g.16xlarge", "us-east-2")
+                .await?,
+            2.0
+        );
+
+        let cache = PriceCache::new(
+            Fixture::new().with_ondemand_price("c7g.16xlarge", "us-east-2", 3.0),
+            &dir,
+            day,
+        )
+        .with_refresh(true);
+        assert_eq!(
+            cache
+                .get_ondemand_price("c7g.16xlarge", "us-east-2")
+                .await?,
+            3.0
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+
+        // a cache that cannot be written still answers
+        std::fs::write(&dir, "not a directory")?;
+        let cache = PriceCache::new(new, &dir, day);
+        assert_eq!(
+            cache
+                .get_ondemand_price("c7g.16xlarge", "us-east-2")
+                .await?,
+            2.0
+        );
+        std::fs::remove_file(&dir)?;
+
+        Ok(())
+    }
+}
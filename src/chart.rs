@@ -0,0 +1,308 @@
+use crate::provider::TimeWindow;
+use aws_sdk_ec2::primitives::{DateTime, DateTimeFormat};
+use std::fmt::Write;
+use std::time::SystemTime;
+
+/// the number of characters of a sparkline
+pub const SPARKLINE_WIDTH: u32 = 12;
+
+// the bars of a sparkline, lowest first
+const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// the markers of the series of a chart, repeated for more series
+const MARKERS: [char; 6] = ['*', '+', 'o', 'x', '#', '@'];
+const REFERENCE: char = '-';
+
+// the width of the price labels left of a chart
+const LABEL_WIDTH: usize = 10;
+
+/// the price in effect at a time, None before the first price of the history
+pub fn price_at(history: &[(DateTime, f64)], time: f64) -> Option<f64> {
+    let index = history.partition_point(|(at, _)| at.as_secs_f64() <= time);
+    index.checked_sub(1).map(|index| history[index].1)
+}
+
+/// the mean price of the histories at `count` evenly spaced times after `start`, the last at `end`
+///
+/// None where no history has a price yet.
+pub fn sample(
+    histories: &[&[(DateTime, f64)]],
+    start: DateTime,
+    end: DateTime,
+    count: u32,
+) -> Vec<Option<f64>> {
+    let start = start.as_secs_f64();
+    let step = (end.as_secs_f64() - start) / f64::from(count.max(1));
+
+    (1..=count)
+        .map(|index| {
+            let time = start + step * f64::from(index);
+            let (sum, prices) = histories
+                .iter()
+                .filter_map(|history| price_at(history, time))
+                .fold((0.0, 0.0), |(sum, prices), price| {
+                    (sum + price, prices + 1.0)
+                });
+            (prices > 0.0).then(|| sum / prices)
+        })
+        .collect()
+}
+
+/// the times a chart of the histories spans: the window, or from the first price until now
+///
+/// None without prices.
+pub fn span(histories: &[&[(DateTime, f64)]], window: &TimeWindow) -> Option<(DateTime, DateTime)> {
+    let first = histories
+        .iter()
+        .filter_map(|history| history.first())
+        .map(|(time, _)| *time)
+        .min()?;
+
+    let start = window.start().unwrap_or(first);
+    let end = window
+        .end()
+        .unwrap_or_else(|| DateTime::from(SystemTime::now()));
+
+    Some((start, end))
+}
+
+/// bars as high as the values between the lowest and the highest one, blank for unknown values
+///
+/// Constant values are drawn as bars of half height.
+pub fn sparkline(values: &[Option<f64>]) -> String {
+    let known = values.iter().flatten().copied();
+    let low = known.clone().fold(f64::INFINITY, f64::min);
+    let high = known.fold(f64::NEG_INFINITY, f64::max);
+
+    values
+        .iter()
+        .map(|value| match value {
+            Some(value) if high > low => {
+                // the number of the 8 levels the value reaches above the lowest one
+                let share = (value - low) / (high - low);
+                let level = (1..8_u32)
+                    .filter(|boundary| share * 8.0 >= f64::from(*boundary))
+                    .count();
+                LEVELS[level]
+            }
+            Some(_) => LEVELS[3],
+            None => ' ',
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// a multi-line text chart of spot prices over time
+pub struct Chart {
+    // the name and the histories of each series, drawn as their mean
+    series: Vec<(String, Vec<Vec<(DateTime, f64)>>)>,
+    reference: Option<(String, f64)>,
+    width: u32,
+    height: u32,
+}
+
+impl Chart {
+    /// a chart of `width` columns and `height` rows of prices
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            series: Vec::new(),
+            reference: None,
+            width: width.max(2),
+            height: height.max(2),
+        }
+    }
+
+    /// add a line of the mean price of histories, e.g. of a zone or of all zones of a region
+    pub fn with_series(mut self, name: &str, histories: &[&[(DateTime, f64)]]) -> Self {
+        let histories = histories.iter().map(|history| history.to_vec()).collect();
+        self.series.push((name.to_string(), histories));
+        self
+    }
+
+    /// draw a constant price as a horizontal line, e.g. the on-demand price
+    pub fn with_reference(self, name: &str, price: f64) -> Self {
+        Self {
+            reference: Some((name.to_string(), price)),
+            ..self
+        }
+    }
+
+    /// the chart from `start` to `end` with the prices on the left and a legend below
+    ///
+    /// Overlapping series show the marker of the first one, above the reference line.
+    pub fn render(&self, start: DateTime, end: DateTime) -> String {
+        let samples = self
+            .series
+            .iter()
+            .map(|(_, histories)| {
+                let histories = histories.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                sample(&histories, start, end, self.width)
+            })
+            .collect::<Vec<_>>();
+
+        let prices = samples
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .chain(self.reference.iter().map(|(_, price)| *price));
+        let mut low = prices.clone().fold(f64::INFINITY, f64::min);
+        let mut high = prices.fold(f64::NEG_INFINITY, f64::max);
+        if low > high {
+            // nothing to draw
+            (low, high) = (0.0, 1.0);
+        } else if low == high {
+            let padding = (high.abs() * 0.1).max(1e-6);
+            (low, high) = (low - padding, high + padding);
+        }
+
+        let mut chart = String::new();
+        let columns = usize::try_from(self.width).unwrap_or_default();
+        let band = (high - low) / f64::from(self.height);
+
+        for row in (0..self.height).rev() {
+            let bottom = low + band * f64::from(row);
+            let top = bottom + band;
+            let top_row = row + 1 == self.height;
+            let contains = |price: f64| bottom <= price && (price < top || top_row);
+
+            let label = if top_row {
+                format!("{high:.5}")
+            } else if row == 0 {
+                format!("{low:.5}")
+            } else {
+                String::new()
+            };
+
+            let reference = self
+                .reference
+                .as_ref()
+                .is_some_and(|(_, price)| contains(*price));
+
+            let cells = (0..columns)
+                .map(|column| {
+                    samples
+                        .iter()
+                        .zip(MARKERS.iter().cycle())
+                        .find(|(series, _)| {
+                            matches!(series.get(column), Some(Some(price)) if contains(*price))
+                        })
+                        .map(|(_, marker)| *marker)
+                        .unwrap_or(if reference { REFERENCE } else { ' ' })
+                })
+                .collect::<String>();
+
+            let _ = writeln!(chart, "{label:>LABEL_WIDTH$} |{}", cells.trim_end());
+        }
+
+        let _ = writeln!(chart, "{:>LABEL_WIDTH$} +{}", "", "-".repeat(columns));
+
+        let start = format_time(&start);
+        let end = format_time(&end);
+        let gap = columns.saturating_sub(start.len() + end.len()).max(1);
+        let _ = writeln!(
+            chart,
+            "{:>LABEL_WIDTH$}  {start}{}{end}",
+            "",
+            " ".repeat(gap)
+        );
+
+        let mut legend = self
+            .series
+            .iter()
+            .zip(MARKERS.iter().cycle())
+            .map(|((name, _), marker)| format!("{marker} {name}"))
+            .collect::<Vec<_>>();
+        if let Some((name, price)) = &self.reference {
+            legend.push(format!("{REFERENCE} {name} {price:.5}"));
+        }
+        let _ = writeln!(chart, "{:>LABEL_WIDTH$}  {}", "", legend.join("   "));
+
+        chart
+    }
+}
+
+// e.g. `2024-06-01 12:00`
+fn format_time(time: &DateTime) -> String {
+    time.fmt(DateTimeFormat::DateTime)
+        .map(|time| time.chars().take(16).collect::<String>().replace('T', " "))
+        .unwrap_or_else(|_| time.secs().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn history(prices: &[(i64, f64)]) -> Vec<(DateTime, f64)> {
+        prices
+            .iter()
+            .map(|&(secs, price)| (DateTime::from_secs(secs), price))
+            .collect()
+    }
+
+    #[test]
+    fn test_sample() {
+        let a = history(&[(0, 1.0), (3600, 3.0)]);
+        let b = history(&[(1800, 2.0)]);
+
+        assert_eq!(price_at(&a, -1.0), None);
+        assert_eq!(price_at(&a, 3600.0), Some(3.0));
+
+        assert_eq!(
+            sample(
+                &[a.as_slice(), b.as_slice()],
+                DateTime::from_secs(-1800),
+                DateTime::from_secs(5400),
+                4
+            ),
+            [Some(1.0), Some(1.5), Some(2.5), Some(2.5)]
+        );
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(
+            sparkline(&[None, Some(1.0), Some(1.5), Some(2.0), Some(1.0)]),
+            " ▁▅█▁"
+        );
+        assert_eq!(sparkline(&[Some(2.0), Some(2.0)]), "▄▄");
+        assert_eq!(sparkline(&[None, None]), "  ");
+    }
+
+    #[test]
+    fn test_span() {
+        let a = history(&[(3600, 1.0)]);
+        let b = history(&[(1800, 2.0)]);
+        let window = TimeWindow::new(None, Some(DateTime::from_secs(7200)));
+
+        assert_eq!(
+            span(&[a.as_slice(), b.as_slice()], &window),
+            Some((DateTime::from_secs(1800), DateTime::from_secs(7200)))
+        );
+        assert_eq!(span(&[&[]], &window), None);
+    }
+
+    #[test]
+    fn test_chart() {
+        let chart = Chart::new(8, 4)
+            .with_series(
+                "us-east-2a",
+                &[history(&[(0, 1.0), (14400, 4.0)]).as_slice()],
+            )
+            .with_series("us-east-2b", &[history(&[(0, 2.0)]).as_slice()])
+            .with_reference("on-demand", 3.5);
+
+        assert_eq!(
+            chart.render(DateTime::from_secs(0), DateTime::from_secs(28800)),
+            "   4.00000 |---*****\n\
+             \x20          |\n\
+             \x20          |++++++++\n\
+             \x20  1.00000 |***\n\
+             \x20          +--------\n\
+             \x20           1970-01-01 00:00 1970-01-01 08:00\n\
+             \x20           * us-east-2a   + us-east-2b   - on-demand 3.50000\n"
+        );
+    }
+}
@@ -0,0 +1,590 @@
+use anyhow::{bail, Result};
+use aws_ec2_analyzer::cache::default_cache_dir;
+use aws_ec2_analyzer::export::{ExportFormat, Interval};
+use aws_ec2_analyzer::normalize::PriceUnit;
+use aws_ec2_analyzer::output::OutputFormat;
+use aws_ec2_analyzer::pricing::{
+    LicenseModel, OperatingSystem, PreInstalledSoftware, PriceQuery, Tenancy,
+};
+use aws_ec2_analyzer::print_spot_region::print_spot_regions::SavingsSort;
+use aws_ec2_analyzer::profile::{Profile, Profiles};
+use aws_ec2_analyzer::provider::{
+    InstanceCatalog, RegionFilter, TimeWindow, WindowLength, ZoneCatalog,
+};
+use aws_ec2_analyzer::savings_plans::SavingsPlan;
+use aws_ec2_analyzer::statistics::Metric;
+use aws_ec2_analyzer::throttle::RetryPolicy;
+use aws_sdk_ec2::primitives::{DateTime, DateTimeFormat};
+use aws_sdk_ec2::types::InstanceType;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// the region instance families are looked up in
+const FAMILY_REGION: &str = "us-east-1";
+
+const DEFAULT_FAVORITE_REGIONS: &[&str] = &[
+    "us-east-1",
+    "us-east-2",
+    "us-west-2",
+    "eu-west-1",
+    "eu-north-1",
+];
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "various tools for analyzing prices of AWS EC2 instances"
+)]
+pub(crate) struct Cli {
+    /// the analysis profile, its lists are extended by the other arguments
+    #[arg(short, long, global = true)]
+    profile: Option<String>,
+
+    /// the TOML file with the analysis profiles
+    #[arg(long, global = true, default_value = "profiles.toml")]
+    config: PathBuf,
+
+    /// record all AWS API responses into a directory
+    #[arg(long, global = true, conflicts_with = "replay")]
+    pub(crate) record: Option<PathBuf>,
+
+    /// serve all AWS API calls from a recording instead of AWS
+    #[arg(long, global = true)]
+    pub(crate) replay: Option<PathBuf>,
+
+    /// the directory of the pricing cache, default `~/.cache/aws_ec2_analyzer`
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// how long cached prices stay valid, in hours
+    #[arg(long, global = true, default_value_t = 24)]
+    pricing_ttl: u64,
+
+    /// ignore cached prices and fetch them again
+    #[arg(long, global = true)]
+    pub(crate) refresh: bool,
+
+    /// the database of spot prices, default `spot_prices.sqlite` in the cache directory
+    #[arg(long, global = true)]
+    spot_store: Option<PathBuf>,
+
+    /// answer all price queries from the local price list index instead of the Pricing API
+    #[arg(long, global = true)]
+    pub(crate) price_list: bool,
+
+    /// the price list index, default `price_list.sqlite` in the cache directory
+    #[arg(long, global = true)]
+    price_list_index: Option<PathBuf>,
+
+    /// the maximum number of concurrent EC2 API calls
+    #[arg(long, global = true, default_value_t = 16)]
+    pub(crate) ec2_concurrency: usize,
+
+    /// the maximum number of concurrent Pricing API calls
+    #[arg(long, global = true, default_value_t = 4)]
+    pub(crate) pricing_concurrency: usize,
+
+    /// the attempts of a throttled API call, including the first one
+    #[arg(long, global = true, default_value_t = 6)]
+    pub(crate) max_attempts: u32,
+
+    /// the retries of throttled API calls of the whole run
+    #[arg(long, global = true, default_value_t = 500)]
+    pub(crate) retry_budget: u32,
+
+    /// list every missing price with its reason instead of a footnote
+    #[arg(long, global = true)]
+    pub(crate) explain: bool,
+
+    /// the format of the report: text, json, csv or markdown
+    #[arg(short, long, global = true, default_value_t = OutputFormat::Text)]
+    pub(crate) output: OutputFormat,
+
+    #[command(flatten)]
+    pub(crate) query: QuerySelection,
+
+    #[command(subcommand)]
+    pub(crate) command: Command,
+}
+
+impl Cli {
+    /// the retry policy of a service calling at most `rate` times per second
+    pub(crate) fn retry_policy(&self, rate: f64) -> RetryPolicy {
+        RetryPolicy::new(rate, 2.0 * rate)
+            .with_max_attempts(self.max_attempts)
+            .with_budget(self.retry_budget)
+    }
+
+    /// the directory of the pricing cache
+    pub(crate) fn cache_dir(&self) -> PathBuf {
+        self.cache_dir.clone().unwrap_or_else(default_cache_dir)
+    }
+
+    /// the database of spot prices
+    pub(crate) fn spot_store(&self) -> PathBuf {
+        self.spot_store
+            .clone()
+            .unwrap_or_else(|| self.cache_dir().join("spot_prices.sqlite"))
+    }
+
+    /// the index of the bulk price list
+    pub(crate) fn price_list_index(&self) -> PathBuf {
+        self.price_list_index
+            .clone()
+            .unwrap_or_else(|| self.cache_dir().join("price_list.sqlite"))
+    }
+
+    /// how long cached prices stay valid
+    pub(crate) fn pricing_ttl(&self) -> Duration {
+        // a huge TTL never expires
+        Duration::from_secs(self.pricing_ttl.saturating_mul(60 * 60))
+    }
+
+    /// the selected profile, if any
+    pub(crate) fn load_profile(&self) -> Result<Option<Profile>> {
+        match &self.profile {
+            Some(name) => {
+                let profiles = Profiles::load(&self.config)?;
+                Ok(Some(profiles.get(name)?.clone()))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct QuerySelection {
+    /// the operating system: linux, rhel, suse, windows or ubuntu-pro
+    #[arg(long = "os", global = true)]
+    operating_system: Option<OperatingSystem>,
+
+    /// the pre-installed software: none, sql-web, sql-std or sql-ent
+    #[arg(long, global = true)]
+    software: Option<PreInstalledSoftware>,
+
+    /// how licenses are paid: included or byol
+    #[arg(long = "license", global = true)]
+    license_model: Option<LicenseModel>,
+
+    /// the tenancy: shared, dedicated or host
+    #[arg(long, global = true)]
+    tenancy: Option<Tenancy>,
+
+    /// report prices per instance, vcpu, core, gib, gpu or nvme-gb and rank by them
+    #[arg(long = "per", global = true)]
+    unit: Option<PriceUnit>,
+}
+
+impl QuerySelection {
+    /// the price lookup of the profile or the default one, overridden by the arguments
+    pub(crate) fn price_query(&self, profile: Option<&Profile>) -> PriceQuery {
+        let mut query = profile.map(Profile::price_query).unwrap_or_default();
+
+        if let Some(operating_system) = self.operating_system {
+            query = query.with_operating_system(operating_system);
+        }
+        if let Some(software) = self.software {
+            query = query.with_software(software);
+        }
+        if let Some(license_model) = self.license_model {
+            query = query.with_license_model(license_model);
+        }
+        if let Some(tenancy) = self.tenancy {
+            query = query.with_tenancy(tenancy);
+        }
+        if let Some(unit) = self.unit {
+            query = query.with_unit(unit);
+        }
+
+        query
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// print the spot and on-demand prices of instances across regions
+    SpotRegions {
+        #[command(flatten)]
+        selection: InstanceSelection,
+        #[command(flatten)]
+        regions: RegionSelection,
+        #[command(flatten)]
+        window: WindowSelection,
+        /// show the rate of a Savings Plan, e.g. `compute-1yr-no-upfront`
+        #[arg(long)]
+        savings_plan: Option<SavingsPlan>,
+        /// the statistic of the spot prices: min, avg, max, last, p50, p90, p99, stddev or changes
+        #[arg(long, default_value = "avg")]
+        metric: Metric,
+        /// add the cheapest availability zone and the spread between the zones of each instance
+        #[arg(long)]
+        zone_columns: bool,
+        /// add the savings, the spot to on-demand ratio and the spike ratio of each instance
+        #[arg(long)]
+        savings_columns: bool,
+        /// order the regions, the instances or both by their savings, highest first
+        #[arg(long, value_delimiter = ',')]
+        sort_by_savings: Vec<SavingsSort>,
+        /// add a sparkline of the spot prices over the window to each cell
+        #[arg(long)]
+        sparklines: bool,
+    },
+    /// draw the spot prices of an instance over time per zone, or per region with several
+    Chart {
+        /// the instance type, e.g. `c7g.16xlarge`
+        #[arg(short, long)]
+        instance: String,
+        #[command(flatten)]
+        regions: RegionSelection,
+        #[command(flatten)]
+        window: WindowSelection,
+        /// the number of columns of the chart
+        #[arg(long, default_value_t = 72)]
+        width: u32,
+        /// the number of rows of the chart
+        #[arg(long, default_value_t = 16)]
+        height: u32,
+    },
+    /// print the spot statistics of every availability zone of instances in regions
+    Zones {
+        #[command(flatten)]
+        selection: InstanceSelection,
+        #[command(flatten)]
+        regions: RegionSelection,
+        #[command(flatten)]
+        window: WindowSelection,
+        /// the statistic the zones are ordered by
+        #[arg(long, default_value = "avg")]
+        metric: Metric,
+    },
+    /// write a self-contained HTML report of spot prices and instances
+    Report {
+        #[command(flatten)]
+        selection: InstanceSelection,
+        #[command(flatten)]
+        regions: RegionSelection,
+        #[command(flatten)]
+        window: WindowSelection,
+        /// show the rate of a Savings Plan, e.g. `compute-1yr-no-upfront`
+        #[arg(long)]
+        savings_plan: Option<SavingsPlan>,
+        /// the statistic of the spot prices: min, avg, max, last, p50, p90, p99, stddev or changes
+        #[arg(long, default_value = "avg")]
+        metric: Metric,
+        /// the HTML file to write
+        #[arg(long, default_value = "report.html")]
+        out: PathBuf,
+    },
+    /// export the spot price history of instances in every zone of regions
+    ExportSpot {
+        #[command(flatten)]
+        selection: InstanceSelection,
+        #[command(flatten)]
+        regions: RegionSelection,
+        #[command(flatten)]
+        window: WindowSelection,
+        /// the file format: csv, jsonl or parquet
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
+        /// resample the prices to a fixed interval with forward fill: hourly or daily
+        #[arg(long)]
+        resample: Option<Interval>,
+        /// the file to write, stdout if none is given
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// print information about EC2 instances
+    Instances {
+        #[command(flatten)]
+        selection: InstanceSelection,
+    },
+    /// print information about all instances with EFA support
+    Efa {
+        /// the region whose instance types are listed
+        #[arg(short, long, default_value = "us-east-2")]
+        region: String,
+    },
+    /// print the on-demand prices of instances in regions
+    Ondemand {
+        #[command(flatten)]
+        selection: InstanceSelection,
+        #[command(flatten)]
+        regions: RegionSelection,
+    },
+    /// compare every reserved offering of instances with their on-demand and spot prices
+    Reserved {
+        #[command(flatten)]
+        selection: InstanceSelection,
+        #[command(flatten)]
+        regions: RegionSelection,
+        #[command(flatten)]
+        window: WindowSelection,
+    },
+    /// download or read EC2 offer files into the price list index used by `--price-list`
+    IngestPriceList {
+        /// regions whose offer file is downloaded, e.g. `us-east-2`
+        #[arg(short, long = "region", value_delimiter = ',')]
+        regions: Vec<String>,
+        /// local offer files, JSON or CSV
+        #[arg(long = "file", value_delimiter = ',')]
+        files: Vec<PathBuf>,
+    },
+    /// print the instance types with the cheapest on-demand price per vCPU
+    VcpuPrices {
+        /// only instance types of a region
+        #[arg(short, long)]
+        region: Option<String>,
+        /// the number of instance types printed
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+    },
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct InstanceSelection {
+    /// instance types, e.g. `hpc7g.16xlarge`
+    #[arg(short, long = "instance", value_delimiter = ',')]
+    instances: Vec<String>,
+
+    /// instance families, e.g. `c7g`, expanded to all of their sizes
+    #[arg(short, long = "family", value_delimiter = ',')]
+    families: Vec<String>,
+}
+
+impl InstanceSelection {
+    /// the instance types plus all instance types of the families
+    pub(crate) async fn resolve<C: InstanceCatalog>(
+        &self,
+        ec2: &C,
+        profile: Option<&Profile>,
+    ) -> Result<Vec<InstanceType>> {
+        let (profile_instances, profile_families) = match profile {
+            Some(profile) => (profile.instances(), profile.families()),
+            None => (&[][..], &[][..]),
+        };
+
+        let mut instances: Vec<InstanceType> = Vec::new();
+
+        for instance in profile_instances.iter().chain(&self.instances) {
+            let instance = InstanceType::from(instance.as_str());
+            if !instances.contains(&instance) {
+                instances.push(instance);
+            }
+        }
+
+        for family in profile_families.iter().chain(&self.families) {
+            for instance in ec2
+                .get_instance_types_of_family(FAMILY_REGION, family)
+                .await?
+            {
+                if !instances.contains(&instance) {
+                    instances.push(instance);
+                }
+            }
+        }
+
+        if instances.is_empty() {
+            bail!("no instance types given: use --instance, --family or --profile");
+        }
+
+        Ok(instances)
+    }
+}
+
+fn parse_date_time(s: &str) -> Result<DateTime, String> {
+    DateTime::from_str(s, DateTimeFormat::DateTime).map_err(|e| e.to_string())
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct WindowSelection {
+    /// the beginning of the analyzed spot prices, e.g. `2024-01-01T00:00:00Z`
+    #[arg(long, value_parser = parse_date_time)]
+    start: Option<DateTime>,
+
+    /// the end of the analyzed spot prices
+    #[arg(long, value_parser = parse_date_time)]
+    end: Option<DateTime>,
+
+    /// the length of the window before the end or now, e.g. `24h`, `7d`, `30d` or `90d`
+    #[arg(long = "window", conflicts_with = "start")]
+    length: Option<WindowLength>,
+}
+
+impl WindowSelection {
+    // a window given by its length only ends at `now`, the time of the recording on replay
+    pub(crate) fn window(&self, now: DateTime) -> TimeWindow {
+        match self.length {
+            Some(length) => TimeWindow::last(length, Some(self.end.unwrap_or(now))),
+            None => TimeWindow::new(self.start, self.end),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct RegionSelection {
+    /// regions to analyze, all regions if none are given
+    #[arg(short, long = "region", value_delimiter = ',')]
+    regions: Vec<String>,
+
+    /// only analyze the discovered regions matching these patterns, e.g. `eu-*`
+    #[arg(long = "include-region", value_delimiter = ',')]
+    include_regions: Vec<String>,
+
+    /// skip the discovered regions matching these patterns, e.g. `ap-*`
+    #[arg(long = "exclude-region", value_delimiter = ',')]
+    exclude_regions: Vec<String>,
+
+    /// regions printed in a separate block on top
+    #[arg(long = "favorite", value_delimiter = ',')]
+    favorite_regions: Vec<String>,
+}
+
+// the regions of a profile and of the arguments, each once in order of appearance
+fn merge(profile: &[String], arguments: &[String]) -> Vec<String> {
+    let mut merged: Vec<String> = Vec::new();
+    for region in profile.iter().chain(arguments) {
+        if !merged.contains(region) {
+            merged.push(region.clone());
+        }
+    }
+    merged
+}
+
+impl RegionSelection {
+    /// the given regions or all enabled regions passing the filter
+    pub(crate) async fn resolve<C: ZoneCatalog>(
+        &self,
+        ec2: &C,
+        profile: Option<&Profile>,
+    ) -> Result<Vec<String>> {
+        let regions = merge(
+            profile.map(Profile::regions).unwrap_or_default(),
+            &self.regions,
+        );
+
+        if regions.is_empty() {
+            let filter = RegionFilter::new(&self.include_regions, &self.exclude_regions);
+            return Ok(filter.apply(&ec2.get_regions().await?));
+        }
+
+        ec2.check_regions(&regions).await?;
+        Ok(regions)
+    }
+
+    /// the given favorite regions or the default ones
+    pub(crate) fn favorites(&self, profile: Option<&Profile>) -> Vec<String> {
+        let favorites = merge(
+            profile.map(Profile::favorite_regions).unwrap_or_default(),
+            &self.favorite_regions,
+        );
+
+        if favorites.is_empty() {
+            return DEFAULT_FAVORITE_REGIONS
+                .iter()
+                .map(|r| r.to_string())
+                .collect();
+        }
+        favorites
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aws_ec2_analyzer::fixture::Fixture;
+    use aws_ec2_analyzer::provider::SpotPriceHistory;
+    use aws_ec2_analyzer::recording::{Recorder, Replay};
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_merge_regions() -> Result<()> {
+        let profiles = Profiles::parse(
+            r#"
+            [hpc]
+            regions = ["us-east-2", "eu-north-1"]
+            favorite_regions = ["us-east-2"]
+            "#,
+        )?;
+        let profile = profiles.get("hpc")?;
+        let Command::Chart { regions, .. } = chart(&[
+            "--region",
+            "eu-west-1,us-east-2",
+            "--favorite",
+            "us-east-2,eu-north-1",
+        ]) else {
+            unreachable!()
+        };
+
+        let fixture = Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a"])
+            .with_zones("eu-north-1", &["eu-north-1a"])
+            .with_zones("eu-west-1", &["eu-west-1a"]);
+        assert_eq!(
+            regions.resolve(&fixture, Some(profile)).await?,
+            ["us-east-2", "eu-north-1", "eu-west-1"]
+        );
+        assert_eq!(
+            regions.favorites(Some(profile)),
+            ["us-east-2", "eu-north-1"]
+        );
+
+        Ok(())
+    }
+
+    fn chart(args: &[&str]) -> Command {
+        let command = ["aws_ec2_analyzer", "chart", "--instance", "c7g.16xlarge"];
+        Cli::parse_from(command.iter().chain(args)).command
+    }
+
+    #[tokio::test]
+    async fn test_replay_window() -> Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("aws_ec2_analyzer_window_{}", std::process::id()));
+        let Command::Chart { window, .. } = chart(&["--window", "7d"]) else {
+            unreachable!()
+        };
+
+        let fixture = Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a"])
+            .with_spot_prices("us-east-2", "us-east-2a", "c7g.16xlarge", &[(0, 1.5)]);
+        let instance = InstanceType::from("c7g.16xlarge");
+
+        let recorded_at = DateTime::from_secs(1_717_200_000);
+        let recorder = Recorder::new(fixture, &dir);
+        recorder.record_time(recorded_at)?;
+        let recorded = recorder
+            .get_spot_price_history(
+                "us-east-2",
+                "us-east-2a",
+                &instance,
+                "Linux/UNIX",
+                &window.window(recorded_at),
+            )
+            .await?;
+
+        // replayed a day later, the window still ends at the time of the recording
+        let replay = Replay::new(&dir);
+        let now = replay
+            .recorded_time()?
+            .unwrap_or(DateTime::from_secs(1_717_286_400));
+        assert_eq!(now, recorded_at);
+        assert_eq!(
+            replay
+                .get_spot_price_history(
+                    "us-east-2",
+                    "us-east-2a",
+                    &instance,
+                    "Linux/UNIX",
+                    &window.window(now),
+                )
+                .await?,
+            recorded
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
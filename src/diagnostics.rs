@@ -0,0 +1,222 @@
+use crate::error::Error;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// why a price is missing
+pub enum Reason {
+    /// the instance type has no product in the region
+    NotOffered,
+    /// no spot prices in the analyzed window
+    NoSpotHistory,
+    /// the price list has several matching products
+    NotUnique(usize),
+    /// AWS throttled the calls until the retries were used up
+    Throttled,
+    /// a response could not be parsed
+    Parse,
+    /// the instance type lacks the resource prices are divided by, e.g. GPUs
+    NoResource,
+    /// any other error
+    Other,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Reason::NotOffered => "not offered in region".to_string(),
+            Reason::NoSpotHistory => "no spot history".to_string(),
+            Reason::NotUnique(count) => format!("pricing product not unique ({count})"),
+            Reason::Throttled => "throttled".to_string(),
+            Reason::Parse => "parse error".to_string(),
+            Reason::NoResource => "nothing to normalize by".to_string(),
+            Reason::Other => "failed".to_string(),
+        };
+
+        // honor widths, e.g. `{:<20}`
+        f.pad(&reason)
+    }
+}
+
+impl Reason {
+    /// classify an error of a lookup
+    pub fn of(err: &Error) -> Self {
+        match err {
+            err if err.is_throttling() => Reason::Throttled,
+            Error::NotOffered(_) | Error::MissingTerm { .. } => Reason::NotOffered,
+            Error::AmbiguousProduct { count, .. } => Reason::NotUnique(*count),
+            Error::MalformedPriceList(_) | Error::MalformedResponse(_) | Error::Json(_) => {
+                Reason::Parse
+            }
+            _ => Reason::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// a missing price of an instance, in a region if known
+pub struct Diagnostic {
+    region: Option<String>,
+    instance: String,
+    reason: Reason,
+    detail: String,
+}
+
+impl Diagnostic {
+    /// the region of the missing price
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// the instance type of the missing price
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    /// why the price is missing
+    pub fn reason(&self) -> Reason {
+        self.reason
+    }
+
+    /// the underlying error
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
+
+#[derive(Debug, Default)]
+/// collects why cells of a report are empty
+pub struct Diagnostics {
+    entries: Mutex<Vec<Diagnostic>>,
+}
+
+impl Diagnostics {
+    /// create an empty collector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// record a missing price with its reason
+    pub fn record(&self, region: Option<&str>, instance: &str, reason: Reason, detail: &str) {
+        self.entries.lock().unwrap().push(Diagnostic {
+            region: region.map(|r| r.to_string()),
+            instance: instance.to_string(),
+            reason,
+            detail: detail.to_string(),
+        });
+    }
+
+    /// record a failed lookup, classified by its error
+    pub fn record_error(&self, region: Option<&str>, instance: &str, err: &Error) {
+        self.record(region, instance, Reason::of(err), &err.to_string());
+    }
+
+    /// all missing prices, ordered by region and instance
+    pub fn entries(&self) -> Vec<Diagnostic> {
+        let mut entries = self.entries.lock().unwrap().clone();
+        entries.sort_by(|a, b| (&a.region, &a.instance).cmp(&(&b.region, &b.instance)));
+        entries
+    }
+
+    /// whether nothing is missing
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    /// a one line footnote counting the missing prices by reason
+    pub fn footnote(&self) -> String {
+        let entries = self.entries();
+        if entries.is_empty() {
+            return String::new();
+        }
+
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for entry in &entries {
+            *counts.entry(entry.reason.to_string()).or_default() += 1;
+        }
+
+        let counts = counts
+            .iter()
+            .map(|(reason, count)| format!("{count} {reason}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!(
+            "{} prices missing: {counts}; run with --explain for details\n",
+            entries.len()
+        )
+    }
+
+    /// every missing price with its reason and error
+    pub fn explain(&self) -> String {
+        let entries = self.entries();
+        if entries.is_empty() {
+            return String::new();
+        }
+
+        let mut report = format!("{} prices missing:\n", entries.len());
+        for entry in &entries {
+            report.push_str(&format!(
+                "  {:<16} {:<20} {:<36} {}\n",
+                entry.region.as_deref().unwrap_or("-"),
+                entry.instance,
+                entry.reason,
+                entry.detail
+            ));
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_reason() {
+        let err = Error::AmbiguousProduct {
+            instance: "c7g.16xlarge".to_string(),
+            count: 2,
+        };
+        assert_eq!(Reason::of(&err), Reason::NotUnique(2));
+
+        let err = Error::sdk(Some("RequestLimitExceeded"), "service error");
+        assert_eq!(Reason::of(&err), Reason::Throttled);
+
+        let err = Error::MalformedResponse("spot price x".to_string());
+        assert_eq!(Reason::of(&err), Reason::Parse);
+
+        let err = Error::sdk(Some("UnauthorizedOperation"), "service error");
+        assert_eq!(Reason::of(&err), Reason::Other);
+    }
+
+    #[test]
+    fn test_footnote() {
+        let diagnostics = Diagnostics::new();
+        assert_eq!(diagnostics.footnote(), "");
+
+        diagnostics.record(
+            Some("us-east-2"),
+            "hpc7g.16xlarge",
+            Reason::NotOffered,
+            "no product",
+        );
+        diagnostics.record(
+            Some("eu-north-1"),
+            "c7g.16xlarge",
+            Reason::NoSpotHistory,
+            "",
+        );
+        diagnostics.record(Some("us-west-2"), "hpc7g.16xlarge", Reason::NotOffered, "");
+
+        assert_eq!(
+            diagnostics.footnote(),
+            "3 prices missing: 1 no spot history, 2 not offered in region; run with --explain for details\n"
+        );
+        assert_eq!(diagnostics.entries()[0].region(), Some("eu-north-1"));
+    }
+}
@@ -1,24 +1,77 @@
-use anyhow::Result;
+use crate::error::{Error, Result};
+use crate::get_region_config;
+use crate::provider::{
+    Ec2Filter, InstanceCatalog, SpotPriceHistory, TimeWindow, Zone, ZoneCatalog,
+};
 use aws_sdk_ec2::client;
+use aws_sdk_ec2::primitives::DateTime;
 use aws_sdk_ec2::types::Filter;
+use aws_sdk_ec2::types::InstanceType;
 use aws_sdk_ec2::types::InstanceTypeInfo;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
 
 #[derive(Debug)]
 /// a wrapper around an AWS EC2 client
-pub struct Ec2(aws_sdk_ec2::client::Client);
+pub struct Ec2 {
+    client: client::Client,
+    // clients for the other regions
+    regional_clients: Mutex<HashMap<String, client::Client>>,
+}
 
 impl Ec2 {
     /// create a wrapper around an AWS EC2 client
     pub fn new(client: client::Client) -> Self {
-        Ec2(client)
+        Ec2 {
+            client,
+            regional_clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// the client for a region
+    async fn client(&self, region: &str) -> client::Client {
+        if self.client.config().region().map(|r| r.as_ref()) == Some(region) {
+            return self.client.clone();
+        }
+
+        let cached = self.regional_clients.lock().await.get(region).cloned();
+        if let Some(client) = cached {
+            return client;
+        }
+
+        let config = get_region_config(region).await;
+        let client = client::Client::new(&config);
+
+        self.regional_clients
+            .lock()
+            .await
+            .insert(region.to_string(), client.clone());
+
+        client
     }
+}
+
+impl InstanceCatalog for Ec2 {
+    async fn describe_instance_types(
+        &self,
+        region: &str,
+        filters: &[Ec2Filter],
+    ) -> Result<Vec<InstanceTypeInfo>> {
+        let filters = filters
+            .iter()
+            .map(|filter| {
+                Filter::builder()
+                    .name(filter.name())
+                    .set_values(Some(filter.values().to_vec()))
+                    .build()
+            })
+            .collect::<Vec<_>>();
 
-    #[allow(unused)]
-    /// get all current types of instances
-    pub(crate) async fn get_instance_types(&self) -> Result<Vec<InstanceTypeInfo>> {
         let instances = self
-            .0
+            .client(region)
+            .await
             .describe_instance_types()
+            .set_filters(Some(filters))
             .into_paginator()
             .items()
             .send()
@@ -27,133 +80,100 @@ impl Ec2 {
 
         Ok(instances)
     }
+}
 
-    /// get all instances with EFA support
-    pub async fn get_instance_types_efa(&self) -> Result<Vec<InstanceTypeInfo>> {
-        let filters = vec![
-            Filter::builder()
-                .name("network-info.efa-supported")
-                .set_values(Some(vec!["true".to_string()]))
-                .build(),
-            Filter::builder()
-                .name("instance-storage-supported")
-                .set_values(Some(vec!["false".to_string()]))
-                .build(),
-            Filter::builder()
-                .name("bare-metal")
-                .set_values(Some(vec!["false".to_string()]))
-                .build(),
-        ];
-
-        let instances = self
-            .0
-            .describe_instance_types()
-            .set_filters(Some(filters.clone()))
+impl SpotPriceHistory for Ec2 {
+    async fn get_spot_price_history(
+        &self,
+        region: &str,
+        availability_zone: &str,
+        instance: &InstanceType,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> Result<Vec<(DateTime, f64)>> {
+        let prices = self
+            .client(region)
+            .await
+            .describe_spot_price_history()
+            .instance_types(instance.clone())
+            .product_descriptions(product_description)
+            .availability_zone(availability_zone)
+            .set_start_time(window.start())
+            .set_end_time(window.end())
             .into_paginator()
             .items()
             .send()
             .collect::<Result<Vec<_>, _>>()
             .await?;
 
-        Ok(instances)
+        let mut spot_prices = prices
+            .iter()
+            .filter_map(|price| Some((*price.timestamp()?, price.spot_price()?)))
+            .map(|(time, spot_price)| {
+                spot_price
+                    .parse::<f64>()
+                    .map(|spot_price| (time, spot_price))
+                    .map_err(|err| {
+                        Error::MalformedResponse(format!("spot price {spot_price}: {err}"))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // AWS returns the newest price first
+        spot_prices.sort_by_key(|(time, _)| *time);
+
+        Ok(spot_prices)
     }
+}
 
-    pub(crate) async fn get_regions(&self) -> Result<Vec<String>> {
-        //let result = self.0.describe_regions().all_regions(true).send().await?;
+impl ZoneCatalog for Ec2 {
+    async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+        let filter = Filter::builder().name("region-name").values(region).build();
 
         // no paging !
 
-        let _regions = vec![
-            "Africa (Cape Town)",
-            "Asia Pacific (Hong Kong)",
-            "Asia Pacific (Melbourne)",
-            "Europe (Zurich)",
-            "Europe (Milan)",
-            "Europe (Spain)",
-            "Israel (Tel Aviv)",
-            "Asia Pacific (Tokyo)",
-            "Asia Pacific (Seoul)",
-            "Asia Pacific (Osaka)",
-            "Asia Pacific (Mumbai)",
-            "Asia Pacific (Singapore)",
-            "Asia Pacific (Sydney)",
-            "Canada (Central",
-            "Europa (Central)",
-            "Europa (Frankfurt)",
-            "Europa (Stockholm)",
-            "Europa (Ireland)",
-            "Europa (London)",
-            "Europa (Paris)",
-            "South America (São Paulo)",
-            "US East (N. Virginia)",
-            "US East (Ohio)",
-            "US West (N. California)",
-            "US West (Oregon)",
-        ];
-
-        let codes: Vec<String> = vec![
-            "af-south-1".to_string(),
-            "ap-east-1".to_string(),
-            "eu-central-2".to_string(),
-            "eu-south-1".to_string(),
-            //"eu-south-2".to_string(),
-            "il-central-1".to_string(),
-            "ap-northeast-1".to_string(),
-            //"ap-northeast-4".to_string(),
-            "ap-northeast-3".to_string(),
-            "ap-south-1".to_string(),
-            "ap-southeast-1".to_string(),
-            "ap-southeast-2".to_string(),
-            "ca-central-1".to_string(),
-            //            "ca-west-1".to_string(),
-            "eu-central-1".to_string(),
-            "eu-north-1".to_string(),
-            "eu-west-1".to_string(),
-            "eu-west-2".to_string(),
-            "eu-west-3".to_string(),
-            "sa-east-1".to_string(),
-            "us-east-1".to_string(),
-            "us-east-2".to_string(),
-            "us-west-1".to_string(),
-            "us-west-2".to_string(),
-        ];
-
-        Ok(codes)
-
-        //        Ok(result
-        //            .regions
-        //            .unwrap_or_default()
-        //            .into_iter()
-        //            .filter(|region| {
-        //                region.opt_in_status().as_ref().unwrap_or(&"") == &"opt-in-not-required"
-        //            })
-        //            .filter(|region| region.region_name().is_some())
-        //            .map(|r| r.region_name().unwrap().to_string())
-        //            .collect::<Vec<_>>())
+        let result = self
+            .client(region)
+            .await
+            .describe_availability_zones()
+            .filters(filter)
+            .send()
+            .await?;
+
+        Ok(result
+            .availability_zones
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|ava| ava.zone_name().map(|zone| Zone::new(zone, ava.zone_id())))
+            .collect::<Vec<_>>())
     }
 
-    //    #[allow(unused)]
-    //    async fn get_max_spot_price_spread(
-    //        &self,
-    //        zone: &str,
-    //        instances: &[InstanceType],
-    //    ) -> Result<Vec<(String, f64)>> {
-    //        let mut spread = Vec::<(String, f64)>::new();
-    //
-    //        for instance in instances {
-    //            let mut prices = self.get_spot_price_history(instance.clone(), zone).await?;
-    //            prices.sort_by(|a, b| a.partial_cmp(b).unwrap());
-    //            if !prices.is_empty() {
-    //                let min = prices.first().unwrap();
-    //                let max = prices.last().unwrap();
-    //                spread.push((instance.as_str().to_string(), max.1 / min.1));
-    //            }
-    //        }
-    //
-    //        spread.sort_by(|(_i1, s1), (_i2, s2)| s1.partial_cmp(s2).unwrap());
-    //
-    //        let (_, second) = spread.split_at(min(spread.len() - 10, spread.len()));
-    //
-    //        Ok(second.to_vec())
-    //    }
+    async fn get_regions(&self) -> Result<Vec<String>> {
+        // no paging !
+
+        let result = self
+            .client
+            .describe_regions()
+            .all_regions(true)
+            .send()
+            .await?;
+
+        // regions which are not enabled for the account have no prices
+        let mut regions = result
+            .regions()
+            .iter()
+            .filter(|region| {
+                matches!(
+                    region.opt_in_status(),
+                    Some("opt-in-not-required" | "opted-in")
+                )
+            })
+            .filter_map(|region| region.region_name())
+            .map(|region| region.to_string())
+            .collect::<Vec<_>>();
+
+        regions.sort();
+
+        Ok(regions)
+    }
 }
@@ -0,0 +1,142 @@
+use aws_sdk_ec2::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use std::fmt::Debug;
+
+// the error codes of EC2 and Pricing when a client is too fast
+const THROTTLING_CODES: &[&str] = &[
+    "RequestLimitExceeded",
+    "ThrottlingException",
+    "Throttling",
+    "TooManyRequestsException",
+];
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+/// the errors of the analyzer
+pub enum Error {
+    /// an EC2 or Pricing API call failed
+    #[error("AWS API error: {message}")]
+    Sdk {
+        /// the AWS error code, e.g. `RequestLimitExceeded`
+        code: Option<String>,
+        /// the error with all of its causes
+        message: String,
+        /// the SDK error, none for errors without one, e.g. of tests
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+    /// the instance type has no product or description, e.g. in a region
+    #[error("not offered: {0}")]
+    NotOffered(String),
+    /// more than one pricing product matches the filters
+    #[error("{count} pricing products for {instance}")]
+    AmbiguousProduct {
+        /// the instance type
+        instance: String,
+        /// the number of matching products
+        count: usize,
+    },
+    /// the product has no unique term of the kind, e.g. `OnDemand`
+    #[error("no {term} term for {instance}")]
+    MissingTerm {
+        /// the instance type
+        instance: String,
+        /// the kind of term
+        term: String,
+    },
+    /// the region is not enabled for the account
+    #[error("unknown region: {0}")]
+    UnknownRegion(String),
+    /// a price list document does not have the expected shape
+    #[error("malformed price list: {0}")]
+    MalformedPriceList(String),
+    /// an EC2 response does not have the expected shape
+    #[error("malformed response: {0}")]
+    MalformedResponse(String),
+    /// a profile or its config file is invalid
+    #[error("invalid config: {0}")]
+    Config(String),
+    /// a recording lacks a response or does not match the request
+    #[error("replay: {0}")]
+    Replay(String),
+    /// downloading a price list offer file failed
+    #[error("download failed: {0}")]
+    Download(String),
+    /// the operation is not supported, e.g. an output format
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+    /// reading or writing a file failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// a stored JSON document is invalid
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// the spot price database failed
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    /// writing a report failed
+    #[error(transparent)]
+    Fmt(#[from] std::fmt::Error),
+    /// writing a Parquet file failed
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+}
+
+/// the result of the analyzer
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// an API error with an AWS error code, e.g. `RequestLimitExceeded`
+    pub fn sdk(code: Option<&str>, message: &str) -> Self {
+        Error::Sdk {
+            code: code.map(|c| c.to_string()),
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    /// whether AWS rejected the call because of too many requests
+    pub fn is_throttling(&self) -> bool {
+        matches!(self, Error::Sdk { code: Some(code), .. } if THROTTLING_CODES.contains(&code.as_str()))
+    }
+}
+
+impl<E, R> From<SdkError<E, R>> for Error
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+    R: Debug + Send + Sync + 'static,
+{
+    fn from(err: SdkError<E, R>) -> Self {
+        Error::Sdk {
+            code: err.code().map(|c| c.to_string()),
+            message: DisplayErrorContext(&err).to_string(),
+            source: Some(Box::new(err)),
+        }
+    }
+}
+
+impl From<aws_sdk_ec2::error::BuildError> for Error {
+    fn from(err: aws_sdk_ec2::error::BuildError) -> Self {
+        Error::Sdk {
+            code: None,
+            message: err.to_string(),
+            source: Some(Box::new(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aws_sdk_ec2::error::BuildError;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_source() {
+        let err = Error::from(BuildError::missing_field("instance_type", "required"));
+        assert!(err.source().is_some());
+
+        assert!(Error::sdk(None, "service error").source().is_none());
+    }
+}
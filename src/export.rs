@@ -0,0 +1,500 @@
+use crate::diagnostics::{Diagnostics, Reason};
+use crate::error::{Error, Result};
+use crate::output::{OutputFormat, Table};
+use crate::pricing::PriceQuery;
+use crate::provider::{SpotPriceHistory, TimeWindow, Zone, ZoneCatalog};
+use aws_sdk_ec2::primitives::{DateTime, DateTimeFormat};
+use aws_sdk_ec2::types::InstanceType;
+use futures::future::{join_all, try_join_all};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+const HEADER: &[&str] = &[
+    "region",
+    "availability_zone",
+    "zone_id",
+    "instance_type",
+    "time",
+    "price",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// how spot price series are exported
+pub enum ExportFormat {
+    /// comma-separated values with a header
+    #[default]
+    Csv,
+    /// one JSON object per line
+    Jsonl,
+    /// an Apache Parquet file, needs the `parquet` feature
+    Parquet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(format!(
+                "unknown export format: {s}, expected csv, jsonl or parquet"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Csv => f.pad("csv"),
+            ExportFormat::Jsonl => f.pad("jsonl"),
+            ExportFormat::Parquet => f.pad("parquet"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// the fixed interval spot prices are resampled to
+pub enum Interval {
+    /// at every full hour
+    Hourly,
+    /// at every midnight UTC
+    Daily,
+}
+
+impl Interval {
+    /// the length in seconds
+    pub fn secs(&self) -> i64 {
+        match self {
+            Interval::Hourly => 60 * 60,
+            Interval::Daily => 24 * 60 * 60,
+        }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hourly" | "1h" => Ok(Interval::Hourly),
+            "daily" | "1d" => Ok(Interval::Daily),
+            _ => Err(format!("unknown interval: {s}, expected hourly or daily")),
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interval::Hourly => f.pad("hourly"),
+            Interval::Daily => f.pad("daily"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+// a spot price of an instance in an availability zone, in effect from `time`
+struct SpotPoint {
+    region: String,
+    availability_zone: String,
+    zone_id: Option<String>,
+    instance_type: String,
+    #[serde(serialize_with = "serialize_time")]
+    time: DateTime,
+    price: f64,
+}
+
+fn serialize_time<S: Serializer>(time: &DateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_time(time))
+}
+
+// e.g. `2024-06-01T00:00:00Z`
+fn format_time(time: &DateTime) -> String {
+    time.fmt(DateTimeFormat::DateTime)
+        .unwrap_or_else(|_| time.secs().to_string())
+}
+
+/// the prices of a step function at every multiple of the interval from `start` to `end`
+///
+/// Each price holds until the next one (forward fill). Times before the first price
+/// of the history have no price and are left out.
+pub fn resample(
+    history: &[(DateTime, f64)],
+    start: Option<DateTime>,
+    end: DateTime,
+    interval: Interval,
+) -> Vec<(DateTime, f64)> {
+    let Some((first, _)) = history.first() else {
+        return Vec::new();
+    };
+    let step = interval.secs();
+    let from = start.map_or(first.secs(), |start| start.secs().max(first.secs()));
+
+    let mut resampled = Vec::new();
+    let mut prices = history.iter().peekable();
+    let mut price = None;
+
+    // the first multiple of the interval at or after the start
+    let mut time = from.div_euclid(step) * step;
+    if time < from {
+        time += step;
+    }
+    while time <= end.secs() {
+        while let Some((_, next)) = prices.next_if(|(at, _)| at.secs() <= time) {
+            price = Some(*next);
+        }
+        if let Some(price) = price {
+            resampled.push((DateTime::from_secs(time), price));
+        }
+        time += step;
+    }
+
+    resampled
+}
+
+// the spot prices of all instances in all zones of the regions, by region, instance and zone
+async fn collect<E: ZoneCatalog + SpotPriceHistory>(
+    ec2: &E,
+    instances: &[InstanceType],
+    region_names: &[String],
+    query: &PriceQuery,
+    window: &TimeWindow,
+    interval: Option<Interval>,
+    diagnostics: &Diagnostics,
+) -> Result<Vec<SpotPoint>> {
+    let product_description = query.spot_product_description();
+    // the last price holds until the end of the window, or until now
+    let end = window
+        .end()
+        .unwrap_or_else(|| DateTime::from(SystemTime::now()));
+
+    // all regions at once, the providers bound the concurrent API calls
+    let regions = try_join_all(region_names.iter().map(|region| async move {
+        let zones = match ec2.get_zones(region).await {
+            Ok(zones) => zones,
+            Err(err) => {
+                for instance in instances {
+                    diagnostics.record_error(Some(region), instance.as_str(), &err);
+                }
+                return Ok::<_, Error>(Vec::new());
+            }
+        };
+
+        let histories = join_all(instances.iter().map(|instance| {
+            try_join_all(zones.iter().map(|zone| {
+                ec2.get_spot_price_history(
+                    region,
+                    zone.name(),
+                    instance,
+                    product_description,
+                    window,
+                )
+            }))
+        }))
+        .await;
+
+        let mut points = Vec::new();
+        for (instance, histories) in instances.iter().zip(histories) {
+            let histories = match histories {
+                Ok(histories) => histories,
+                Err(err) => {
+                    diagnostics.record_error(Some(region), instance.as_str(), &err);
+                    continue;
+                }
+            };
+            if histories.iter().all(Vec::is_empty) {
+                diagnostics.record(
+                    Some(region),
+                    instance.as_str(),
+                    Reason::NoSpotHistory,
+                    &format!("no spot prices in {} zones", zones.len()),
+                );
+            }
+
+            for (zone, history) in zones.iter().zip(histories) {
+                let history = match interval {
+                    Some(interval) => resample(&history, window.start(), end, interval),
+                    None => history,
+                };
+                points.extend(
+                    history
+                        .into_iter()
+                        .map(|(time, price)| point(region, zone, instance, time, price)),
+                );
+            }
+        }
+
+        Ok(points)
+    }))
+    .await?;
+
+    Ok(regions.into_iter().flatten().collect())
+}
+
+fn point(
+    region: &str,
+    zone: &Zone,
+    instance: &InstanceType,
+    time: DateTime,
+    price: f64,
+) -> SpotPoint {
+    SpotPoint {
+        region: region.to_string(),
+        availability_zone: zone.name().to_string(),
+        zone_id: zone.id().map(|id| id.to_string()),
+        instance_type: instance.as_str().to_string(),
+        time,
+        price,
+    }
+}
+
+fn to_csv(points: &[SpotPoint]) -> Result<String> {
+    let mut table = Table::new(HEADER);
+    for point in points {
+        table.push(vec![
+            point.region.clone(),
+            point.availability_zone.clone(),
+            point.zone_id.clone().unwrap_or_default(),
+            point.instance_type.clone(),
+            format_time(&point.time),
+            point.price.to_string(),
+        ]);
+    }
+
+    table.render(OutputFormat::Csv)
+}
+
+fn to_jsonl(points: &[SpotPoint]) -> Result<String> {
+    let mut lines = String::new();
+    for point in points {
+        lines.push_str(&serde_json::to_string(point)?);
+        lines.push('\n');
+    }
+
+    Ok(lines)
+}
+
+#[cfg(feature = "parquet")]
+fn to_parquet(points: &[SpotPoint]) -> Result<Vec<u8>> {
+    use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, TimestampSecondArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use parquet::arrow::ArrowWriter;
+    use parquet::errors::ParquetError;
+    use std::sync::Arc;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("region", DataType::Utf8, false),
+        Field::new("availability_zone", DataType::Utf8, false),
+        Field::new("zone_id", DataType::Utf8, true),
+        Field::new("instance_type", DataType::Utf8, false),
+        Field::new(
+            "time",
+            DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            false,
+        ),
+        Field::new("price", DataType::Float64, false),
+    ]));
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            points.iter().map(|point| point.region.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            points.iter().map(|point| point.availability_zone.as_str()),
+        )),
+        Arc::new(StringArray::from(
+            points
+                .iter()
+                .map(|point| point.zone_id.as_deref())
+                .collect::<Vec<_>>(),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            points.iter().map(|point| point.instance_type.as_str()),
+        )),
+        Arc::new(
+            TimestampSecondArray::from_iter_values(points.iter().map(|point| point.time.secs()))
+                .with_timezone("UTC"),
+        ),
+        Arc::new(Float64Array::from_iter_values(
+            points.iter().map(|point| point.price),
+        )),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns).map_err(ParquetError::from)?;
+
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(buffer)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn to_parquet(_points: &[SpotPoint]) -> Result<Vec<u8>> {
+    Err(Error::Unsupported(
+        "Parquet export, build with `--features parquet`".to_string(),
+    ))
+}
+
+/// export the spot price history of instances in every zone of the regions
+///
+/// Without an interval every price change is a row, with its time and price. With an
+/// interval the prices are resampled to its multiples with forward fill. Writes to `out`
+/// or stdout and returns why series are missing.
+#[allow(clippy::too_many_arguments)]
+pub async fn export_spot_prices<E: ZoneCatalog + SpotPriceHistory>(
+    ec2: &E,
+    instances: &[InstanceType],
+    region_names: &[String],
+    query: &PriceQuery,
+    window: &TimeWindow,
+    interval: Option<Interval>,
+    format: ExportFormat,
+    out: Option<&Path>,
+) -> Result<Diagnostics> {
+    let diagnostics = Diagnostics::new();
+    let points = collect(
+        ec2,
+        instances,
+        region_names,
+        query,
+        window,
+        interval,
+        &diagnostics,
+    )
+    .await?;
+
+    let bytes = match format {
+        ExportFormat::Csv => to_csv(&points)?.into_bytes(),
+        ExportFormat::Jsonl => to_jsonl(&points)?.into_bytes(),
+        ExportFormat::Parquet => to_parquet(&points)?,
+    };
+
+    match out {
+        Some(path) => std::fs::write(path, bytes)?,
+        None => std::io::stdout().write_all(&bytes)?,
+    }
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use pretty_assertions::assert_eq;
+
+    fn history(prices: &[(i64, f64)]) -> Vec<(DateTime, f64)> {
+        prices
+            .iter()
+            .map(|&(secs, price)| (DateTime::from_secs(secs), price))
+            .collect()
+    }
+
+    #[test]
+    fn test_resample() {
+        // 1.0 from 00:30, 2.0 from 02:00 and 3.0 from 02:10
+        let history = history(&[(1800, 1.0), (7200, 2.0), (7800, 3.0)]);
+
+        assert_eq!(
+            resample(&history, None, DateTime::from_secs(14400), Interval::Hourly),
+            [(3600, 1.0), (7200, 2.0), (10800, 3.0), (14400, 3.0)]
+                .map(|(secs, price)| (DateTime::from_secs(secs), price))
+        );
+
+        // the price in effect at the start of the window
+        assert_eq!(
+            resample(
+                &history,
+                Some(DateTime::from_secs(7000)),
+                DateTime::from_secs(9000),
+                Interval::Hourly
+            ),
+            [(DateTime::from_secs(7200), 2.0)]
+        );
+
+        assert_eq!(
+            resample(&history, None, DateTime::from_secs(86400), Interval::Daily),
+            [(DateTime::from_secs(86400), 3.0)]
+        );
+        assert!(resample(&[], None, DateTime::from_secs(86400), Interval::Daily).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collect() -> Result<()> {
+        let fixture = Fixture::new()
+            .with_zone_ids(
+                "us-east-2",
+                &[("us-east-2a", "use2-az1"), ("us-east-2b", "use2-az2")],
+            )
+            .with_spot_prices(
+                "us-east-2",
+                "us-east-2a",
+                "c7g.16xlarge",
+                &[(0, 1.0), (5400, 1.5)],
+            );
+        let instances = [
+            InstanceType::from("c7g.16xlarge"),
+            InstanceType::from("c7g.8xlarge"),
+        ];
+        let window = TimeWindow::new(None, Some(DateTime::from_secs(7200)));
+
+        let diagnostics = Diagnostics::new();
+        let points = collect(
+            &fixture,
+            &instances,
+            &["us-east-2".to_string()],
+            &PriceQuery::default(),
+            &window,
+            None,
+            &diagnostics,
+        )
+        .await?;
+        assert_eq!(
+            to_csv(&points)?,
+            "region,availability_zone,zone_id,instance_type,time,price\n\
+             us-east-2,us-east-2a,use2-az1,c7g.16xlarge,1970-01-01T00:00:00Z,1\n\
+             us-east-2,us-east-2a,use2-az1,c7g.16xlarge,1970-01-01T01:30:00Z,1.5\n"
+        );
+        assert_eq!(diagnostics.entries()[0].instance(), "c7g.8xlarge");
+
+        let points = collect(
+            &fixture,
+            &instances,
+            &["us-east-2".to_string()],
+            &PriceQuery::default(),
+            &window,
+            Some(Interval::Hourly),
+            &diagnostics,
+        )
+        .await?;
+        assert_eq!(
+            to_jsonl(&points)?,
+            "{\"region\":\"us-east-2\",\"availability_zone\":\"us-east-2a\",\"zone_id\":\"use2-az1\",\
+             \"instance_type\":\"c7g.16xlarge\",\"time\":\"1970-01-01T00:00:00Z\",\"price\":1.0}\n\
+             {\"region\":\"us-east-2\",\"availability_zone\":\"us-east-2a\",\"zone_id\":\"use2-az1\",\
+             \"instance_type\":\"c7g.16xlarge\",\"time\":\"1970-01-01T01:00:00Z\",\"price\":1.0}\n\
+             {\"region\":\"us-east-2\",\"availability_zone\":\"us-east-2a\",\"zone_id\":\"use2-az1\",\
+             \"instance_type\":\"c7g.16xlarge\",\"time\":\"1970-01-01T02:00:00Z\",\"price\":1.5}\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("jsonl".parse::<ExportFormat>(), Ok(ExportFormat::Jsonl));
+        assert_eq!(ExportFormat::Parquet.to_string(), "parquet");
+        assert_eq!("1d".parse::<Interval>(), Ok(Interval::Daily));
+        assert_eq!(Interval::Hourly.to_string(), "hourly");
+        assert!("weekly".parse::<Interval>().is_err());
+    }
+}
@@ -0,0 +1,390 @@
+use crate::error::{Error, Result};
+use crate::pricing::OperatingSystem;
+use crate::provider::{
+    Ec2Filter, InstanceCatalog, ProductFilter, ProductPrices, SpotPriceHistory, TimeWindow, Zone,
+    ZoneCatalog,
+};
+use crate::savings_plans::{SavingsPlanFilter, SavingsPlanRate};
+use aws_sdk_ec2::primitives::DateTime;
+use aws_sdk_ec2::types::InstanceType;
+use aws_sdk_ec2::types::{
+    ArchitectureType, EbsInfo, EbsOptimizedInfo, InstanceTypeInfo, MemoryInfo, NetworkInfo,
+    ProcessorInfo, VCpuInfo,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone)]
+/// an in-memory replacement for the EC2 and Pricing APIs
+pub struct Fixture {
+    regions: Vec<String>,
+    zones: HashMap<String, Vec<Zone>>,
+    instance_types: HashMap<String, Vec<InstanceTypeInfo>>,
+    spot_prices: HashMap<(String, String, String, String), Vec<(DateTime, f64)>>,
+    products: Vec<String>,
+    savings_plan_rates: HashMap<(String, String), Vec<SavingsPlanRate>>,
+}
+
+impl Fixture {
+    /// create an empty fixture
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a region with its availability zones
+    pub fn with_zones(self, region: &str, zones: &[&str]) -> Self {
+        let zones = zones
+            .iter()
+            .map(|name| Zone::new(name, None))
+            .collect::<Vec<_>>();
+        self.with_zone_list(region, zones)
+    }
+
+    /// add a region with its availability zones and their zone IDs, e.g. `use2-az1`
+    pub fn with_zone_ids(self, region: &str, zones: &[(&str, &str)]) -> Self {
+        let zones = zones
+            .iter()
+            .map(|(name, id)| Zone::new(name, Some(id)))
+            .collect::<Vec<_>>();
+        self.with_zone_list(region, zones)
+    }
+
+    fn with_zone_list(mut self, region: &str, zones: Vec<Zone>) -> Self {
+        if !self.regions.iter().any(|r| r == region) {
+            self.regions.push(region.to_string());
+        }
+        self.zones.insert(region.to_string(), zones);
+        self
+    }
+
+    /// offer an instance type in a region
+    pub fn with_instance_type(mut self, region: &str, info: InstanceTypeInfo) -> Self {
+        self.instance_types
+            .entry(region.to_string())
+            .or_default()
+            .push(info);
+        self
+    }
+
+    /// offer an arm64 instance type with as many cores as vCPUs and 2 GiB of memory per vCPU
+    pub fn with_instance(self, region: &str, instance: &str, vcpus: i32) -> Self {
+        let info = InstanceTypeInfo::builder()
+            .instance_type(InstanceType::from(instance))
+            .processor_info(
+                ProcessorInfo::builder()
+                    .supported_architectures(ArchitectureType::Arm64)
+                    .build(),
+            )
+            .v_cpu_info(
+                VCpuInfo::builder()
+                    .default_v_cpus(vcpus)
+                    .default_cores(vcpus)
+                    .build(),
+            )
+            .memory_info(
+                MemoryInfo::builder()
+                    .size_in_mib(i64::from(vcpus) * 2048)
+                    .build(),
+            )
+            .network_info(
+                NetworkInfo::builder()
+                    .network_performance("30 Gigabit")
+                    .build(),
+            )
+            .ebs_info(
+                EbsInfo::builder()
+                    .ebs_optimized_info(
+                        EbsOptimizedInfo::builder()
+                            .maximum_bandwidth_in_mbps(20000)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        self.with_instance_type(region, info)
+    }
+
+    /// add the Linux spot prices (seconds since the epoch, USD) of an instance in an availability zone
+    pub fn with_spot_prices(
+        self,
+        region: &str,
+        availability_zone: &str,
+        instance: &str,
+        prices: &[(i64, f64)],
+    ) -> Self {
+        let linux = OperatingSystem::Linux.spot_product_description();
+        self.with_product_spot_prices(linux, region, availability_zone, instance, prices)
+    }
+
+    /// add the spot prices of an operating system, e.g. `Windows`
+    pub fn with_product_spot_prices(
+        mut self,
+        product_description: &str,
+        region: &str,
+        availability_zone: &str,
+        instance: &str,
+        prices: &[(i64, f64)],
+    ) -> Self {
+        let mut prices = prices
+            .iter()
+            .map(|(secs, price)| (DateTime::from_secs(*secs), *price))
+            .collect::<Vec<_>>();
+        prices.sort_by_key(|(time, _)| *time);
+
+        self.spot_prices.insert(
+            (
+                region.to_string(),
+                availability_zone.to_string(),
+                instance.to_string(),
+                product_description.to_string(),
+            ),
+            prices,
+        );
+        self
+    }
+
+    /// add a price list document (JSON)
+    pub fn with_product(mut self, product: &str) -> Self {
+        self.products.push(product.to_string());
+        self
+    }
+
+    /// add the Linux on-demand price of an instance in a region
+    pub fn with_ondemand_price(self, instance: &str, region: &str, price: f64) -> Self {
+        let product = json!({
+            "product": {
+                "productFamily": "Compute Instance",
+                "attributes": {
+                    "instanceType": instance,
+                    "regionCode": region,
+                    "operatingSystem": "Linux",
+                    "preInstalledSw": "NA",
+                    "licenseModel": "No License required",
+                    "operation": "RunInstances",
+                    "capacitystatus": "UnusedCapacityReservation",
+                    "tenancy": "Shared",
+                },
+                "sku": "FIXTURE",
+            },
+            "serviceCode": "AmazonEC2",
+            "terms": {
+                "OnDemand": {
+                    "FIXTURE.JRTCKXETXF": {
+                        "priceDimensions": {
+                            "FIXTURE.JRTCKXETXF.6YS6EN2CT7": {
+                                "unit": "Hrs",
+                                "pricePerUnit": { "USD": format!("{price:.10}") },
+                            }
+                        },
+                        "sku": "FIXTURE",
+                        "offerTermCode": "JRTCKXETXF",
+                        "termAttributes": {},
+                    }
+                }
+            },
+        });
+
+        self.with_product(&product.to_string())
+    }
+
+    /// add the Linux Savings Plan rate of an instance in a region
+    pub fn with_savings_plan_rate(
+        mut self,
+        instance: &str,
+        region: &str,
+        rate: SavingsPlanRate,
+    ) -> Self {
+        self.savings_plan_rates
+            .entry((instance.to_string(), region.to_string()))
+            .or_default()
+            .push(rate);
+        self
+    }
+}
+
+impl InstanceCatalog for Fixture {
+    async fn describe_instance_types(
+        &self,
+        region: &str,
+        filters: &[Ec2Filter],
+    ) -> Result<Vec<InstanceTypeInfo>> {
+        let mut result = Vec::new();
+
+        for info in self.instance_types.get(region).into_iter().flatten() {
+            let mut matches = true;
+            for filter in filters {
+                matches &= matches_ec2_filter(info, filter)?;
+            }
+            if matches {
+                result.push(info.clone());
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+fn matches_ec2_filter(info: &InstanceTypeInfo, filter: &Ec2Filter) -> Result<bool> {
+    let value = match filter.name() {
+        "instance-type" => info.instance_type().map(|i| i.as_str().to_string()),
+        "network-info.efa-supported" => info
+            .network_info()
+            .and_then(|n| n.efa_supported())
+            .map(|b| b.to_string()),
+        "instance-storage-supported" => info.instance_storage_supported().map(|b| b.to_string()),
+        "bare-metal" => info.bare_metal().map(|b| b.to_string()),
+        name => return Err(Error::Unsupported(format!("filter {name}"))),
+    };
+
+    let Some(value) = value else {
+        return Ok(false);
+    };
+
+    // only trailing wildcards, e.g. `c7g.*`
+    Ok(filter
+        .values()
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => value.starts_with(prefix),
+            None => value == *pattern,
+        }))
+}
+
+impl SpotPriceHistory for Fixture {
+    async fn get_spot_price_history(
+        &self,
+        region: &str,
+        availability_zone: &str,
+        instance: &InstanceType,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> Result<Vec<(DateTime, f64)>> {
+        let key = (
+            region.to_string(),
+            availability_zone.to_string(),
+            instance.as_str().to_string(),
+            product_description.to_string(),
+        );
+
+        Ok(self
+            .spot_prices
+            .get(&key)
+            .map(|prices| window.clip(prices))
+            .unwrap_or_default())
+    }
+}
+
+impl ZoneCatalog for Fixture {
+    async fn get_regions(&self) -> Result<Vec<String>> {
+        Ok(self.regions.clone())
+    }
+
+    async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+        Ok(self.zones.get(region).cloned().unwrap_or_default())
+    }
+}
+
+impl ProductPrices for Fixture {
+    async fn get_products(&self, filters: &[ProductFilter]) -> Result<Vec<String>> {
+        let mut result = Vec::new();
+
+        for product in &self.products {
+            let v: Value = serde_json::from_str(product)?;
+            if filters
+                .iter()
+                .all(|filter| matches_product_filter(&v, filter))
+            {
+                result.push(product.clone());
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn get_savings_plan_rates(
+        &self,
+        filter: &SavingsPlanFilter,
+    ) -> Result<Vec<SavingsPlanRate>> {
+        let key = (filter.instance().to_string(), filter.region().to_string());
+        Ok(self
+            .savings_plan_rates
+            .get(&key)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+// a filter matches a product attribute or a term attribute of any term
+fn matches_product_filter(product: &Value, filter: &ProductFilter) -> bool {
+    let value = Value::from(filter.value());
+
+    if product["product"]["attributes"][filter.field()] == value {
+        return true;
+    }
+
+    product["terms"]
+        .as_object()
+        .into_iter()
+        .flat_map(|kinds| kinds.values())
+        .filter_map(|terms| terms.as_object())
+        .flat_map(|terms| terms.values())
+        .any(|term| term["termAttributes"][filter.field()] == value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn info(instance: &str, efa: bool) -> InstanceTypeInfo {
+        InstanceTypeInfo::builder()
+            .instance_type(InstanceType::from(instance))
+            .bare_metal(false)
+            .instance_storage_supported(false)
+            .network_info(NetworkInfo::builder().efa_supported(efa).build())
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_describe_instance_types() -> Result<()> {
+        let fixture = Fixture::new()
+            .with_instance_type("us-east-2", info("c7g.16xlarge", false))
+            .with_instance_type("us-east-2", info("c7gn.16xlarge", true))
+            .with_instance_type("us-east-2", info("hpc7g.16xlarge", true));
+
+        let family = fixture
+            .get_instance_types_of_family("us-east-2", "c7g")
+            .await?;
+        assert_eq!(family, vec![InstanceType::from("c7g.16xlarge")]);
+
+        let efa = fixture.get_instance_types_efa("us-east-2").await?;
+        assert_eq!(efa.len(), 2);
+
+        assert!(fixture
+            .describe_instance("us-east-1", &InstanceType::from("c7g.16xlarge"))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ondemand_price() -> Result<()> {
+        let fixture = Fixture::new()
+            .with_ondemand_price("c7g.16xlarge", "us-east-2", 2.312)
+            .with_ondemand_price("c7g.16xlarge", "us-west-2", 2.5);
+
+        let price = fixture
+            .get_ondemand_price("c7g.16xlarge", "us-east-2")
+            .await?;
+        assert_eq!(price, 2.312);
+
+        assert!(fixture
+            .get_ondemand_price("c7g.16xlarge", "eu-west-1")
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
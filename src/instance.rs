@@ -1,62 +1,72 @@
 use crate::availability_zone::AvailabilityZone;
+use crate::statistics::{Metric, SpotStatistics};
 use aws_sdk_ec2::types::InstanceType;
 
+#[derive(Clone)]
 pub(super) struct Instance {
     _region: String,
-    _instance: InstanceType,
-    spot_prices: Option<MinMax>,
+    instance: InstanceType,
+    zones: Vec<AvailabilityZone>,
+    spot_prices: Option<SpotStatistics>,
     ondemand_price: f64,
+    savings_plan_rate: Option<f64>,
+    // the units all prices are divided by, 1 for whole instances
+    amount: f64,
 }
 
 impl Instance {
     pub(super) fn new(
         region: &str,
-        _instance: InstanceType,
+        instance: InstanceType,
         zones: &[AvailabilityZone],
         ondemand: f64,
     ) -> Self {
         let data = Self::process_zones(zones);
         Self {
             _region: region.to_string(),
-            _instance,
+            instance,
+            zones: zones.to_vec(),
             spot_prices: data,
             ondemand_price: ondemand,
+            savings_plan_rate: None,
+            amount: 1.0,
         }
     }
 
-    fn process_zones(zones: &[AvailabilityZone]) -> Option<MinMax> {
-        if zones.is_empty() {
-            return None;
+    /// the hourly rate under the selected Savings Plan
+    pub(super) fn with_savings_plan_rate(self, savings_plan_rate: Option<f64>) -> Self {
+        Self {
+            savings_plan_rate,
+            ..self
         }
+    }
 
-        assert!(!zones.is_empty());
-
-        let min_entry = zones
+    /// all prices divided by the units of the instance, e.g. 64 vCPUs
+    pub(super) fn per(self, amount: f64) -> Self {
+        let zones = self
+            .zones
             .iter()
-            .map(|e| e.get_min())
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
+            .map(|zone| zone.clone().per(amount))
+            .collect::<Vec<_>>();
 
-        let max_entry = zones
-            .iter()
-            .map(|e| e.get_max())
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
+        Self {
+            spot_prices: Self::process_zones(&zones),
+            zones,
+            ondemand_price: self.ondemand_price / amount,
+            savings_plan_rate: self.savings_plan_rate.map(|rate| rate / amount),
+            amount: self.amount * amount,
+            ..self
+        }
+    }
 
-        let last_entry = zones
+    // the spot prices of all zones as one history
+    fn process_zones(zones: &[AvailabilityZone]) -> Option<SpotStatistics> {
+        let statistics = zones
             .iter()
-            .map(|e| e.get_last())
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
-
-        let sum_avg: f64 = zones.iter().map(|e| e.get_average()).sum();
+            .map(AvailabilityZone::get_statistics)
+            .collect::<Vec<_>>();
 
-        Some(MinMax {
-            min: min_entry,
-            avg: sum_avg / zones.len() as f64,
-            max: max_entry,
-            last: last_entry,
-        })
+        SpotStatistics::merge(&statistics)
     }
 
     //    #[allow(unused)]
@@ -65,10 +75,14 @@ impl Instance {
     //    }
 
     pub(super) fn get_average_price(&self) -> Option<f64> {
-        if let Some(price) = &self.spot_prices {
-            return Some(price.avg);
-        }
-        None
+        self.get_spot_metric(Metric::Avg)
+    }
+
+    /// a statistic of the spot prices over all zones, none without spot prices
+    pub(super) fn get_spot_metric(&self, metric: Metric) -> Option<f64> {
+        self.spot_prices
+            .as_ref()
+            .map(|statistics| statistics.get(metric))
     }
     //
     //#[allow(unused)]
@@ -79,6 +93,64 @@ impl Instance {
     pub(super) fn get_ondemand_price(&self) -> f64 {
         self.ondemand_price
     }
+
+    pub(super) fn get_savings_plan_rate(&self) -> Option<f64> {
+        self.savings_plan_rate
+    }
+
+    /// a statistic of the spot prices divided by the on-demand price
+    pub(super) fn get_spot_ratio(&self, metric: Metric) -> Option<f64> {
+        let spot = self.get_spot_metric(metric)?;
+        (self.ondemand_price > 0.0).then(|| spot / self.ondemand_price)
+    }
+
+    /// the share of the on-demand price saved with spot, negative if spot is more expensive
+    pub(super) fn get_savings(&self, metric: Metric) -> Option<f64> {
+        self.get_spot_ratio(metric).map(|ratio| 1.0 - ratio)
+    }
+
+    /// the highest spot price divided by the on-demand price
+    pub(super) fn get_spike_ratio(&self) -> Option<f64> {
+        self.get_spot_ratio(Metric::Max)
+    }
+
+    pub(super) fn get_instance(&self) -> &InstanceType {
+        &self.instance
+    }
+
+    /// the statistics of the spot prices over all zones
+    pub(super) fn get_spot_prices(&self) -> Option<&SpotStatistics> {
+        self.spot_prices.as_ref()
+    }
+
+    pub(super) fn get_zones(&self) -> &[AvailabilityZone] {
+        &self.zones
+    }
+
+    /// the zone with the lowest statistic of the spot prices
+    pub(super) fn get_cheapest_zone(&self, metric: Metric) -> Option<&AvailabilityZone> {
+        self.zones.iter().min_by(|a, b| {
+            let a = a.get_statistics().get(metric);
+            a.total_cmp(&b.get_statistics().get(metric))
+        })
+    }
+
+    /// the highest statistic of the zones divided by the lowest one, 1 for a single zone
+    pub(super) fn get_zone_spread(&self, metric: Metric) -> Option<f64> {
+        let values = self
+            .zones
+            .iter()
+            .map(|zone| zone.get_statistics().get(metric));
+        let min = values.clone().min_by(f64::total_cmp)?;
+        let max = values.max_by(f64::total_cmp)?;
+
+        (min > 0.0).then(|| max / min)
+    }
+
+    /// the units the prices are divided by
+    pub(super) fn get_amount(&self) -> f64 {
+        self.amount
+    }
 }
 
 use std::fmt;
@@ -91,37 +163,3 @@ impl fmt::Debug for Instance {
             .finish()
     }
 }
-
-//impl Copy for Instance {}
-
-impl Clone for Instance {
-    fn clone(&self) -> Self {
-        if let Some(spot_price) = &self.spot_prices {
-            Instance {
-                _region: self._region.clone(),
-                _instance: self._instance.clone(),
-                spot_prices: Some(MinMax {
-                    min: spot_price.min,
-                    avg: spot_price.avg,
-                    max: spot_price.max,
-                    last: spot_price.last,
-                }),
-                ondemand_price: self.ondemand_price,
-            }
-        } else {
-            Instance {
-                _region: self._region.clone(),
-                _instance: self._instance.clone(),
-                spot_prices: None,
-                ondemand_price: self.ondemand_price,
-            }
-        }
-    }
-}
-
-struct MinMax {
-    min: f64,
-    avg: f64,
-    max: f64,
-    last: f64,
-}
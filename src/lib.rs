@@ -16,10 +16,46 @@
 
 //! various tools for analyzing prices of AWS EC2 instances
 
+/// an offline index of the bulk price list
+pub mod bulk;
+/// an on-disk cache for prices
+pub mod cache;
+/// sparklines and text charts of spot prices
+pub mod chart;
+/// why prices are missing from a report
+pub mod diagnostics;
 /// the AWS EC2 client
 pub mod ec2;
+/// the errors of the analyzer
+pub mod error;
+/// an in-memory data source for offline tests
+pub mod fixture;
+/// bound the number of concurrent API calls
+pub mod limit;
+/// prices per vCPU, core, GiB of memory, GPU or GB of NVMe storage
+pub mod normalize;
+/// the output formats of the reports
+pub mod output;
+/// the documents of the AWS price list
+pub mod price_list;
 /// the AWS pricing client
 pub mod pricing;
+/// named analysis profiles loaded from a config file
+pub mod profile;
+/// the data sources of the analyzer
+pub mod provider;
+/// record and replay the responses of the AWS APIs
+pub mod recording;
+/// the offerings of reserved instances
+pub mod reserved;
+/// the rates of Savings Plans
+pub mod savings_plans;
+/// a local database of spot prices
+pub mod spot_store;
+/// time-weighted statistics of spot prices
+pub mod statistics;
+/// pace and retry throttled API calls
+pub mod throttle;
 
 /// print spot prices for the different regions
 pub mod print_spot_region {
@@ -34,36 +70,36 @@ pub mod print_spot_region {
     mod reorder;
 
     mod printer;
+
+    mod json;
+
+    /// a self-contained HTML report
+    pub mod report;
+
+    /// compare reserved offerings with on-demand and spot prices
+    pub mod print_reserved;
+
+    /// the spot prices of every availability zone
+    pub mod print_zones;
+
+    /// a text chart of the spot prices of an instance over time
+    pub mod print_chart;
 }
 
+/// export spot price series for data analysis
+pub mod export;
+
 /// print information about EC2 instances
 pub mod print_instances;
 
+/// print questions about the whole catalog, e.g. the cheapest price per vCPU
+pub mod print_catalog;
+
 mod availability_zone;
 mod instance;
 
-use anyhow::Result;
 use aws_config;
 use aws_config::BehaviorVersion;
-use aws_sdk_ec2::primitives::DateTime;
-use aws_sdk_ec2::types::Filter;
-use aws_sdk_ec2::types::InstanceType;
-use aws_sdk_ec2::types::InstanceTypeInfo;
-
-async fn describe_instance(
-    client: &aws_sdk_ec2::Client,
-    instance: InstanceType,
-) -> Result<InstanceTypeInfo> {
-    let result = client
-        .describe_instance_types()
-        .instance_types(instance)
-        .send()
-        .await?;
-
-    return Ok(result.instance_types().first().unwrap().clone());
-}
-
-// FIXME: join_all + tokio::spawn
 
 /// get a shared_config configured for a given region
 pub async fn get_region_config(region: &str) -> aws_config::SdkConfig {
@@ -73,55 +109,6 @@ pub async fn get_region_config(region: &str) -> aws_config::SdkConfig {
         .await
 }
 
-async fn get_spot_price_history(
-    client: &aws_sdk_ec2::Client,
-    availibility_zone: &str,
-    instance_type: InstanceType,
-) -> Result<Vec<(DateTime, f64)>> {
-    let prices = client
-        .describe_spot_price_history()
-        .instance_types(instance_type)
-        .product_descriptions("Linux/UNIX")
-        .availability_zone(availibility_zone)
-        .into_paginator()
-        .items()
-        .send()
-        .collect::<Result<Vec<_>, _>>()
-        .await?;
-
-    let spot_prices = prices
-        .iter()
-        .filter(|price| price.spot_price().is_some() && price.timestamp().is_some())
-        .map(|price| {
-            let time = *price.timestamp().unwrap(); //.to_chrono_utc()
-            let spot_price = price.spot_price().as_ref().unwrap().parse::<f64>().unwrap();
-            (time, spot_price)
-        })
-        .collect::<Vec<_>>();
-
-    Ok(spot_prices)
-}
-
-async fn get_zones(client: &aws_sdk_ec2::Client, region: &str) -> Result<Vec<String>> {
-    let filter = Filter::builder().name("region-name").values(region).build();
-
-    // no paging !
-
-    let result = client
-        .describe_availability_zones()
-        .filters(filter)
-        .send()
-        .await?;
-
-    Ok(result
-        .availability_zones
-        .unwrap_or_default()
-        .into_iter()
-        .filter(|ava| ava.zone_name().is_some())
-        .map(|ava| ava.zone_name().unwrap().to_string())
-        .collect::<Vec<_>>())
-}
-
 fn get_padding(pad: char, len: usize) -> String {
     let mut s = String::default();
 
@@ -0,0 +1,140 @@
+use crate::error::Result;
+use crate::provider::{
+    Ec2Filter, InstanceCatalog, ProductFilter, ProductPrices, SpotPriceHistory, TimeWindow, Zone,
+    ZoneCatalog,
+};
+use crate::savings_plans::{SavingsPlanFilter, SavingsPlanRate};
+use aws_sdk_ec2::primitives::DateTime;
+use aws_sdk_ec2::types::InstanceType;
+use aws_sdk_ec2::types::InstanceTypeInfo;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+#[derive(Debug)]
+/// a provider making at most a number of concurrent calls to its inner provider
+pub struct Limited<T> {
+    inner: T,
+    permits: Semaphore,
+}
+
+impl<T> Limited<T> {
+    /// allow at most `limit` concurrent calls, but at least one
+    pub fn new(inner: T, limit: usize) -> Self {
+        Self {
+            inner,
+            permits: Semaphore::new(limit.max(1)),
+        }
+    }
+
+    async fn permit(&self) -> SemaphorePermit<'_> {
+        self.permits
+            .acquire()
+            .await
+            .expect("the semaphore is not closed")
+    }
+}
+
+impl<T: InstanceCatalog> InstanceCatalog for Limited<T> {
+    async fn describe_instance_types(
+        &self,
+        region: &str,
+        filters: &[Ec2Filter],
+    ) -> Result<Vec<InstanceTypeInfo>> {
+        let _permit = self.permit().await;
+        self.inner.describe_instance_types(region, filters).await
+    }
+}
+
+impl<T: SpotPriceHistory> SpotPriceHistory for Limited<T> {
+    async fn get_spot_price_history(
+        &self,
+        region: &str,
+        availability_zone: &str,
+        instance: &InstanceType,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> Result<Vec<(DateTime, f64)>> {
+        let _permit = self.permit().await;
+        self.inner
+            .get_spot_price_history(
+                region,
+                availability_zone,
+                instance,
+                product_description,
+                window,
+            )
+            .await
+    }
+}
+
+impl<T: ZoneCatalog> ZoneCatalog for Limited<T> {
+    async fn get_regions(&self) -> Result<Vec<String>> {
+        let _permit = self.permit().await;
+        self.inner.get_regions().await
+    }
+
+    async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+        let _permit = self.permit().await;
+        self.inner.get_zones(region).await
+    }
+}
+
+impl<T: ProductPrices> ProductPrices for Limited<T> {
+    async fn get_products(&self, filters: &[ProductFilter]) -> Result<Vec<String>> {
+        let _permit = self.permit().await;
+        self.inner.get_products(filters).await
+    }
+
+    async fn get_savings_plan_rates(
+        &self,
+        filter: &SavingsPlanFilter,
+    ) -> Result<Vec<SavingsPlanRate>> {
+        let _permit = self.permit().await;
+        self.inner.get_savings_plan_rates(filter).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::future::try_join_all;
+    use pretty_assertions::assert_eq;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Counter {
+        current: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    impl ZoneCatalog for Counter {
+        async fn get_regions(&self) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+            let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(current, Ordering::SeqCst);
+
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            Ok(vec![Zone::new(&format!("{region}a"), None)])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limit() -> Result<()> {
+        let limited = Limited::new(Counter::default(), 2);
+
+        let regions = ["us-east-1", "us-east-2", "us-west-2", "eu-north-1"];
+        let zones = try_join_all(regions.iter().map(|region| limited.get_zones(region))).await?;
+
+        assert_eq!(zones[3], vec![Zone::new("eu-north-1a", None)]);
+        assert_eq!(limited.inner.max.load(Ordering::SeqCst), 2);
+
+        Ok(())
+    }
+}
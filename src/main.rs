@@ -1,153 +1,509 @@
 use anyhow::Result;
 use aws_config::BehaviorVersion;
-use aws_ec2_analyzer::{ec2::Ec2, get_region_config, pricing::Pricing};
-
-//print_ondemand_region
-//use aws_sdk_ec2::model::InstanceType;
-//use aws_ec2_spot::print::print_spot_placement::print_spot_placement;
-//use aws_ec2_spot::print_spot_region::print_spot_regions::print_spot_regions;
-//use aws_ec2_analyzer::describe_instance_type_offerings;
+use aws_ec2_analyzer::{
+    bulk::{self, PriceListIndex},
+    cache::PriceCache,
+    diagnostics::Diagnostics,
+    ec2::Ec2,
+    get_region_config,
+    limit::Limited,
+    normalize::PriceUnit,
+    output::OutputFormat,
+    pricing::{PriceQuery, Pricing, Term},
+    print_instances::collect_specs,
+    print_spot_region::print_spot_regions::MatrixOptions,
+    profile::Profile,
+    provider::{InstanceCatalog, ProductPrices, SpotPriceHistory, ZoneCatalog},
+    recording::{Recorder, Replay},
+    spot_store::SpotStore,
+    throttle::{RetryLog, Throttled},
+};
+use aws_sdk_ec2::primitives::DateTime;
 use aws_sdk_ec2::types::InstanceType;
+use clap::Parser;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+mod cli;
+
+use cli::{Cli, Command};
+
+// the calls per second to the services, halved while they are throttled
+const EC2_RATE: f64 = 20.0;
+const PRICING_RATE: f64 = 5.0;
 
-const REGIONS: &[&str] = &[
-    "us-east-1",
-    "us-east-2",
-    "us-west-2",
-    "eu-west-1",
-    "eu-north-1",
-];
-
-const INSTANCE_TYPES: &[InstanceType] = &[
-    //InstanceType::C5n18xlarge,
-    InstanceType::C6i32xlarge,
-    InstanceType::C6a48xlarge,
-    InstanceType::C6g16xlarge,
-    //InstanceType::C6gn16xlarge,
-    //InstanceType::C6in32xlarge,
-    InstanceType::C7g16xlarge,
-    InstanceType::C8g48xlarge,
-    //InstanceType::M524xlarge,
-    //InstanceType::M5n24xlarge,
-    //InstanceType::M6i32xlarge,
-    //InstanceType::M6a48xlarge,
-    //InstanceType::M6g16xlarge,
-    // accelerator
-    //InstanceType::G548xlarge,
-    //        InstanceType::C5Metal,
-    //        InstanceType::C6iMetal,
-    //        InstanceType::C6aMetal,
-    //        InstanceType::M5znMetal
-    //        InstanceType::C5nMetal,
-    //InstanceType::G54xlarge,
-    //InstanceType::P4de24xlarge,
-    //InstanceType::P4d24xlarge,
-    //InstanceType::P3dn24xlarge,
-    //InstanceType::P216xlarge,
-    //InstanceType::G4ad16xlarge,
-    //InstanceType::G4dnMetal,
-    //InstanceType::G5gMetal,
-    //InstanceType::C7g4xlarge,
-    // memory
-    //InstanceType::X2iedn32xlarge,
-    //InstanceType::R6i32xlarge,
-    //nstanceType::X2idn32xlarge,
-    //InstanceType::R6g16xlarge,
-    //InstanceType::R5b24xlarge,
-    //InstanceType::X2gd16xlarge,
-    //nstanceType::R5b24xlarge,
-    //InstanceType::U12tb1112xlarge,
-    //InstanceType::U24tb1Metal,
-    //InstanceType::R6a48xlarge,
-    //InstanceType::R7a48xlarge,
-    //InstanceType::R7i48xlarge,
-    //InstanceType::R8g48xlarge,
-    // r8g.48xlarge
-    // storage
-    InstanceType::I7ie48xlarge,
-    //InstanceType::Im4gn16xlarge,
-    //InstanceType::I3en24xlarge,
-    //InstanceType::I4i32xlarge,
-    //InstanceType::Unknown("trn1.32xlarge".to_string()),
-    //InstanceType::Unknown("inf2.48xlarge".to_string()),
-    //InstanceType::Unknown("inf1.24xlarge".to_string()),
-    //InstanceType::Inf124xlarge,
-    InstanceType::Hpc6id32xlarge,
-    InstanceType::Hpc6a48xlarge,
-    InstanceType::C7gn16xlarge,
-    InstanceType::Hpc7g16xlarge,
-    //InstanceType::R7iz32xlarge,
-    //InstanceType::M7a48xlarge,
-    //InstanceType::P548xlarge,
-    //InstanceType::M7i48xlarge,
-    InstanceType::C7i48xlarge,
-    InstanceType::C7a48xlarge,
-    InstanceType::Hpc7a96xlarge,
-    //InstanceType::P3dn24xlarge,
-];
-
-#[tokio::main(flavor = "current_thread")]
+#[tokio::main]
 async fn main() -> Result<()> {
-    //tracing_subscriber::fmt::init();
+    // warnings of the library, e.g. failed cache writes
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::WARN)
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    let profile = cli.load_profile()?;
+    let profile = profile.as_ref();
+
+    let output = cli.output;
+    let explain = cli.explain;
+    let query = cli.query.price_query(profile);
+
+    if let Command::IngestPriceList { regions, files } = &cli.command {
+        return ingest_price_list(&cli.price_list_index(), &cli.cache_dir(), regions, files).await;
+    }
 
-    //         InstanceType::U24tb1112xlarge,
+    if let Some(dir) = &cli.replay {
+        let replay = Replay::new(dir);
+        let now = replay
+            .recorded_time()?
+            .unwrap_or_else(|| DateTime::from(SystemTime::now()));
+        if cli.price_list {
+            let index = PriceListIndex::open(&cli.price_list_index())?;
+            return run(
+                cli.command,
+                profile,
+                &query,
+                output,
+                explain,
+                now,
+                &replay,
+                &index,
+            )
+            .await;
+        }
+        return run(
+            cli.command,
+            profile,
+            &query,
+            output,
+            explain,
+            now,
+            &replay,
+            &replay,
+        )
+        .await;
+    }
+
+    let now = DateTime::from(SystemTime::now());
 
     let shared_config = aws_config::load_defaults(BehaviorVersion::v2025_08_07()).await;
 
     let ec2_client = aws_sdk_ec2::Client::new(&shared_config);
 
-    let ec2 = Ec2::new(ec2_client);
+    // shared by the throttling layers of both services
+    let log = RetryLog::new();
 
-    let pricing_config = get_region_config("us-east-1").await;
-    let pricing = Pricing::new(pricing_config);
+    let ec2 = SpotStore::open(
+        Throttled::new(
+            Limited::new(Ec2::new(ec2_client), cli.ec2_concurrency),
+            "ec2",
+            cli.retry_policy(EC2_RATE),
+            &log,
+        ),
+        &cli.spot_store(),
+    )?;
+
+    if cli.price_list {
+        // every price from the local index, the Pricing API is not called
+        let index = PriceListIndex::open(&cli.price_list_index())?;
+        let result = match &cli.record {
+            Some(dir) => {
+                let ec2 = Recorder::new(ec2, dir);
+                ec2.record_time(now)?;
+                run(
+                    cli.command,
+                    profile,
+                    &query,
+                    output,
+                    explain,
+                    now,
+                    &ec2,
+                    &index,
+                )
+                .await
+            }
+            None => {
+                run(
+                    cli.command,
+                    profile,
+                    &query,
+                    output,
+                    explain,
+                    now,
+                    &ec2,
+                    &index,
+                )
+                .await
+            }
+        };
+
+        eprint!("{}", log.summary());
 
-    aws_ec2_analyzer::print_spot_region::print_spot_regions::print_spot_regions(
-        &ec2,
-        &pricing,
-        INSTANCE_TYPES,
-        REGIONS,
+        return result;
+    }
+
+    let pricing_config = get_region_config("us-east-1").await;
+    let pricing = PriceCache::new(
+        Throttled::new(
+            Limited::new(Pricing::new(pricing_config), cli.pricing_concurrency),
+            "pricing",
+            cli.retry_policy(PRICING_RATE),
+            &log,
+        ),
+        &cli.cache_dir(),
+        cli.pricing_ttl(),
     )
-    .await?;
-
-    aws_ec2_analyzer::print_instances::print_instances(INSTANCE_TYPES).await?;
-
-    //print_spot_regions(&ec2, "c6gn.16xlarge").await?;
-    //println!();
-    //print_spot_regions(&ec2, "m6i.32xlarge").await?;
-    //println!();
-    //print_spot_regions(&ec2, "c6i.32xlarge").await?;
-    ////println!();
-    //    print_spot_regions(&ec2, "g5.48xlarge").await?;
-    //    println!();
-    //    print_spot_regions(&ec2, "g5.24xlarge").await?;
-    //    println!();
-    //    print_spot_regions(&ec2, "g5.16xlarge").await?;
-    //    println!();
-    //    print_spot_regions(&ec2, "g5.12xlarge").await?;
-    //    println!();
-    //    print_spot_regions(&ec2, "g5.4xlarge").await?;
-
-    //
-    //print_spot_regions(
-    //    &ec2,
-    //    &["g5.48xlarge", "g5.24xlarge", "g5.16xlarge", "g5.4xlarge"],
-    //)
-    //.await?;
-
-    //render_spot_region(
-    //    &ec2,
-    //    &pricing,
-    //    &[
-    //        InstanceType::from("m6i.32xlarge"),
-    //        InstanceType::from("m5.24xlarge"),
-    //        InstanceType::from("m5a.24xlarge"),
-    //        InstanceType::from("m6g.16xlarge"),
-    //        InstanceType::from("c5n.18xlarge"),
-    //        InstanceType::from("c6gn.16xlarge"),
-    //    ],
-    //    "us-east-2",
-    //    "meds",
-    //)
-    //.await?;
+    .with_refresh(cli.refresh);
+
+    let result = match &cli.record {
+        Some(dir) => {
+            let ec2 = Recorder::new(ec2, dir);
+            let pricing = Recorder::new(pricing, dir);
+            ec2.record_time(now)?;
+            run(
+                cli.command,
+                profile,
+                &query,
+                output,
+                explain,
+                now,
+                &ec2,
+                &pricing,
+            )
+            .await
+        }
+        None => {
+            run(
+                cli.command,
+                profile,
+                &query,
+                output,
+                explain,
+                now,
+                &ec2,
+                &pricing,
+            )
+            .await
+        }
+    };
+
+    eprint!("{}", log.summary());
+
+    result
+}
+
+// download the offer files of the regions and index them with the local files
+async fn ingest_price_list(
+    index: &Path,
+    cache_dir: &Path,
+    regions: &[String],
+    files: &[PathBuf],
+) -> Result<()> {
+    let index = PriceListIndex::open(index)?;
+
+    let mut paths = files.to_vec();
+    for region in regions {
+        eprintln!("downloading {}", bulk::offer_file_url(region));
+        paths.push(bulk::download(region, &cache_dir.join("offer_files")).await?);
+    }
+
+    if paths.is_empty() {
+        anyhow::bail!("no offer files given: use --region or --file");
+    }
+
+    for path in paths {
+        let count = index.ingest(&path)?;
+        println!("indexed {count} products of {}", path.display());
+    }
+
+    Ok(())
+}
+
+// why cells are empty, on stderr unless the output is a plain text table
+fn print_diagnostics(diagnostics: &Diagnostics, output: OutputFormat, explain: bool) {
+    let text = if explain {
+        diagnostics.explain()
+    } else {
+        diagnostics.footnote()
+    };
+
+    match output {
+        OutputFormat::Text => print!("{text}"),
+        _ => eprint!("{text}"),
+    }
+}
+
+// colors only for text on a terminal, unless disabled by NO_COLOR
+fn use_color(output: OutputFormat) -> bool {
+    output == OutputFormat::Text
+        && std::io::stdout().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none()
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run<E, P>(
+    command: Command,
+    profile: Option<&Profile>,
+    query: &PriceQuery,
+    output: OutputFormat,
+    explain: bool,
+    now: DateTime,
+    ec2: &E,
+    pricing: &P,
+) -> Result<()>
+where
+    E: InstanceCatalog + SpotPriceHistory + ZoneCatalog,
+    P: ProductPrices,
+{
+    match command {
+        Command::SpotRegions {
+            selection,
+            regions,
+            window,
+            savings_plan,
+            metric,
+            zone_columns,
+            savings_columns,
+            sort_by_savings,
+            sparklines,
+        } => {
+            let instances = selection.resolve(ec2, profile).await?;
+            let region_names = regions.resolve(ec2, profile).await?;
+            let favorites = regions.favorites(profile);
+            let favorites = favorites.iter().map(String::as_str).collect::<Vec<_>>();
+
+            let options = sort_by_savings.into_iter().fold(
+                MatrixOptions::default()
+                    .with_zone_columns(zone_columns)
+                    .with_savings_columns(savings_columns)
+                    .with_color(use_color(output))
+                    .with_sparklines(sparklines),
+                MatrixOptions::with_sort_by_savings,
+            );
+
+            let diagnostics =
+                aws_ec2_analyzer::print_spot_region::print_spot_regions::print_spot_regions(
+                    ec2,
+                    pricing,
+                    &instances,
+                    &region_names,
+                    &favorites,
+                    &query.with_savings_plan(savings_plan).with_metric(metric),
+                    &window.window(now),
+                    output,
+                    &options,
+                )
+                .await?;
+
+            print_diagnostics(&diagnostics, output, explain);
+        }
+        Command::Chart {
+            instance,
+            regions,
+            window,
+            width,
+            height,
+        } => {
+            if output != OutputFormat::Text {
+                anyhow::bail!("chart only supports text output, not {output}");
+            }
+
+            let region_names = regions.resolve(ec2, profile).await?;
+
+            let diagnostics = aws_ec2_analyzer::print_spot_region::print_chart::print_chart(
+                ec2,
+                pricing,
+                &InstanceType::from(instance.as_str()),
+                &region_names,
+                query,
+                &window.window(now),
+                width,
+                height,
+            )
+            .await?;
+
+            print_diagnostics(&diagnostics, output, explain);
+        }
+        Command::Zones {
+            selection,
+            regions,
+            window,
+            metric,
+        } => {
+            let instances = selection.resolve(ec2, profile).await?;
+            let region_names = regions.resolve(ec2, profile).await?;
+
+            let diagnostics = aws_ec2_analyzer::print_spot_region::print_zones::print_zones(
+                ec2,
+                pricing,
+                &instances,
+                &region_names,
+                &query.with_metric(metric),
+                &window.window(now),
+                output,
+            )
+            .await?;
+
+            print_diagnostics(&diagnostics, output, explain);
+        }
+        Command::Report {
+            selection,
+            regions,
+            window,
+            savings_plan,
+            metric,
+            out,
+        } => {
+            let instances = selection.resolve(ec2, profile).await?;
+            let region_names = regions.resolve(ec2, profile).await?;
+            let favorites = regions.favorites(profile);
+            let favorites = favorites.iter().map(String::as_str).collect::<Vec<_>>();
+
+            let report = aws_ec2_analyzer::print_spot_region::report::render_report(
+                ec2,
+                pricing,
+                &instances,
+                &region_names,
+                &favorites,
+                &query.with_savings_plan(savings_plan).with_metric(metric),
+                &window.window(now),
+            )
+            .await?;
+
+            std::fs::write(&out, report)?;
+            println!("wrote {}", out.display());
+        }
+        Command::ExportSpot {
+            selection,
+            regions,
+            window,
+            format,
+            resample,
+            out,
+        } => {
+            let instances = selection.resolve(ec2, profile).await?;
+            let region_names = regions.resolve(ec2, profile).await?;
+
+            let diagnostics = aws_ec2_analyzer::export::export_spot_prices(
+                ec2,
+                &instances,
+                &region_names,
+                query,
+                &window.window(now),
+                resample,
+                format,
+                out.as_deref(),
+            )
+            .await?;
+
+            // stdout holds the data
+            print_diagnostics(&diagnostics, OutputFormat::Csv, explain);
+        }
+        Command::Instances { selection } => {
+            let instances = selection.resolve(ec2, profile).await?;
+
+            let diagnostics =
+                aws_ec2_analyzer::print_instances::print_instances(ec2, &instances, output).await?;
+
+            print_diagnostics(&diagnostics, output, explain);
+        }
+        Command::Efa { region } => {
+            let instances = ec2
+                .get_instance_types_efa(&region)
+                .await?
+                .iter()
+                .filter_map(|info| info.instance_type().cloned())
+                .collect::<Vec<_>>();
+
+            let diagnostics =
+                aws_ec2_analyzer::print_instances::print_instances(ec2, &instances, output).await?;
+
+            print_diagnostics(&diagnostics, output, explain);
+        }
+        Command::Ondemand { selection, regions } => {
+            if output != OutputFormat::Text {
+                anyhow::bail!("ondemand only supports text output, not {output}");
+            }
+
+            let instances = selection.resolve(ec2, profile).await?;
+            let query = query.with_term(Term::OnDemand);
+
+            let diagnostics = Diagnostics::new();
+            let unit = query.unit();
+            let specs = match unit {
+                // whole instances need no description
+                PriceUnit::Instance => Default::default(),
+                unit => {
+                    println!("USD per {}", unit.label());
+                    collect_specs(ec2, &instances, &diagnostics).await
+                }
+            };
+
+            for region in regions.resolve(ec2, profile).await? {
+                for instance in &instances {
+                    let amount = match unit {
+                        PriceUnit::Instance => Some(1.0),
+                        unit => specs
+                            .get(instance.as_str())
+                            .and_then(|specs| specs.amount(unit)),
+                    };
+                    let Some(amount) = amount else {
+                        println!("{region:<16} {:<20} no {unit}", instance.as_str());
+                        continue;
+                    };
+
+                    match pricing
+                        .get_query_price(instance.as_str(), &region, &query)
+                        .await
+                    {
+                        Ok(price) => println!(
+                            "{region:<16} {:<20} {:.5}",
+                            instance.as_str(),
+                            price / amount
+                        ),
+                        Err(err) => println!("{region:<16} {:<20} {err}", instance.as_str()),
+                    }
+                }
+            }
+
+            print_diagnostics(&diagnostics, output, explain);
+        }
+        Command::Reserved {
+            selection,
+            regions,
+            window,
+        } => {
+            let instances = selection.resolve(ec2, profile).await?;
+            let region_names = regions.resolve(ec2, profile).await?;
+            // the on-demand column compares with the hourly price
+            let query = query.with_term(Term::OnDemand);
+
+            let diagnostics = aws_ec2_analyzer::print_spot_region::print_reserved::print_reserved(
+                ec2,
+                pricing,
+                &instances,
+                &region_names,
+                &query,
+                &window.window(now),
+                output,
+            )
+            .await?;
+
+            print_diagnostics(&diagnostics, output, explain);
+        }
+        Command::VcpuPrices { region, top } => {
+            aws_ec2_analyzer::print_catalog::print_vcpu_prices(
+                pricing,
+                &query.with_term(Term::OnDemand),
+                region.as_deref(),
+                top,
+                output,
+            )
+            .await?;
+        }
+        Command::IngestPriceList { .. } => unreachable!("the price list is ingested before"),
+    }
 
     Ok(())
 }
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// the resource prices are divided by, e.g. USD per vCPU-hour
+pub enum PriceUnit {
+    /// the whole instance
+    #[default]
+    Instance,
+    /// a virtual CPU
+    Vcpu,
+    /// a physical core
+    Core,
+    /// a GiB of memory
+    #[serde(rename = "gib")]
+    Memory,
+    /// a GPU
+    Gpu,
+    /// a GB of NVMe instance storage
+    #[serde(rename = "nvme-gb")]
+    Nvme,
+}
+
+impl PriceUnit {
+    /// whether prices are divided at all
+    pub fn is_normalized(self) -> bool {
+        self != PriceUnit::Instance
+    }
+
+    /// the unit of the hourly prices, e.g. `vCPU-hour`, or `hour` for whole instances
+    pub fn label(self) -> &'static str {
+        match self {
+            PriceUnit::Instance => "hour",
+            PriceUnit::Vcpu => "vCPU-hour",
+            PriceUnit::Core => "core-hour",
+            PriceUnit::Memory => "GiB-hour",
+            PriceUnit::Gpu => "GPU-hour",
+            PriceUnit::Nvme => "NVMe-GB-hour",
+        }
+    }
+}
+
+impl FromStr for PriceUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "instance" => Ok(PriceUnit::Instance),
+            "vcpu" => Ok(PriceUnit::Vcpu),
+            "core" => Ok(PriceUnit::Core),
+            "gib" | "memory" => Ok(PriceUnit::Memory),
+            "gpu" => Ok(PriceUnit::Gpu),
+            "nvme-gb" | "nvme" => Ok(PriceUnit::Nvme),
+            _ => Err(format!(
+                "unknown price unit: {s}, expected instance, vcpu, core, gib, gpu or nvme-gb"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PriceUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriceUnit::Instance => f.pad("instance"),
+            PriceUnit::Vcpu => f.pad("vcpu"),
+            PriceUnit::Core => f.pad("core"),
+            PriceUnit::Memory => f.pad("gib"),
+            PriceUnit::Gpu => f.pad("gpu"),
+            PriceUnit::Nvme => f.pad("nvme-gb"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// the resources of an instance type prices are normalized by
+pub struct Specs {
+    vcpus: i32,
+    cores: i32,
+    memory_mib: i64,
+    gpus: i32,
+    nvme_gb: Option<i64>,
+}
+
+impl Specs {
+    /// create the resources of an instance type
+    pub fn new(vcpus: i32, cores: i32, memory_mib: i64, gpus: i32, nvme_gb: Option<i64>) -> Self {
+        Self {
+            vcpus,
+            cores,
+            memory_mib,
+            gpus,
+            nvme_gb,
+        }
+    }
+
+    /// how many units the instance has, none if it has none, e.g. GPUs of a c7g
+    pub fn amount(&self, unit: PriceUnit) -> Option<f64> {
+        let amount = match unit {
+            PriceUnit::Instance => 1.0,
+            PriceUnit::Vcpu => f64::from(self.vcpus),
+            PriceUnit::Core => f64::from(self.cores),
+            PriceUnit::Memory => f64::from(i32::try_from(self.memory_mib).ok()?) / 1024.0,
+            PriceUnit::Gpu => f64::from(self.gpus),
+            PriceUnit::Nvme => f64::from(i32::try_from(self.nvme_gb?).ok()?),
+        };
+
+        Some(amount).filter(|amount| *amount > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_amount() {
+        let c7g = Specs::new(64, 64, 131072, 0, None);
+        assert_eq!(c7g.amount(PriceUnit::Instance), Some(1.0));
+        assert_eq!(c7g.amount(PriceUnit::Vcpu), Some(64.0));
+        assert_eq!(c7g.amount(PriceUnit::Memory), Some(128.0));
+        assert_eq!(c7g.amount(PriceUnit::Gpu), None);
+        assert_eq!(c7g.amount(PriceUnit::Nvme), None);
+
+        let t4g = Specs::new(2, 2, 512, 0, None);
+        assert_eq!(t4g.amount(PriceUnit::Memory), Some(0.5));
+
+        let p5 = Specs::new(192, 96, 2097152, 8, Some(30400));
+        assert_eq!(p5.amount(PriceUnit::Core), Some(96.0));
+        assert_eq!(p5.amount(PriceUnit::Gpu), Some(8.0));
+        assert_eq!(p5.amount(PriceUnit::Nvme), Some(30400.0));
+    }
+
+    #[test]
+    fn test_parse() {
+        for unit in [
+            PriceUnit::Instance,
+            PriceUnit::Vcpu,
+            PriceUnit::Core,
+            PriceUnit::Memory,
+            PriceUnit::Gpu,
+            PriceUnit::Nvme,
+        ] {
+            assert_eq!(unit.to_string().parse::<PriceUnit>(), Ok(unit));
+        }
+        assert!("tpu".parse::<PriceUnit>().is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// how reports are written to stdout
+pub enum OutputFormat {
+    /// fixed-width ASCII tables
+    #[default]
+    Text,
+    /// JSON documents, see README.md for the schema
+    Json,
+    /// comma-separated values for spreadsheets
+    Csv,
+    /// GitHub-flavored Markdown tables
+    Markdown,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            _ => Err(format!(
+                "unknown output format: {s}, expected text, json, csv or markdown"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Markdown => write!(f, "markdown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// a table of cells, rendered as CSV or Markdown
+pub(crate) struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub(crate) fn new(header: &[&str]) -> Self {
+        Self {
+            header: header.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub(crate) fn header(&self) -> &[String] {
+        &self.header
+    }
+
+    pub(crate) fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    /// add a row, missing cells are left empty
+    pub(crate) fn push(&mut self, mut row: Vec<String>) {
+        row.resize(self.header.len(), String::new());
+        self.rows.push(row);
+    }
+
+    /// render the table in a tabular format
+    pub(crate) fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Csv => Ok(self.to_csv()),
+            OutputFormat::Markdown => Ok(self.to_markdown()),
+            format => Err(Error::Unsupported(format!(
+                "{format} is not a tabular output format"
+            ))),
+        }
+    }
+
+    /// fixed-width columns, as wide as the widest cell
+    pub(crate) fn to_text(&self) -> String {
+        let widths = (0..self.header.len())
+            .map(|column| {
+                std::iter::once(&self.header)
+                    .chain(&self.rows)
+                    .map(|row| text_width(&row[column]))
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut result = String::new();
+
+        for row in std::iter::once(&self.header).chain(&self.rows) {
+            let cells = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - text_width(cell))))
+                .collect::<Vec<_>>();
+            result.push_str(cells.join("  ").trim_end());
+            result.push('\n');
+        }
+
+        result
+    }
+
+    fn to_csv(&self) -> String {
+        let mut result = String::new();
+
+        for row in std::iter::once(&self.header).chain(&self.rows) {
+            let cells = row.iter().map(|cell| csv_cell(cell)).collect::<Vec<_>>();
+            result.push_str(&cells.join(","));
+            result.push('\n');
+        }
+
+        result
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut result = markdown_row(&self.header);
+
+        let separator = vec!["---".to_string(); self.header.len()];
+        result.push_str(&markdown_row(&separator));
+
+        for row in &self.rows {
+            result.push_str(&markdown_row(row));
+        }
+
+        result
+    }
+}
+
+// the columns a cell takes on a terminal, without the escape sequences of colors
+fn text_width(cell: &str) -> usize {
+    let mut width = 0;
+    let mut escape = false;
+    for c in cell.chars() {
+        if escape {
+            escape = c != 'm';
+        } else if c == '\x1b' {
+            escape = true;
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+// quote cells with separators, quotes or line breaks (RFC 4180)
+fn csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+fn markdown_row(row: &[String]) -> String {
+    let cells = row
+        .iter()
+        .map(|cell| cell.replace('|', "\\|").replace('\n', " "))
+        .collect::<Vec<_>>();
+
+    format!("| {} |\n", cells.join(" | "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["instance", "network"]);
+        table.push(vec!["c7g.16xlarge".to_string(), "30 Gigabit".to_string()]);
+        table.push(vec![
+            "p5.48xlarge".to_string(),
+            "3200, \"EFA\" | x".to_string(),
+        ]);
+        table.push(vec!["c7g.8xlarge".to_string()]);
+        table
+    }
+
+    #[test]
+    fn test_csv() -> Result<()> {
+        assert_eq!(
+            table().render(OutputFormat::Csv)?,
+            "instance,network\n\
+             c7g.16xlarge,30 Gigabit\n\
+             p5.48xlarge,\"3200, \"\"EFA\"\" | x\"\n\
+             c7g.8xlarge,\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            table().to_text(),
+            "instance      network\n\
+             c7g.16xlarge  30 Gigabit\n\
+             p5.48xlarge   3200, \"EFA\" | x\n\
+             c7g.8xlarge\n"
+        );
+
+        // colors take no room
+        assert_eq!(text_width("\x1b[32m75.0%\x1b[0m"), 5);
+    }
+
+    #[test]
+    fn test_markdown() -> Result<()> {
+        assert_eq!(
+            table().render(OutputFormat::Markdown)?,
+            "| instance | network |\n\
+             | --- | --- |\n\
+             | c7g.16xlarge | 30 Gigabit |\n\
+             | p5.48xlarge | 3200, \"EFA\" \\| x |\n\
+             | c7g.8xlarge |  |\n"
+        );
+
+        assert!(table().render(OutputFormat::Text).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+use crate::error::{Error, Result};
+use crate::provider::ProductFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// the unit of hourly prices and of upfront fees
+const HOURS: &str = "Hrs";
+const QUANTITY: &str = "Quantity";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// a price list document of the Pricing API: a product with all of its terms
+pub struct PriceListDocument {
+    product: Product,
+    #[serde(default)]
+    service_code: String,
+    #[serde(default)]
+    terms: Terms,
+    version: Option<String>,
+    publication_date: Option<String>,
+}
+
+impl PriceListDocument {
+    /// create a document of a product and its terms, keyed by offer term code
+    pub fn new(
+        product: Product,
+        service_code: &str,
+        on_demand: BTreeMap<String, OfferTerm>,
+        reserved: BTreeMap<String, OfferTerm>,
+        version: Option<String>,
+        publication_date: Option<String>,
+    ) -> Self {
+        Self {
+            product,
+            service_code: service_code.to_string(),
+            terms: Terms {
+                on_demand,
+                reserved,
+            },
+            version,
+            publication_date,
+        }
+    }
+
+    /// parse a price list document (JSON)
+    pub fn parse(document: &str) -> Result<Self> {
+        serde_json::from_str(document).map_err(|err| Error::MalformedPriceList(err.to_string()))
+    }
+
+    /// the product, e.g. an instance type in a region
+    pub fn product(&self) -> &Product {
+        &self.product
+    }
+
+    /// the service of the product, e.g. `AmazonEC2`
+    pub fn service_code(&self) -> &str {
+        &self.service_code
+    }
+
+    /// the version of the price list, e.g. `20241011201005`
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// when the price list was published, e.g. `2024-10-11T20:10:05Z`
+    pub fn publication_date(&self) -> Option<&str> {
+        self.publication_date.as_deref()
+    }
+
+    /// the on-demand terms, usually exactly one
+    pub fn on_demand_terms(&self) -> impl Iterator<Item = &OfferTerm> {
+        self.terms.on_demand.values()
+    }
+
+    /// the reserved terms, one per lease length, offering class and purchase option
+    pub fn reserved_terms(&self) -> impl Iterator<Item = &OfferTerm> {
+        self.terms.reserved.values()
+    }
+
+    /// whether an attribute of the product or of any term has the value of the filter
+    pub fn matches(&self, filter: &ProductFilter) -> bool {
+        self.product.attribute(filter.field()) == Some(filter.value())
+            || self
+                .on_demand_terms()
+                .chain(self.reserved_terms())
+                .any(|term| term.term_attribute(filter.field()) == Some(filter.value()))
+    }
+
+    /// the document as returned by the Pricing API (JSON)
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// the product of a price list document
+pub struct Product {
+    product_family: Option<String>,
+    #[serde(default)]
+    attributes: BTreeMap<String, String>,
+    sku: String,
+}
+
+impl Product {
+    /// create a product
+    pub fn new(
+        sku: &str,
+        product_family: Option<String>,
+        attributes: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            product_family,
+            attributes,
+            sku: sku.to_string(),
+        }
+    }
+
+    /// the family of the product, e.g. `Compute Instance`
+    pub fn product_family(&self) -> Option<&str> {
+        self.product_family.as_deref()
+    }
+
+    /// an attribute of the product, e.g. `instanceType`
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|value| value.as_str())
+    }
+
+    /// all attributes of the product
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// the stock keeping unit, the id of the product
+    pub fn sku(&self) -> &str {
+        &self.sku
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Terms {
+    #[serde(default)]
+    on_demand: BTreeMap<String, OfferTerm>,
+    #[serde(default)]
+    reserved: BTreeMap<String, OfferTerm>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// the conditions a product is offered at, e.g. a 3 year reservation
+pub struct OfferTerm {
+    offer_term_code: String,
+    sku: String,
+    effective_date: Option<String>,
+    #[serde(default)]
+    price_dimensions: BTreeMap<String, PriceDimension>,
+    #[serde(default)]
+    term_attributes: BTreeMap<String, String>,
+}
+
+impl OfferTerm {
+    /// create a term with its prices, keyed by rate code
+    pub fn new(
+        offer_term_code: &str,
+        sku: &str,
+        effective_date: Option<String>,
+        price_dimensions: BTreeMap<String, PriceDimension>,
+        term_attributes: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            offer_term_code: offer_term_code.to_string(),
+            sku: sku.to_string(),
+            effective_date,
+            price_dimensions,
+            term_attributes,
+        }
+    }
+
+    /// the code of the term, e.g. `JRTCKXETXF` for on-demand
+    pub fn offer_term_code(&self) -> &str {
+        &self.offer_term_code
+    }
+
+    /// the product of the term
+    pub fn sku(&self) -> &str {
+        &self.sku
+    }
+
+    /// since when the prices apply, e.g. `2024-10-01T00:00:00Z`
+    pub fn effective_date(&self) -> Option<&str> {
+        self.effective_date.as_deref()
+    }
+
+    /// the prices of the term, e.g. an upfront fee and an hourly price
+    pub fn price_dimensions(&self) -> impl Iterator<Item = &PriceDimension> {
+        self.price_dimensions.values()
+    }
+
+    /// an attribute of the term, e.g. `LeaseContractLength`
+    pub fn term_attribute(&self, name: &str) -> Option<&str> {
+        self.term_attributes.get(name).map(|value| value.as_str())
+    }
+
+    /// the length of a reservation, e.g. `1yr` or `3yr`
+    pub fn lease_contract_length(&self) -> Option<&str> {
+        self.term_attribute("LeaseContractLength")
+    }
+
+    /// the class of a reservation, `standard` or `convertible`
+    pub fn offering_class(&self) -> Option<&str> {
+        self.term_attribute("OfferingClass")
+    }
+
+    /// how a reservation is paid, e.g. `All Upfront`
+    pub fn purchase_option(&self) -> Option<&str> {
+        self.term_attribute("PurchaseOption")
+    }
+
+    /// the hourly price, zero without an hourly price dimension
+    pub fn hourly_price(&self) -> Result<f64> {
+        self.price_of(HOURS)
+    }
+
+    /// the upfront fee, zero without an upfront price dimension
+    pub fn upfront_fee(&self) -> Result<f64> {
+        self.price_of(QUANTITY)
+    }
+
+    // the sum of the dimensions of a unit
+    fn price_of(&self, unit: &str) -> Result<f64> {
+        self.price_dimensions()
+            .filter(|dimension| dimension.unit() == unit)
+            .map(PriceDimension::usd)
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// a price of a term, e.g. per hour
+pub struct PriceDimension {
+    #[serde(default)]
+    rate_code: String,
+    #[serde(default)]
+    description: String,
+    unit: String,
+    begin_range: Option<String>,
+    end_range: Option<String>,
+    #[serde(default)]
+    price_per_unit: BTreeMap<String, String>,
+    #[serde(default)]
+    applies_to: Vec<String>,
+}
+
+impl PriceDimension {
+    /// create a price of a term in one currency
+    pub fn new(
+        rate_code: &str,
+        description: &str,
+        unit: &str,
+        range: (Option<String>, Option<String>),
+        currency: &str,
+        price: &str,
+    ) -> Self {
+        Self {
+            rate_code: rate_code.to_string(),
+            description: description.to_string(),
+            unit: unit.to_string(),
+            begin_range: range.0,
+            end_range: range.1,
+            price_per_unit: BTreeMap::from([(currency.to_string(), price.to_string())]),
+            applies_to: Vec::new(),
+        }
+    }
+
+    /// the code of the price, `<sku>.<offer term code>.<rate code>`
+    pub fn rate_code(&self) -> &str {
+        &self.rate_code
+    }
+
+    /// what the price is for, e.g. `Upfront Fee`
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// the unit of the price, e.g. `Hrs` or `Quantity`
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    /// the first unit the price applies to, e.g. `0`
+    pub fn begin_range(&self) -> Option<&str> {
+        self.begin_range.as_deref()
+    }
+
+    /// the last unit the price applies to, e.g. `Inf`
+    pub fn end_range(&self) -> Option<&str> {
+        self.end_range.as_deref()
+    }
+
+    /// the price in a currency, e.g. `USD`
+    pub fn price_per_unit(&self, currency: &str) -> Option<&str> {
+        self.price_per_unit
+            .get(currency)
+            .map(|price| price.as_str())
+    }
+
+    /// the products the price also applies to
+    pub fn applies_to(&self) -> &[String] {
+        &self.applies_to
+    }
+
+    /// the price in USD
+    pub fn usd(&self) -> Result<f64> {
+        let usd = self.price_per_unit("USD").ok_or_else(|| {
+            Error::MalformedPriceList(format!("no USD price for {}", self.rate_code))
+        })?;
+
+        usd.parse::<f64>()
+            .map_err(|err| Error::MalformedPriceList(format!("USD price {usd}: {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    const ONDEMAND: &str =
+        include_str!("../testdata/price_list/c7g.16xlarge-us-east-2-ondemand.json");
+    const RESERVED: &str =
+        include_str!("../testdata/price_list/c7g.16xlarge-us-east-2-reserved.json");
+
+    #[test]
+    fn test_ondemand() -> Result<()> {
+        let document = PriceListDocument::parse(ONDEMAND)?;
+
+        assert_eq!(document.service_code(), "AmazonEC2");
+        assert_eq!(document.version(), Some("20241011201005"));
+        assert_eq!(document.publication_date(), Some("2024-10-11T20:10:05Z"));
+
+        let product = document.product();
+        assert_eq!(product.sku(), "2Q8GZ9TXKTK6GXZB");
+        assert_eq!(product.product_family(), Some("Compute Instance"));
+        assert_eq!(product.attribute("instanceType"), Some("c7g.16xlarge"));
+        assert_eq!(product.attribute("regionCode"), Some("us-east-2"));
+        assert_eq!(product.attribute("gpu"), None);
+
+        let terms = document.on_demand_terms().collect::<Vec<_>>();
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].offer_term_code(), "JRTCKXETXF");
+        assert_eq!(terms[0].effective_date(), Some("2024-10-01T00:00:00Z"));
+        assert_eq!(terms[0].lease_contract_length(), None);
+        assert_eq!(terms[0].hourly_price()?, 2.312);
+        assert_eq!(terms[0].upfront_fee()?, 0.0);
+
+        let dimension = terms[0].price_dimensions().next().unwrap();
+        assert_eq!(dimension.unit(), "Hrs");
+        assert_eq!(dimension.begin_range(), Some("0"));
+        assert_eq!(dimension.end_range(), Some("Inf"));
+
+        assert_eq!(document.reserved_terms().count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reserved() -> Result<()> {
+        let document = PriceListDocument::parse(RESERVED)?;
+
+        assert_eq!(document.product().attribute("capacitystatus"), Some("Used"));
+        assert_eq!(document.on_demand_terms().count(), 1);
+
+        let terms = document
+            .reserved_terms()
+            .map(|term| -> Result<_> {
+                Ok((
+                    term.lease_contract_length().unwrap_or_default(),
+                    term.offering_class().unwrap_or_default(),
+                    term.purchase_option().unwrap_or_default(),
+                    term.upfront_fee()?,
+                    term.hourly_price()?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            terms,
+            vec![
+                ("3yr", "standard", "Partial Upfront", 16363.0, 0.623),
+                ("1yr", "standard", "No Upfront", 0.0, 1.457),
+                ("3yr", "convertible", "All Upfront", 39321.0, 0.0),
+                ("3yr", "standard", "All Upfront", 30756.0, 0.0),
+            ]
+        );
+
+        assert!(document.matches(&ProductFilter::new("capacitystatus", "Used")));
+        assert!(document.matches(&ProductFilter::new("OfferingClass", "convertible")));
+        assert!(!document.matches(&ProductFilter::new("tenancy", "Dedicated")));
+
+        assert_eq!(PriceListDocument::parse(&document.to_json()?)?, document);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed() {
+        let err = PriceListDocument::parse("{\"terms\": {}}").unwrap_err();
+        assert!(matches!(err, Error::MalformedPriceList(_)));
+
+        let document = PriceListDocument::parse(
+            r#"{
+                "product": { "sku": "X", "attributes": {} },
+                "terms": { "OnDemand": { "X.JRTCKXETXF": {
+                    "offerTermCode": "JRTCKXETXF",
+                    "sku": "X",
+                    "priceDimensions": { "X.JRTCKXETXF.6YS6EN2CT7": {
+                        "rateCode": "X.JRTCKXETXF.6YS6EN2CT7",
+                        "unit": "Hrs",
+                        "pricePerUnit": { "CNY": "1.0" }
+                    } }
+                } } }
+            }"#,
+        )
+        .unwrap();
+
+        let term = document.on_demand_terms().next().unwrap();
+        assert!(matches!(
+            term.hourly_price(),
+            Err(Error::MalformedPriceList(_))
+        ));
+    }
+}
@@ -1,66 +1,456 @@
-use crate::pricing::regions::region2region;
-use anyhow::{anyhow, Result};
+use crate::error::{Error, Result};
+use crate::normalize::PriceUnit;
+use crate::price_list::PriceListDocument;
+use crate::provider::{ProductFilter, ProductPrices};
+use crate::reserved::{PurchaseOption, ReservedOffering};
+use crate::savings_plans::{
+    lease_length, SavingsPlan, SavingsPlanFilter, SavingsPlanRate, SavingsPlanType,
+};
+use crate::statistics::Metric;
 use aws_sdk_pricing::client;
 use aws_sdk_pricing::types::Filter;
 use aws_sdk_pricing::types::FilterType::TermMatch;
-use serde_json::Value;
-
-mod regions {
-    // FIXME may change
-    // https://docs.aws.amazon.com/AWSEC2/latest/UserGuide/using-regions-availability-zones.html#concepts-available-regions
-    const REGIONS: &[(&str, &str)] = &[
-        ("af-south-1", "Africa (Cape Town)"),
-        ("ap-east-1", "Asia Pacific (Hong Kong)"),
-        ("ap-south-1", "Asia Pacific (Mumbai)"),
-        ("ap-northeast-3", "Asia Pacific (Osaka)"),
-        ("ap-northeast-2", "Asia Pacific (Seoul)"),
-        ("ap-southeast-1", "Asia Pacific (Singapore)"),
-        ("ap-southeast-2", "Asia Pacific (Sydney)"),
-        ("ap-southeast-4", "Asia Pacific (Melbourne)"),
-        ("ap-northeast-1", "Asia Pacific (Tokyo)"),
-        ("ca-central-1", "Canada (Central)"),
-        ("eu-central-1", "EU (Frankfurt)"),
-        ("eu-central-2", "EU (Zürich)"),
-        ("eu-west-1", "EU (Ireland)"),
-        ("eu-west-2", "EU (London)"),
-        ("eu-south-1", "EU (Milan)"),
-        ("eu-south-1", "EU (Spain)"),
-        ("eu-west-3", "EU (Paris)"),
-        ("eu-north-1", "EU (Stockholm)"),
-        ("me-south-1", "Middle East (Bahrain)"),
-        ("sa-east-1", "South America (Sao Paulo)"),
-        ("us-east-2", "US East (Ohio)"),
-        ("us-east-1", "US East (N. Virginia)"),
-        ("us-west-1", "US West (N. California)"),
-        ("us-west-2", "US West (Oregon)"),
-        ("il-central-1", "Isreal (Tel Aviv)"),
-    ];
+use aws_sdk_savingsplans::types::{
+    SavingsPlanOfferingRate, SavingsPlanOfferingRateFilterElement,
+    SavingsPlanPaymentOption as SpPaymentOption, SavingsPlanProductType,
+    SavingsPlanRateFilterAttribute as RateFilter, SavingsPlanType as SpType,
+};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// the pricing term of a price lookup
+pub enum Term {
+    /// pay by the hour without commitment
+    #[default]
+    OnDemand,
+    /// 3yr standard reservation, paid all upfront
+    Reserved,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+/// the operating system of a price lookup
+pub enum OperatingSystem {
+    /// Linux/UNIX, e.g. Amazon Linux
+    #[default]
+    #[serde(alias = "linux")]
+    Linux,
+    /// Red Hat Enterprise Linux
+    #[serde(rename = "RHEL", alias = "rhel")]
+    Rhel,
+    /// SUSE Linux Enterprise Server
+    #[serde(rename = "SUSE", alias = "suse")]
+    Suse,
+    /// Windows Server
+    #[serde(alias = "windows")]
+    Windows,
+    /// Ubuntu Pro
+    #[serde(rename = "Ubuntu Pro", alias = "ubuntu-pro")]
+    UbuntuPro,
+}
+
+impl OperatingSystem {
+    /// the Savings Plan and price list product description, e.g. `Linux/UNIX`
+    pub fn product_description(self) -> &'static str {
+        match self {
+            OperatingSystem::Linux => "Linux/UNIX",
+            OperatingSystem::Rhel => "Red Hat Enterprise Linux",
+            OperatingSystem::Suse => "SUSE Linux",
+            OperatingSystem::Windows => "Windows",
+            OperatingSystem::UbuntuPro => "Ubuntu Pro",
+        }
+    }
+
+    /// the spot price history product description, e.g. `Linux/UNIX`
+    ///
+    /// Ubuntu Pro runs at the Linux spot price, its license fee is billed separately.
+    pub fn spot_product_description(self) -> &'static str {
+        match self {
+            OperatingSystem::UbuntuPro => OperatingSystem::Linux.spot_product_description(),
+            operating_system => operating_system.product_description(),
+        }
+    }
+}
+
+impl FromStr for OperatingSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linux" => Ok(OperatingSystem::Linux),
+            "rhel" => Ok(OperatingSystem::Rhel),
+            "suse" => Ok(OperatingSystem::Suse),
+            "windows" => Ok(OperatingSystem::Windows),
+            "ubuntu-pro" | "ubuntu pro" => Ok(OperatingSystem::UbuntuPro),
+            _ => Err(format!(
+                "unknown operating system: {s}, expected linux, rhel, suse, windows or ubuntu-pro"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OperatingSystem {
+    /// the name in AWS Pricing convention, e.g. `RHEL`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperatingSystem::Linux => f.pad("Linux"),
+            OperatingSystem::Rhel => f.pad("RHEL"),
+            OperatingSystem::Suse => f.pad("SUSE"),
+            OperatingSystem::Windows => f.pad("Windows"),
+            OperatingSystem::UbuntuPro => f.pad("Ubuntu Pro"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+/// the software pre-installed on the image
+pub enum PreInstalledSoftware {
+    /// only the operating system
+    #[default]
+    #[serde(rename = "NA", alias = "none")]
+    None,
+    /// SQL Server Web
+    #[serde(rename = "SQL Web", alias = "sql-web")]
+    SqlWeb,
+    /// SQL Server Standard
+    #[serde(rename = "SQL Std", alias = "sql-std")]
+    SqlStandard,
+    /// SQL Server Enterprise
+    #[serde(rename = "SQL Ent", alias = "sql-ent")]
+    SqlEnterprise,
+}
+
+impl FromStr for PreInstalledSoftware {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" | "na" => Ok(PreInstalledSoftware::None),
+            "sql-web" | "sql web" => Ok(PreInstalledSoftware::SqlWeb),
+            "sql-std" | "sql std" => Ok(PreInstalledSoftware::SqlStandard),
+            "sql-ent" | "sql ent" => Ok(PreInstalledSoftware::SqlEnterprise),
+            _ => Err(format!(
+                "unknown pre-installed software: {s}, expected none, sql-web, sql-std or sql-ent"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PreInstalledSoftware {
+    /// the name in AWS Pricing convention, e.g. `SQL Std`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreInstalledSoftware::None => f.pad("NA"),
+            PreInstalledSoftware::SqlWeb => f.pad("SQL Web"),
+            PreInstalledSoftware::SqlStandard => f.pad("SQL Std"),
+            PreInstalledSoftware::SqlEnterprise => f.pad("SQL Ent"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// how the licenses of the image are paid
+pub enum LicenseModel {
+    /// with the instance, if the image needs one at all
+    #[default]
+    Included,
+    /// bring your own license, only for Windows
+    #[serde(alias = "bring-your-own")]
+    Byol,
+}
+
+impl LicenseModel {
+    /// the `licenseModel` of the price list
+    pub fn attribute(self) -> &'static str {
+        match self {
+            LicenseModel::Included => "No License required",
+            LicenseModel::Byol => "Bring your own license",
+        }
+    }
+}
+
+impl FromStr for LicenseModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "included" => Ok(LicenseModel::Included),
+            "byol" | "bring-your-own" => Ok(LicenseModel::Byol),
+            _ => Err(format!(
+                "unknown license model: {s}, expected included or byol"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LicenseModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicenseModel::Included => f.pad("included"),
+            LicenseModel::Byol => f.pad("byol"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+/// whether instances share their hosts with other accounts
+pub enum Tenancy {
+    /// on hosts shared with other accounts
+    #[default]
+    #[serde(alias = "shared")]
+    Shared,
+    /// on hosts dedicated to the account
+    #[serde(alias = "dedicated")]
+    Dedicated,
+    /// on a dedicated host allocated by the account
+    #[serde(alias = "host")]
+    Host,
+}
+
+impl FromStr for Tenancy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "shared" => Ok(Tenancy::Shared),
+            "dedicated" => Ok(Tenancy::Dedicated),
+            "host" => Ok(Tenancy::Host),
+            _ => Err(format!(
+                "unknown tenancy: {s}, expected shared, dedicated or host"
+            )),
+        }
+    }
+}
 
-    // FIXME from from 1.56
-    pub(super) fn region2region(region: &str) -> String {
-        if let Some((_, r2)) = REGIONS.iter().find(|(r1, _)| *r1 == region) {
-            return r2.to_string();
+impl fmt::Display for Tenancy {
+    /// the name in AWS Pricing convention, e.g. `Shared`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tenancy::Shared => f.pad("Shared"),
+            Tenancy::Dedicated => f.pad("Dedicated"),
+            Tenancy::Host => f.pad("Host"),
         }
-        panic!("unknown region {region}");
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// the product a price is looked up for, besides instance and region
+pub struct PriceQuery {
+    operating_system: OperatingSystem,
+    software: PreInstalledSoftware,
+    license_model: LicenseModel,
+    tenancy: Tenancy,
+    term: Term,
+    savings_plan: Option<SavingsPlan>,
+    unit: PriceUnit,
+    metric: Metric,
+}
+
+impl PriceQuery {
+    /// create a new query without pre-installed software and with included licenses
+    pub fn new(operating_system: OperatingSystem, tenancy: Tenancy, term: Term) -> Self {
+        Self {
+            operating_system,
+            software: PreInstalledSoftware::None,
+            license_model: LicenseModel::Included,
+            tenancy,
+            term,
+            savings_plan: None,
+            unit: PriceUnit::Instance,
+            metric: Metric::Avg,
+        }
+    }
+
+    /// the operating system
+    pub fn operating_system(&self) -> OperatingSystem {
+        self.operating_system
+    }
+
+    /// the software pre-installed on the image
+    pub fn software(&self) -> PreInstalledSoftware {
+        self.software
+    }
+
+    /// how the licenses are paid
+    pub fn license_model(&self) -> LicenseModel {
+        self.license_model
+    }
+
+    /// the tenancy
+    pub fn tenancy(&self) -> Tenancy {
+        self.tenancy
+    }
+
+    /// the pricing term
+    pub fn term(&self) -> Term {
+        self.term
+    }
+
+    /// the Savings Plan whose rates are looked up besides the prices, if any
+    pub fn savings_plan(&self) -> Option<SavingsPlan> {
+        self.savings_plan
+    }
+
+    /// the resource the reported prices are divided by
+    pub fn unit(&self) -> PriceUnit {
+        self.unit
+    }
+
+    /// the statistic of the spot prices that is reported
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// the spot product description, e.g. `Windows`
+    ///
+    /// Spot prices only depend on the operating system, not on pre-installed software.
+    pub fn spot_product_description(&self) -> &'static str {
+        self.operating_system.spot_product_description()
+    }
+
+    /// the Savings Plan product description, e.g. `Windows with SQL Server Standard`
+    pub fn product_description(&self) -> String {
+        let software = match self.software {
+            PreInstalledSoftware::None => {
+                return self.operating_system.product_description().to_string()
+            }
+            PreInstalledSoftware::SqlWeb => "Web",
+            PreInstalledSoftware::SqlStandard => "Standard",
+            PreInstalledSoftware::SqlEnterprise => "Enterprise",
+        };
+        // Linux/UNIX becomes `Linux with SQL Server Web`
+        let operating_system = match self.operating_system {
+            OperatingSystem::Linux => "Linux",
+            operating_system => operating_system.product_description(),
+        };
+
+        format!("{operating_system} with SQL Server {software}")
+    }
+
+    /// the `operation` of the price list, which is unique per image type
+    ///
+    /// Fails with [`Error::Unsupported`] for images AWS does not offer.
+    pub fn operation(&self) -> Result<&'static str> {
+        use LicenseModel::{Byol, Included};
+        use OperatingSystem::{Linux, Rhel, Suse, UbuntuPro, Windows};
+        use PreInstalledSoftware as Sw;
+
+        match (self.operating_system, self.software, self.license_model) {
+            (Linux, Sw::None, Included) => Ok("RunInstances"),
+            (Linux, Sw::SqlStandard, Included) => Ok("RunInstances:0004"),
+            (Linux, Sw::SqlWeb, Included) => Ok("RunInstances:0200"),
+            (Linux, Sw::SqlEnterprise, Included) => Ok("RunInstances:0100"),
+            (Rhel, Sw::None, Included) => Ok("RunInstances:0010"),
+            (Rhel, Sw::SqlStandard, Included) => Ok("RunInstances:0014"),
+            (Rhel, Sw::SqlWeb, Included) => Ok("RunInstances:0210"),
+            (Rhel, Sw::SqlEnterprise, Included) => Ok("RunInstances:0110"),
+            (Suse, Sw::None, Included) => Ok("RunInstances:000g"),
+            (Windows, Sw::None, Included) => Ok("RunInstances:0002"),
+            (Windows, Sw::SqlStandard, Included) => Ok("RunInstances:0006"),
+            (Windows, Sw::SqlWeb, Included) => Ok("RunInstances:0202"),
+            (Windows, Sw::SqlEnterprise, Included) => Ok("RunInstances:0102"),
+            (Windows, Sw::None, Byol) => Ok("RunInstances:0800"),
+            (UbuntuPro, Sw::None, Included) => Ok("RunInstances:0g00"),
+            (operating_system, software, license_model) => Err(Error::Unsupported(format!(
+                "{operating_system} with pre-installed software {software} and {license_model} licenses"
+            ))),
+        }
+    }
+
+    /// the same query with another operating system
+    pub fn with_operating_system(&self, operating_system: OperatingSystem) -> Self {
+        Self {
+            operating_system,
+            ..self.clone()
+        }
+    }
+
+    /// the same query with another tenancy
+    pub fn with_tenancy(&self, tenancy: Tenancy) -> Self {
+        Self {
+            tenancy,
+            ..self.clone()
+        }
+    }
+
+    /// the same query with another term
+    pub fn with_term(&self, term: Term) -> Self {
+        Self {
+            term,
+            ..self.clone()
+        }
+    }
+
+    /// the same query with other pre-installed software
+    pub fn with_software(&self, software: PreInstalledSoftware) -> Self {
+        Self {
+            software,
+            ..self.clone()
+        }
+    }
+
+    /// the same query with another license model
+    pub fn with_license_model(&self, license_model: LicenseModel) -> Self {
+        Self {
+            license_model,
+            ..self.clone()
+        }
+    }
+
+    /// the same query with another Savings Plan
+    pub fn with_savings_plan(&self, savings_plan: Option<SavingsPlan>) -> Self {
+        Self {
+            savings_plan,
+            ..self.clone()
+        }
+    }
+
+    /// the same query reporting prices per another unit
+    pub fn with_unit(&self, unit: PriceUnit) -> Self {
+        Self {
+            unit,
+            ..self.clone()
+        }
+    }
+
+    /// the same query reporting another statistic of the spot prices
+    pub fn with_metric(&self, metric: Metric) -> Self {
+        Self {
+            metric,
+            ..self.clone()
+        }
+    }
+}
+
+impl Default for PriceQuery {
+    fn default() -> Self {
+        Self::new(OperatingSystem::Linux, Tenancy::Shared, Term::OnDemand)
     }
 }
 
 #[derive(Debug)]
-/// A wrapper around an AWS SDK Pricing client
-pub struct Pricing(aws_sdk_pricing::client::Client);
+/// A wrapper around the AWS SDK Pricing and Savings Plans clients
+pub struct Pricing {
+    client: client::Client,
+    savings_plans: aws_sdk_savingsplans::Client,
+}
 
 impl Pricing {
-    /// create new Pricing object taking an AWS Pricing client
+    /// create new Pricing object taking the config of the Pricing region, e.g. `us-east-1`
     pub fn new(config: aws_types::SdkConfig) -> Self {
-        let client = client::Client::new(&config);
-
-        Pricing(client)
+        Pricing {
+            client: client::Client::new(&config),
+            savings_plans: aws_sdk_savingsplans::Client::new(&config),
+        }
     }
 
     /// get all regions in AWS Pricing convention
     pub async fn get_regions(&self) -> Result<Vec<String>> {
         let values = self
-            .0
+            .client
             .get_attribute_values()
             .service_code("AmazonEC2")
             .attribute_name("location")
@@ -79,32 +469,32 @@ impl Pricing {
 
         Ok(regions)
     }
+}
 
-    /// the OnDemand price for instance give a region
-    pub async fn get_ondemand_price2(&self, instance: &str, region: &str) -> Result<f64> {
-        let filters = self.get_ondemand_filters(instance, region);
-        return self.get_price(instance, "OnDemand", &filters).await;
-    }
+impl ProductPrices for Pricing {
+    async fn get_products(&self, filters: &[ProductFilter]) -> Result<Vec<String>> {
+        let filters = filters
+            .iter()
+            .map(|filter| {
+                Filter::builder()
+                    .set_type(Some(TermMatch))
+                    .field(filter.field())
+                    .value(filter.value())
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-    /// the Reservation price for instance give a region
-    pub async fn get_reservation_price(&self, instance: &str, region: &str) -> Result<f64> {
-        let filters = self.get_reservation_filters(instance, region);
-        return self.get_price(instance, "Reserved", &filters).await;
-    }
-
-    /// the OnDemand price for instance give a region
-    pub async fn get_ondemand_price(&self, instance: &str, region: &str) -> Result<f64> {
         let values = self
-            .0
+            .client
             .get_products()
             .service_code("AmazonEC2")
-            .set_filters(Some(self.get_ondemand_filters(instance, region)))
+            .set_filters(Some(filters))
             .into_paginator()
             .send()
             .collect::<Result<Vec<_>, _>>()
             .await?;
 
-        let mut products = Vec::new();
+        let mut products: Vec<String> = Vec::new();
 
         for page in values {
             for product in page.price_list() {
@@ -112,267 +502,381 @@ impl Pricing {
             }
         }
 
-        if products.len() != 1 {
-            return Err(anyhow!("not found: {instance}"));
+        Ok(products)
+    }
+
+    async fn get_savings_plan_rates(
+        &self,
+        filter: &SavingsPlanFilter,
+    ) -> Result<Vec<SavingsPlanRate>> {
+        let filters = [
+            (RateFilter::InstanceType, filter.instance()),
+            (RateFilter::Region, filter.region()),
+            (RateFilter::ProductDescription, filter.product_description()),
+            (RateFilter::Tenancy, filter.tenancy()),
+        ]
+        .into_iter()
+        .map(|(name, value)| {
+            SavingsPlanOfferingRateFilterElement::builder()
+                .name(name)
+                .values(value)
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+        let mut rates: Vec<SavingsPlanRate> = Vec::new();
+        let mut next_token = None;
+
+        // no paginator for the offering rates
+        loop {
+            let page = self
+                .savings_plans
+                .describe_savings_plans_offering_rates()
+                .products(SavingsPlanProductType::Ec2)
+                .savings_plan_types(SpType::Compute)
+                .savings_plan_types(SpType::Ec2Instance)
+                .operations(filter.operation())
+                .set_filters(Some(filters.clone()))
+                .set_next_token(next_token)
+                .send()
+                .await?;
+
+            // several usage types per plan, e.g. of unused capacity reservations
+            for offering_rate in page.search_results().iter().filter(|offering_rate| {
+                offering_rate
+                    .usage_type()
+                    .is_some_and(|usage_type| filter.matches_usage_type(usage_type))
+            }) {
+                rates.push(savings_plan_rate(offering_rate)?);
+            }
+
+            match page.next_token() {
+                Some(token) if !token.is_empty() => next_token = Some(token.to_string()),
+                _ => break,
+            }
         }
 
-        let v: Value = serde_json::from_str(&products[0])?;
+        rates.sort_by_key(SavingsPlanRate::plan);
 
-        let kv = v["terms"]["OnDemand"]
-            .as_object()
-            .unwrap()
-            .into_iter()
-            .collect::<Vec<_>>();
+        Ok(rates)
+    }
+}
 
-        if kv.len() != 1 {
-            return Err(anyhow!("not found: Ondemand"));
-        }
+// the rate of a Savings Plan offering
+fn savings_plan_rate(offering_rate: &SavingsPlanOfferingRate) -> Result<SavingsPlanRate> {
+    let malformed =
+        |what: &str| Error::MalformedResponse(format!("savings plan rate without {what}"));
+
+    let offering = offering_rate
+        .savings_plan_offering()
+        .ok_or_else(|| malformed("offering"))?;
+
+    let plan_type = match offering.plan_type() {
+        Some(SpType::Compute) => SavingsPlanType::Compute,
+        Some(SpType::Ec2Instance) => SavingsPlanType::Ec2Instance,
+        _ => return Err(malformed("EC2 plan type")),
+    };
+    let payment_option = match offering.payment_option() {
+        Some(SpPaymentOption::NoUpfront) => PurchaseOption::NoUpfront,
+        Some(SpPaymentOption::PartialUpfront) => PurchaseOption::PartialUpfront,
+        Some(SpPaymentOption::AllUpfront) => PurchaseOption::AllUpfront,
+        _ => return Err(malformed("payment option")),
+    };
+    let plan = SavingsPlan::new(
+        plan_type,
+        lease_length(offering.duration_seconds())?,
+        payment_option,
+    );
+
+    let rate = offering_rate.rate().ok_or_else(|| malformed("rate"))?;
+    let rate = rate
+        .parse::<f64>()
+        .map_err(|err| Error::MalformedResponse(format!("savings plan rate {rate}: {err}")))?;
+
+    Ok(SavingsPlanRate::new(plan, rate))
+}
 
-        let kvs = kv[0].1["priceDimensions"]
-            .as_object()
-            .unwrap()
-            .into_iter()
-            .collect::<Vec<_>>();
+// the filters of every instance type in every region
+fn get_image_filters(query: &PriceQuery, capacity_status: &str) -> Result<Vec<ProductFilter>> {
+    Ok(vec![
+        ProductFilter::new("operatingSystem", &query.operating_system().to_string()),
+        ProductFilter::new("preInstalledSw", &query.software().to_string()),
+        ProductFilter::new("licenseModel", query.license_model().attribute()),
+        ProductFilter::new("operation", query.operation()?),
+        ProductFilter::new("capacitystatus", capacity_status),
+        ProductFilter::new("tenancy", &query.tenancy().to_string()),
+    ])
+}
 
-        if kvs.len() != 1 {
-            return Err(anyhow!("not found: price"));
-        }
+fn get_common_filters(
+    instance: &str,
+    region: &str,
+    query: &PriceQuery,
+    capacity_status: &str,
+) -> Result<Vec<ProductFilter>> {
+    // every product carries the region code besides the location, e.g. `US East (Ohio)`
+    let mut filters = vec![
+        ProductFilter::new("instanceType", instance),
+        ProductFilter::new("regionCode", region),
+    ];
+    filters.append(&mut get_image_filters(query, capacity_status)?);
+    Ok(filters)
+}
 
-        let mut value = kvs[0].1["pricePerUnit"]["USD"].to_string();
-        value.pop();
+/// the on-demand filters of every instance type in every region
+pub(crate) fn get_catalog_filters(query: &PriceQuery) -> Result<Vec<ProductFilter>> {
+    get_image_filters(query, "UnusedCapacityReservation")
+}
 
-        return Ok(value.strip_prefix('\"').unwrap().parse::<f64>()?);
-    }
+pub(crate) fn get_ondemand_filters(
+    instance: &str,
+    region: &str,
+    query: &PriceQuery,
+) -> Result<Vec<ProductFilter>> {
+    get_common_filters(instance, region, query, "UnusedCapacityReservation")
+}
 
-    fn get_common_filters(
-        &self,
-        instance: &str,
-        region: &str,
-        capacity_status: &str,
-        tenancy: &str,
-    ) -> Vec<Filter> {
-        let location = region2region(region);
-        vec![
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("instanceType")
-                .value(instance)
-                .build()
-                .unwrap(),
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("location")
-                .value(location)
-                .build()
-                .unwrap(),
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("operatingSystem")
-                .value("Linux")
-                .build()
-                .unwrap(),
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("preInstalledSw")
-                .value("NA")
-                .build()
-                .unwrap(),
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("operation")
-                .value("RunInstances")
-                .build()
-                .unwrap(),
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("capacitystatus")
-                .value(capacity_status)
-                .build()
-                .unwrap(),
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("tenancy")
-                .value(tenancy)
-                .build()
-                .unwrap(),
-        ]
+pub(crate) fn get_reserved_offering_filters(
+    instance: &str,
+    region: &str,
+    query: &PriceQuery,
+) -> Result<Vec<ProductFilter>> {
+    get_common_filters(instance, region, query, "Used")
+}
+
+pub(crate) fn get_reservation_filters(
+    instance: &str,
+    region: &str,
+    query: &PriceQuery,
+) -> Result<Vec<ProductFilter>> {
+    let mut filters = get_reserved_offering_filters(instance, region, query)?;
+    let mut remainder = vec![
+        ProductFilter::new("LeaseContractLength", "3yr"),
+        ProductFilter::new("OfferingClass", "standard"),
+        ProductFilter::new("PurchaseOption", "All Upfront"),
+    ];
+    filters.append(&mut remainder);
+    Ok(filters)
+}
+
+// exactly one product must match the filters
+fn check_unique(instance: &str, products: &[String]) -> Result<()> {
+    match products.len() {
+        1 => Ok(()),
+        0 => Err(Error::NotOffered(instance.to_string())),
+        count => Err(Error::AmbiguousProduct {
+            instance: instance.to_string(),
+            count,
+        }),
     }
-    fn get_ondemand_filters(&self, instance: &str, region: &str) -> Vec<Filter> {
-        //let location = self.region2region(region);
-        //vec![
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("instanceType".to_string()))
-        //        .set_value(Some(instance.to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("location".to_string()))
-        //        .set_value(Some(location))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("operatingSystem".to_string()))
-        //        .set_value(Some("Linux".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("preInstalledSw".to_string()))
-        //        .set_value(Some("NA".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("operation".to_string()))
-        //        .set_value(Some("RunInstances".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("capacitystatus".to_string()))
-        //        .set_value(Some("UnusedCapacityReservation".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("tenancy".to_string()))
-        //        .set_value(Some("Shared".to_string()))
-        //        .build(),
-        //]
-        self.get_common_filters(instance, region, "UnusedCapacityReservation", "Shared")
-    }
-
-    fn get_reservation_filters(&self, instance: &str, region: &str) -> Vec<Filter> {
-        let mut filters = self.get_common_filters(instance, region, "Used", "Dedicated");
-        let mut remainder = vec![
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("LeaseContractLength")
-                .value("3yr")
-                .build()
-                .unwrap(),
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("OfferingClass")
-                .value("standard")
-                .build()
-                .unwrap(),
-            Filter::builder()
-                .set_type(Some(TermMatch))
-                .field("PurchaseOption")
-                .value("All Upfront")
-                .build()
-                .unwrap(),
-        ];
-        filters.append(&mut remainder);
-        filters
-
-        //vec![
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("instanceType".to_string()))
-        //        .set_value(Some(instance.to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("location".to_string()))
-        //        .set_value(Some(location))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("operatingSystem".to_string()))
-        //        .set_value(Some("Linux".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("preInstalledSw".to_string()))
-        //        .set_value(Some("NA".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("operation".to_string()))
-        //        .set_value(Some("RunInstances".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("capacitystatus".to_string()))
-        //        .set_value(Some("Used".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("tenancy".to_string()))
-        //        .set_value(Some("Dedicated".to_string()))
-        //        .build(),
-        //    //self.get_common_filters(instance, region, "Used", "Dedicated")
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("LeaseContractLength".to_string()))
-        //        .set_value(Some("3yr".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("OfferingClass".to_string()))
-        //        .set_value(Some("standard".to_string()))
-        //        .build(),
-        //    Filter::builder()
-        //        .set_type(Some(FilterType::TermMatch))
-        //        .set_field(Some("PurchaseOption".to_string()))
-        //        .set_value(Some("All Upfront".to_string()))
-        //        .build(),
-        ////]
-    }
-
-    /// the price for instance give a region
-    async fn get_price(&self, instance: &str, price_kind: &str, filters: &[Filter]) -> Result<f64> {
-        let values = self
-            .0
-            .get_products()
-            .service_code("AmazonEC2")
-            .set_filters(Some(filters.to_vec()))
-            .into_paginator()
-            .send()
-            .collect::<Result<Vec<_>, _>>()
-            .await?;
+}
 
-        let mut products: Vec<String> = Vec::new();
+// the document of the only product
+fn parse_unique(instance: &str, products: &[String]) -> Result<PriceListDocument> {
+    check_unique(instance, products)?;
 
-        for product in values {
-            for item in product.price_list() {
-                products.push(item.clone());
-            }
-        }
+    PriceListDocument::parse(&products[0])
+}
 
-        if products.len() != 1 {
-            return Err(anyhow!("not found: {instance}"));
-        }
+/// the OnDemand price of the only product
+pub(crate) fn ondemand_price(instance: &str, products: &[String]) -> Result<f64> {
+    let document = parse_unique(instance, products)?;
+
+    let terms = document.on_demand_terms().collect::<Vec<_>>();
+    match terms[..] {
+        [term] => term.hourly_price(),
+        [] => Err(Error::MissingTerm {
+            instance: instance.to_string(),
+            term: "OnDemand".to_string(),
+        }),
+        _ => Err(Error::MalformedPriceList(format!(
+            "{} OnDemand terms for {instance}",
+            terms.len()
+        ))),
+    }
+}
 
-        let v: Value = serde_json::from_str(&products[0])?;
+/// the effective hourly rate of the 3yr standard All Upfront reservation of the only product
+pub(crate) fn reservation_price(instance: &str, products: &[String]) -> Result<f64> {
+    let document = parse_unique(instance, products)?;
+
+    // the filters select the term, but the document has all terms of the product
+    let term = document
+        .reserved_terms()
+        .find(|term| {
+            term.lease_contract_length() == Some("3yr")
+                && term.offering_class() == Some("standard")
+                && term.purchase_option() == Some("All Upfront")
+        })
+        .ok_or_else(|| Error::MissingTerm {
+            instance: instance.to_string(),
+            term: "Reserved 3yr standard All Upfront".to_string(),
+        })?;
+
+    Ok(ReservedOffering::from_term(term)?.effective_hourly_rate())
+}
 
-        let kv = v["terms"][price_kind]
-            .as_object()
-            .unwrap()
-            .into_iter()
-            .collect::<Vec<_>>();
+/// every reserved offering of the only product, by lease length, class and purchase option
+pub(crate) fn reserved_offerings(
+    instance: &str,
+    products: &[String],
+) -> Result<Vec<ReservedOffering>> {
+    let document = parse_unique(instance, products)?;
+
+    let mut offerings = document
+        .reserved_terms()
+        .map(ReservedOffering::from_term)
+        .collect::<Result<Vec<_>>>()?;
+
+    if offerings.is_empty() {
+        return Err(Error::MissingTerm {
+            instance: instance.to_string(),
+            term: "Reserved".to_string(),
+        });
+    }
 
-        let kvs = kv[0].1["priceDimensions"]
-            .as_object()
-            .unwrap()
-            .into_iter()
-            .collect::<Vec<_>>();
+    offerings.sort_by_key(|offering| {
+        (
+            offering.lease_contract_length(),
+            offering.offering_class(),
+            offering.purchase_option(),
+        )
+    });
 
-        if kvs.len() != 2 {
-            println!("v = {v}");
-            println!("kv = {kv:?}");
-            println!("kvs = {kvs:?}");
-            println!("kvs.len() = {}", kvs.len());
-            return Err(anyhow!("not found: price"));
-        }
+    Ok(offerings)
+}
 
-        let mut value = kvs[0].1["pricePerUnit"]["USD"].to_string();
-        value.pop();
-        return Ok(value.strip_prefix('\"').unwrap().parse::<f64>()?);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    const ONDEMAND: &str =
+        include_str!("../testdata/price_list/c7g.16xlarge-us-east-2-ondemand.json");
+    const RESERVED: &str =
+        include_str!("../testdata/price_list/c7g.16xlarge-us-east-2-reserved.json");
+
+    #[test]
+    fn test_query() -> Result<()> {
+        let linux = PriceQuery::default();
+        assert_eq!(linux.product_description(), "Linux/UNIX");
+        assert_eq!(linux.operation()?, "RunInstances");
+
+        let windows = linux
+            .with_operating_system(OperatingSystem::Windows)
+            .with_software(PreInstalledSoftware::SqlStandard)
+            .with_tenancy(Tenancy::Dedicated);
+        assert_eq!(
+            windows.product_description(),
+            "Windows with SQL Server Standard"
+        );
+        assert_eq!(windows.spot_product_description(), "Windows");
+
+        let filters = get_ondemand_filters("c7i.48xlarge", "us-east-1", &windows)?
+            .iter()
+            .map(|f| format!("{}={}", f.field(), f.value()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            filters,
+            vec![
+                "instanceType=c7i.48xlarge",
+                "regionCode=us-east-1",
+                "operatingSystem=Windows",
+                "preInstalledSw=SQL Std",
+                "licenseModel=No License required",
+                "operation=RunInstances:0006",
+                "capacitystatus=UnusedCapacityReservation",
+                "tenancy=Dedicated",
+            ]
+        );
+
+        let byol = windows.with_license_model(LicenseModel::Byol);
+        assert!(matches!(byol.operation(), Err(Error::Unsupported(_))));
+        let byol = byol.with_software(PreInstalledSoftware::None);
+        assert_eq!(byol.operation()?, "RunInstances:0800");
+
+        assert_eq!(
+            "ubuntu-pro".parse::<OperatingSystem>(),
+            Ok(OperatingSystem::UbuntuPro)
+        );
+        let ubuntu_pro = linux.with_operating_system(OperatingSystem::UbuntuPro);
+        assert_eq!(ubuntu_pro.product_description(), "Ubuntu Pro");
+        assert_eq!(ubuntu_pro.spot_product_description(), "Linux/UNIX");
+        assert_eq!(
+            OperatingSystem::Rhel.spot_product_description(),
+            "Red Hat Enterprise Linux"
+        );
+        assert_eq!(
+            "sql-ent".parse::<PreInstalledSoftware>(),
+            Ok(PreInstalledSoftware::SqlEnterprise)
+        );
+        assert!("solaris".parse::<OperatingSystem>().is_err());
+
+        Ok(())
     }
-}
 
-fn _print_filters(filters: &[Filter]) {
-    for filter in filters {
-        println!("{} = {}", filter.field, filter.value);
+    #[test]
+    fn test_prices() -> Result<()> {
+        let instance = "c7g.16xlarge";
+
+        assert_eq!(ondemand_price(instance, &[ONDEMAND.to_string()])?, 2.312);
+        assert_eq!(
+            reservation_price(instance, &[RESERVED.to_string()])?,
+            30756.0 / 26280.0
+        );
+
+        assert!(matches!(
+            reservation_price(instance, &[ONDEMAND.to_string()]),
+            Err(Error::MissingTerm { .. })
+        ));
+        assert!(matches!(
+            ondemand_price(instance, &[ONDEMAND.to_string(), RESERVED.to_string()]),
+            Err(Error::AmbiguousProduct { count: 2, .. })
+        ));
+        assert!(matches!(
+            ondemand_price(instance, &[]),
+            Err(Error::NotOffered(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reserved_offerings() -> Result<()> {
+        let offerings = reserved_offerings("c7g.16xlarge", &[RESERVED.to_string()])?;
+
+        let keys = offerings
+            .iter()
+            .map(|o| {
+                format!(
+                    "{} {} {}",
+                    o.lease_contract_length(),
+                    o.offering_class(),
+                    o.purchase_option()
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                "1yr standard No Upfront",
+                "3yr standard Partial Upfront",
+                "3yr standard All Upfront",
+                "3yr convertible All Upfront",
+            ]
+        );
+
+        assert_eq!(offerings[0].effective_hourly_rate(), 1.457);
+        assert_eq!(offerings[2].upfront_fee(), 30756.0);
+        assert_eq!(offerings[2].effective_hourly_rate(), 30756.0 / 26280.0);
+        assert!((offerings[1].effective_hourly_rate() - (0.623 + 16363.0 / 26280.0)).abs() < 1e-12);
+
+        assert!(matches!(
+            reserved_offerings("c7g.16xlarge", &[ONDEMAND.to_string()]),
+            Err(Error::MissingTerm { .. })
+        ));
+
+        Ok(())
     }
 }
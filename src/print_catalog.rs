@@ -0,0 +1,146 @@
+use crate::error::Result;
+use crate::output::{OutputFormat, Table};
+use crate::price_list::PriceListDocument;
+use crate::pricing::{get_catalog_filters, PriceQuery};
+use crate::provider::{ProductFilter, ProductPrices};
+use serde::Serialize;
+
+const HEADER: &[&str] = &["region", "instance", "vcpus", "on-demand", "per vcpu"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+// the on-demand price of an instance type in a region, per vCPU
+struct VcpuPrice {
+    region: String,
+    instance_type: String,
+    vcpus: u32,
+    ondemand: f64,
+    per_vcpu: f64,
+}
+
+// the price per vCPU of an instance product, none for products without vCPUs or price
+fn vcpu_price(document: &PriceListDocument) -> Result<Option<VcpuPrice>> {
+    let product = document.product();
+    let (Some(region), Some(instance), Some(vcpus)) = (
+        product.attribute("regionCode"),
+        product.attribute("instanceType"),
+        product.attribute("vcpu"),
+    ) else {
+        return Ok(None);
+    };
+    let Ok(vcpus) = vcpus.parse::<u32>() else {
+        return Ok(None);
+    };
+    let Some(term) = document.on_demand_terms().next() else {
+        return Ok(None);
+    };
+
+    let ondemand = term.hourly_price()?;
+    if vcpus == 0 || ondemand <= 0.0 {
+        return Ok(None);
+    }
+
+    Ok(Some(VcpuPrice {
+        region: region.to_string(),
+        instance_type: instance.to_string(),
+        vcpus,
+        ondemand,
+        per_vcpu: ondemand / f64::from(vcpus),
+    }))
+}
+
+// the on-demand prices of all instance types, cheapest per vCPU first
+async fn vcpu_prices<P: ProductPrices>(
+    pricing: &P,
+    query: &PriceQuery,
+    region: Option<&str>,
+) -> Result<Vec<VcpuPrice>> {
+    let mut filters = get_catalog_filters(query)?;
+    if let Some(region) = region {
+        filters.push(ProductFilter::new("regionCode", region));
+    }
+
+    let mut prices = Vec::new();
+    for product in pricing.get_products(&filters).await? {
+        if let Some(price) = vcpu_price(&PriceListDocument::parse(&product)?)? {
+            prices.push(price);
+        }
+    }
+
+    prices.sort_by(|a, b| {
+        a.per_vcpu
+            .total_cmp(&b.per_vcpu)
+            .then_with(|| a.region.cmp(&b.region))
+            .then_with(|| a.instance_type.cmp(&b.instance_type))
+    });
+
+    Ok(prices)
+}
+
+fn table(prices: &[VcpuPrice]) -> Table {
+    let mut table = Table::new(HEADER);
+
+    for price in prices {
+        table.push(vec![
+            price.region.clone(),
+            price.instance_type.clone(),
+            price.vcpus.to_string(),
+            format!("{:.5}", price.ondemand),
+            format!("{:.5}", price.per_vcpu),
+        ]);
+    }
+
+    table
+}
+
+/// print the `top` cheapest instance types by on-demand price per vCPU, in a region or all
+///
+/// This scans the whole catalog, best answered from a local price list index.
+pub async fn print_vcpu_prices<P: ProductPrices>(
+    pricing: &P,
+    query: &PriceQuery,
+    region: Option<&str>,
+    top: usize,
+    output: OutputFormat,
+) -> Result<()> {
+    let mut prices = vcpu_prices(pricing, query, region).await?;
+    prices.truncate(top);
+
+    match output {
+        OutputFormat::Text => print!("{}", table(&prices).to_text()),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&prices)?),
+        OutputFormat::Csv | OutputFormat::Markdown => {
+            print!("{}", table(&prices).render(output)?);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bulk::PriceListIndex;
+    use pretty_assertions::assert_eq;
+
+    const OFFER_FILE: &str = include_str!("../testdata/offer_file/AmazonEC2-us-east-2.json");
+
+    #[tokio::test]
+    async fn test_vcpu_prices() -> Result<()> {
+        let index = PriceListIndex::in_memory()?;
+        index.ingest_json(OFFER_FILE.as_bytes())?;
+
+        let prices = vcpu_prices(&index, &PriceQuery::default(), None).await?;
+        assert_eq!(
+            table(&prices).render(OutputFormat::Csv)?,
+            "region,instance,vcpus,on-demand,per vcpu\n\
+             us-east-2,c7g.16xlarge,64,2.31200,0.03612\n\
+             us-east-2,c7g.8xlarge,32,1.15600,0.03612\n"
+        );
+
+        let prices = vcpu_prices(&index, &PriceQuery::default(), Some("eu-north-1")).await?;
+        assert!(prices.is_empty());
+
+        Ok(())
+    }
+}
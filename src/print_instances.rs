@@ -1,20 +1,24 @@
-use crate::describe_instance;
+use crate::diagnostics::{Diagnostics, Reason};
+use crate::error::{Error, Result};
 use crate::get_integer_with_len;
-use crate::get_region_config;
 use crate::get_string_network_and_len;
 use crate::get_string_with_dot_and_len;
 use crate::get_string_with_len;
 use crate::get_string_with_len_and_padding;
-use anyhow::Result;
+use crate::normalize::Specs;
+use crate::output::{OutputFormat, Table};
+use crate::provider::InstanceCatalog;
 use aws_sdk_ec2::types::ArchitectureType;
+use aws_sdk_ec2::types::EphemeralNvmeSupport;
 use aws_sdk_ec2::types::InstanceType;
 use aws_sdk_ec2::types::InstanceTypeInfo;
 use aws_sdk_ec2::types::NetworkInfo;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 
 fn get_nr_of_efas(info: &NetworkInfo) -> i32 {
     if let Some(efa_info) = info.efa_info() {
-        efa_info.maximum_efa_interfaces().unwrap()
+        efa_info.maximum_efa_interfaces().unwrap_or_default()
     } else {
         0
     }
@@ -75,11 +79,12 @@ impl Gpu {
 #[derive(Clone)]
 struct InstanceStorage {
     gb: Option<i64>,
+    nvme: bool,
 }
 
 impl InstanceStorage {
-    fn new(gb: Option<i64>) -> Self {
-        Self { gb }
+    fn new(gb: Option<i64>, nvme: bool) -> Self {
+        Self { gb, nvme }
     }
 }
 
@@ -100,17 +105,19 @@ impl Network {
 struct Cpu {
     name: InstanceType,
     arch: String,
+    vcpus: i32,
     cores: i32,
-    memory: i64,
+    memory_mib: i64,
 }
 
 impl Cpu {
-    fn new(name: InstanceType, arch: &str, cores: i32, memory: i64) -> Self {
+    fn new(name: InstanceType, arch: &str, vcpus: i32, cores: i32, memory_mib: i64) -> Self {
         Self {
             name,
             arch: arch.to_string(),
+            vcpus,
             cores,
-            memory,
+            memory_mib,
         }
     }
 }
@@ -153,7 +160,7 @@ impl Instance {
     }
 
     fn memory(&self) -> i64 {
-        self.cpu.memory
+        self.cpu.memory_mib / 1024
     }
 
     fn network_performance(&self) -> String {
@@ -179,6 +186,19 @@ impl Instance {
     fn instance_storage(&self) -> InstanceStorage {
         self.instance_storage.clone()
     }
+
+    // the resources prices are normalized by
+    fn specs(&self) -> Specs {
+        let storage = &self.instance_storage;
+
+        Specs::new(
+            self.cpu.vcpus,
+            self.cores(),
+            self.cpu.memory_mib,
+            self.gpus.iter().map(Gpu::count).sum(),
+            storage.gb.filter(|_| storage.nvme),
+        )
+    }
 }
 
 impl Ord for Instance {
@@ -214,82 +234,113 @@ impl PartialEq for Instance {
 
 impl Eq for Instance {}
 
-fn get_instance(info: &InstanceTypeInfo, instance: &InstanceType) -> Instance {
-    let arch = get_architecture(info.processor_info().unwrap().supported_architectures());
+// the error of an instance type description without a field
+fn missing(instance: &InstanceType, field: &str) -> Error {
+    Error::MalformedResponse(format!("{instance} has no {field}"))
+}
+
+fn get_instance(info: &InstanceTypeInfo, instance: &InstanceType) -> Result<Instance> {
+    let processor = info
+        .processor_info()
+        .ok_or_else(|| missing(instance, "processor info"))?;
+    let arch = get_architecture(processor.supported_architectures());
     let mut gpu_vec = Vec::new();
     if let Some(gpu_info) = info.gpu_info() {
         for gpu in gpu_info.gpus() {
             gpu_vec.push(Gpu::new(
-                gpu.manufacturer().unwrap(),
-                gpu.name().unwrap(),
-                gpu.count().unwrap(),
-                gpu.memory_info().unwrap().size_in_mib().unwrap() / 1024,
+                gpu.manufacturer()
+                    .ok_or_else(|| missing(instance, "GPU manufacturer"))?,
+                gpu.name().ok_or_else(|| missing(instance, "GPU name"))?,
+                gpu.count().ok_or_else(|| missing(instance, "GPU count"))?,
+                gpu.memory_info()
+                    .and_then(|memory| memory.size_in_mib())
+                    .ok_or_else(|| missing(instance, "GPU memory"))?
+                    / 1024,
             ));
         }
     }
 
     let is;
     if let Some(storage) = info.instance_storage_info() {
-        is = InstanceStorage::new(storage.total_size_in_gb());
+        let nvme = storage.nvme_support() != Some(&EphemeralNvmeSupport::Unsupported);
+        is = InstanceStorage::new(storage.total_size_in_gb(), nvme);
     } else {
-        is = InstanceStorage::new(None);
+        is = InstanceStorage::new(None, false);
     }
 
     let cpu = Cpu::new(
         instance.clone(),
         &arch,
-        info.v_cpu_info().unwrap().default_cores().unwrap(),
-        info.memory_info().unwrap().size_in_mib().unwrap() / 1024,
+        info.v_cpu_info()
+            .and_then(|cpu| cpu.default_v_cpus())
+            .ok_or_else(|| missing(instance, "default vCPUs"))?,
+        info.v_cpu_info()
+            .and_then(|cpu| cpu.default_cores())
+            .ok_or_else(|| missing(instance, "default cores"))?,
+        info.memory_info()
+            .and_then(|memory| memory.size_in_mib())
+            .ok_or_else(|| missing(instance, "memory"))?,
     );
 
+    let network_info = info
+        .network_info()
+        .ok_or_else(|| missing(instance, "network info"))?;
     let network = Network::new(
-        info.network_info().unwrap().network_performance().unwrap(),
-        get_nr_of_efas(info.network_info().unwrap()),
+        network_info
+            .network_performance()
+            .ok_or_else(|| missing(instance, "network performance"))?,
+        get_nr_of_efas(network_info),
     );
 
-    Instance::new(
+    Ok(Instance::new(
         cpu,
         network,
         info.ebs_info()
-            .unwrap()
-            .ebs_optimized_info()
-            .unwrap()
-            .maximum_bandwidth_in_mbps()
-            .unwrap()
+            .and_then(|ebs| ebs.ebs_optimized_info())
+            .and_then(|ebs| ebs.maximum_bandwidth_in_mbps())
+            .ok_or_else(|| missing(instance, "EBS bandwidth"))?
             / 1024,
         &gpu_vec,
         is,
-    )
+    ))
 }
 
-// "us-east-1"
-async fn collect(instances: &[InstanceType]) -> Vec<Instance> {
-    let shared_config = get_region_config("us-east-1").await;
-    let shared_config2 = get_region_config("us-east-2").await;
-    let shared_config3 = get_region_config("us-west-2").await;
-
-    let ec2 = aws_sdk_ec2::Client::new(&shared_config);
-    let ec2_2 = aws_sdk_ec2::Client::new(&shared_config2);
-    let ec2_3 = aws_sdk_ec2::Client::new(&shared_config3);
+// not every instance type is offered in every region
+const REGIONS: &[&str] = &["us-east-1", "us-east-2", "us-west-2"];
 
+async fn collect<C: InstanceCatalog>(
+    catalog: &C,
+    instances: &[InstanceType],
+    diagnostics: &Diagnostics,
+) -> Vec<Instance> {
     let mut vec = Vec::new();
 
-    for instance in instances {
-        if let Ok(info) = describe_instance(&ec2, instance.clone()).await {
-            let instance = get_instance(&info, instance);
-            vec.push(instance);
-        } else if let Ok(info) = describe_instance(&ec2_2, instance.clone()).await {
-            let instance = get_instance(&info, instance);
-            vec.push(instance);
-        } else if let Ok(info) = describe_instance(&ec2_3, instance.clone()).await {
-            let instance = get_instance(&info, instance);
-            vec.push(instance);
-        } else {
-            println!("failed for {}", instance.as_str());
+    'instances: for instance in instances {
+        let mut reason = Reason::NotOffered;
+        let mut errors = Vec::new();
+        for region in REGIONS {
+            match catalog
+                .describe_instance(region, instance)
+                .await
+                .and_then(|info| get_instance(&info, instance))
+            {
+                Ok(instance) => {
+                    vec.push(instance);
+                    continue 'instances;
+                }
+                Err(err) => {
+                    // a real failure explains more than an unknown instance
+                    if Reason::of(&err) != Reason::NotOffered {
+                        reason = Reason::of(&err);
+                    }
+                    errors.push(format!("{region}: {err}"));
+                }
+            }
         }
+        diagnostics.record(None, instance.as_str(), reason, &errors.join("; "));
     }
 
-    vec.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    vec.sort_unstable();
 
     // largest in front
     vec.reverse();
@@ -298,11 +349,51 @@ async fn collect(instances: &[InstanceType]) -> Vec<Instance> {
 }
 
 /// print information about EC2 instances
-pub async fn print_instances(instances: &[InstanceType]) -> Result<()> {
-    let instance_data = collect(instances).await;
-    print(&instance_data);
+///
+/// Returns why instances are missing from the table.
+pub async fn print_instances<C: InstanceCatalog>(
+    catalog: &C,
+    instances: &[InstanceType],
+    output: OutputFormat,
+) -> Result<Diagnostics> {
+    let diagnostics = Diagnostics::new();
+    let instance_data = collect(catalog, instances, &diagnostics).await;
+
+    match output {
+        OutputFormat::Text => print(&instance_data),
+        OutputFormat::Csv | OutputFormat::Markdown => {
+            print!("{}", table(&instance_data).render(output)?);
+        }
+        OutputFormat::Json => {
+            return Err(Error::Unsupported(
+                "the instance table has no JSON output".to_string(),
+            ))
+        }
+    }
 
-    Ok(())
+    Ok(diagnostics)
+}
+
+/// the resources of instances by name, to normalize prices by
+pub async fn collect_specs<C: InstanceCatalog>(
+    catalog: &C,
+    instances: &[InstanceType],
+    diagnostics: &Diagnostics,
+) -> HashMap<String, Specs> {
+    collect(catalog, instances, diagnostics)
+        .await
+        .iter()
+        .map(|instance| (instance.name().to_string(), instance.specs()))
+        .collect()
+}
+
+/// the table of instances for reports
+pub(crate) async fn instance_table<C: InstanceCatalog>(
+    catalog: &C,
+    instances: &[InstanceType],
+    diagnostics: &Diagnostics,
+) -> Table {
+    table(&collect(catalog, instances, diagnostics).await)
 }
 
 const TOP_LINE: &[(&str, usize)] = &[
@@ -364,3 +455,109 @@ fn print(instances: &[Instance]) {
         println!()
     }
 }
+
+// one row per GPU model, the other columns are repeated
+fn table(instances: &[Instance]) -> Table {
+    let header = TOP_LINE.iter().map(|element| element.0).collect::<Vec<_>>();
+    let mut table = Table::new(&header);
+
+    for instance in instances {
+        let columns = vec![
+            instance.name().to_string(),
+            instance.arch().to_string(),
+            instance.cores().to_string(),
+            instance.memory().to_string(),
+            instance.network_performance(),
+            instance.get_efas().to_string(),
+            instance.ebs().to_string(),
+        ];
+
+        let storage = instance
+            .instance_storage()
+            .gb
+            .map(|gb| gb.to_string())
+            .unwrap_or_default();
+
+        let gpus = instance
+            .gpus()
+            .iter()
+            .map(|gpu| {
+                vec![
+                    gpu.manufacturer().to_string(),
+                    gpu.name().to_string(),
+                    gpu.count().to_string(),
+                    gpu.memory().to_string(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        if gpus.is_empty() {
+            let mut row = columns;
+            row.extend(vec![String::new(); 4]);
+            row.push(storage);
+            table.push(row);
+        } else {
+            for gpu in gpus {
+                let mut row = columns.clone();
+                row.extend(gpu);
+                row.push(storage.clone());
+                table.push(row);
+            }
+        }
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn instance(name: &str, gpus: &[Gpu]) -> Instance {
+        Instance::new(
+            Cpu::new(InstanceType::from(name), "X86", 96, 48, 768 * 1024),
+            Network::new("3200 Gigabit", 32),
+            78,
+            gpus,
+            InstanceStorage::new(Some(30400), true),
+        )
+    }
+
+    #[test]
+    fn test_table() -> Result<()> {
+        let instances = [
+            instance(
+                "p5.48xlarge",
+                &[
+                    Gpu::new("NVIDIA", "H100", 8, 80),
+                    Gpu::new("NVIDIA", "T4", 1, 16),
+                ],
+            ),
+            instance("c7i.48xlarge", &[]),
+        ];
+
+        assert_eq!(
+            table(&instances).render(OutputFormat::Csv)?,
+            "instance,arch,cores,memory,network,EFA,EBS,vendor,model,number,memory,storage\n\
+             p5.48xlarge,X86,48,768,3200 Gigabit,32,78,NVIDIA,H100,8,80,30400\n\
+             p5.48xlarge,X86,48,768,3200 Gigabit,32,78,NVIDIA,T4,1,16,30400\n\
+             c7i.48xlarge,X86,48,768,3200 Gigabit,32,78,,,,,30400\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_specs() {
+        let p5 = instance(
+            "p5.48xlarge",
+            &[
+                Gpu::new("NVIDIA", "H100", 8, 80),
+                Gpu::new("NVIDIA", "T4", 1, 16),
+            ],
+        );
+        assert_eq!(p5.specs(), Specs::new(96, 48, 768 * 1024, 9, Some(30400)));
+    }
+}
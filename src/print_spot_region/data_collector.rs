@@ -1,102 +1,239 @@
 use crate::availability_zone::AvailabilityZone;
-use crate::get_spot_price_history;
+use crate::diagnostics::{Diagnostics, Reason};
+use crate::error::Result;
 use crate::instance::Instance;
-use crate::pricing::Pricing;
+use crate::pricing::PriceQuery;
 use crate::print_spot_region::spot_region::SpotRegion;
-use anyhow::Result;
+use crate::provider::{ProductPrices, SpotPriceHistory, TimeWindow, Zone};
+use crate::statistics::SpotStatistics;
 use aws_sdk_ec2::primitives::DateTime;
 use aws_sdk_ec2::types::InstanceType;
+use futures::future::{join, join_all, try_join, try_join_all};
+use std::time::SystemTime;
 
 pub(super) struct DataCollector {
     region: String,
-    zones: Vec<String>,
+    zones: Vec<Zone>,
     instances: Vec<InstanceType>,
+    window: TimeWindow,
 }
 
 impl DataCollector {
-    pub(super) fn new(region: &str, zones: &[String], instances: &[InstanceType]) -> Self {
+    pub(super) fn new(
+        region: &str,
+        zones: &[Zone],
+        instances: &[InstanceType],
+        window: &TimeWindow,
+    ) -> Self {
         DataCollector {
             region: region.to_string(),
             zones: zones.to_vec(),
             instances: instances.to_vec(),
+            window: *window,
         }
     }
 
-    pub(super) async fn get_region(
+    pub(super) async fn get_region<S: SpotPriceHistory, P: ProductPrices>(
         &self,
-        client: &aws_sdk_ec2::Client,
-        pricing: &Pricing,
+        spot: &S,
+        pricing: &P,
+        query: &PriceQuery,
+        diagnostics: &Diagnostics,
     ) -> Result<SpotRegion> {
         let mut region_data: SpotRegion = SpotRegion::new(&self.region);
 
-        for instance in &self.instances {
-            if let Ok(spot_region) = self
-                .get_zones(client, pricing, &self.zones, instance.clone())
-                .await
-            {
-                region_data.add(instance.as_str(), spot_region);
+        let results = join_all(self.instances.iter().map(|instance| {
+            self.get_zones(
+                spot,
+                pricing,
+                query,
+                &self.zones,
+                instance.clone(),
+                diagnostics,
+            )
+        }))
+        .await;
+
+        for (instance, result) in self.instances.iter().zip(results) {
+            match result {
+                Ok(spot_region) => {
+                    if spot_region.get_spot_prices().is_none() {
+                        diagnostics.record(
+                            Some(&self.region),
+                            instance.as_str(),
+                            Reason::NoSpotHistory,
+                            &format!("no spot prices in {} zones", self.zones.len()),
+                        );
+                    }
+                    region_data.add(instance.as_str(), spot_region);
+                }
+                Err(err) => diagnostics.record_error(Some(&self.region), instance.as_str(), &err),
             }
         }
 
         Ok(region_data)
     }
 
-    async fn get_zones(
+    async fn get_zones<S: SpotPriceHistory, P: ProductPrices>(
         &self,
-        client: &aws_sdk_ec2::Client,
-        pricing: &Pricing,
-        zones: &[String],
+        spot: &S,
+        pricing: &P,
+        query: &PriceQuery,
+        zones: &[Zone],
         instance: InstanceType,
+        diagnostics: &Diagnostics,
     ) -> Result<Instance> {
-        let mut result_zones = Vec::new();
+        let product_description = query.spot_product_description();
+        let histories = try_join_all(zones.iter().map(|zone| {
+            spot.get_spot_price_history(
+                &self.region,
+                zone.name(),
+                &instance,
+                product_description,
+                &self.window,
+            )
+        }));
+        let ondemand = pricing.get_query_price(instance.as_str(), &self.region, query);
+
+        let savings_plan = async {
+            match query.savings_plan() {
+                Some(plan) => Some(
+                    pricing
+                        .get_savings_plan_rate(instance.as_str(), &self.region, query, &plan)
+                        .await,
+                ),
+                None => None,
+            }
+        };
 
-        for zone in zones {
-            let spot_history = get_spot_price_history(client, zone, instance.clone()).await?;
+        let (prices, savings_plan) = join(try_join(histories, ondemand), savings_plan).await;
+        let (histories, ondemand) = prices?;
 
-            if spot_history.is_empty() {
-                continue;
+        // a missing Savings Plan rate leaves the spot and on-demand prices in the matrix
+        let savings_plan_rate = match savings_plan {
+            Some(Ok(rate)) => Some(rate),
+            Some(Err(err)) => {
+                diagnostics.record_error(Some(&self.region), instance.as_str(), &err);
+                None
             }
+            None => None,
+        };
+
+        // the last price holds until the end of the window, or until now
+        let end = self
+            .window
+            .end()
+            .unwrap_or_else(|| DateTime::from(SystemTime::now()));
 
-            let min_entry = self.min(&spot_history);
-            let max_entry = self.max(&spot_history);
-            let avg_entry = self.average(&spot_history);
+        let mut result_zones = Vec::new();
 
-            let last = spot_history.last().unwrap().1;
+        for (zone, spot_history) in zones.iter().zip(histories) {
+            let Some(statistics) = SpotStatistics::new(&spot_history, self.window.start(), end)
+            else {
+                continue;
+            };
 
-            let zone = AvailabilityZone::new(min_entry, avg_entry, max_entry, last);
+            let zone = AvailabilityZone::new(zone, statistics).with_history(&spot_history);
 
             result_zones.push(zone);
         }
 
-        //if result_zones.is_empty() {
-        //    return Err(anyhow!("no entries found"));
-        //}
+        let spot_region = Instance::new(&self.region, instance, &result_zones, ondemand)
+            .with_savings_plan_rate(savings_plan_rate);
+
+        Ok(spot_region)
+    }
+}
 
-        let ondemand = pricing
-            .get_ondemand_price(instance.as_str(), &self.region)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use crate::provider::ZoneCatalog;
+    use crate::savings_plans::{SavingsPlan, SavingsPlanRate};
+    use crate::statistics::Metric;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_get_region() -> Result<()> {
+        let fixture = Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a", "us-east-2b"])
+            .with_spot_prices(
+                "us-east-2",
+                "us-east-2a",
+                "c7g.16xlarge",
+                &[(0, 1.0), (3600, 2.0)],
+            )
+            .with_spot_prices("us-east-2", "us-east-2b", "c7g.16xlarge", &[(0, 3.0)])
+            .with_ondemand_price("c7g.16xlarge", "us-east-2", 4.0);
+
+        let zones = fixture.get_zones("us-east-2").await?;
+        let instances = [
+            InstanceType::from("c7g.16xlarge"),
+            InstanceType::from("hpc7g.16xlarge"),
+        ];
+        // two hours, the last prices hold for the second one
+        let window = TimeWindow::new(None, Some(DateTime::from_secs(7200)));
+        let collector = DataCollector::new("us-east-2", &zones, &instances, &window);
+
+        let diagnostics = Diagnostics::new();
+        let region = collector
+            .get_region(&fixture, &fixture, &PriceQuery::default(), &diagnostics)
             .await?;
 
-        let spot_region = Instance::new(&self.region, instance, &result_zones, ondemand);
+        let c7g = region.find_instance("c7g.16xlarge").unwrap();
+        assert_eq!(c7g.get_average_price(), Some(2.25));
+        assert_eq!(c7g.get_spot_metric(Metric::Max), Some(3.0));
+        assert_eq!(c7g.get_spot_metric(Metric::Changes), Some(1.0));
+        assert_eq!(c7g.get_ondemand_price(), 4.0);
 
-        Ok(spot_region)
-    }
+        // no on-demand price
+        assert!(!region.contains("hpc7g.16xlarge"));
+        let entries = diagnostics.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].instance(), "hpc7g.16xlarge");
+        assert_eq!(entries[0].reason(), Reason::NotOffered);
 
-    fn min(&self, data: &[(DateTime, f64)]) -> f64 {
-        data.iter()
-            .map(|e| e.1)
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap()
+        Ok(())
     }
 
-    fn max(&self, data: &[(DateTime, f64)]) -> f64 {
-        data.iter()
-            .map(|e| e.1)
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap()
-    }
+    #[tokio::test]
+    async fn test_savings_plan() -> Result<()> {
+        let plan = "compute-1yr-no-upfront".parse::<SavingsPlan>().unwrap();
+        let fixture = Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a"])
+            .with_spot_prices("us-east-2", "us-east-2a", "c7g.16xlarge", &[(0, 1.0)])
+            .with_spot_prices("us-east-2", "us-east-2a", "c7g.8xlarge", &[(0, 0.5)])
+            .with_ondemand_price("c7g.16xlarge", "us-east-2", 4.0)
+            .with_ondemand_price("c7g.8xlarge", "us-east-2", 2.0)
+            .with_savings_plan_rate("c7g.16xlarge", "us-east-2", SavingsPlanRate::new(plan, 3.0));
+
+        let zones = fixture.get_zones("us-east-2").await?;
+        let instances = [
+            InstanceType::from("c7g.16xlarge"),
+            InstanceType::from("c7g.8xlarge"),
+        ];
+        let collector = DataCollector::new("us-east-2", &zones, &instances, &TimeWindow::default());
+
+        let diagnostics = Diagnostics::new();
+        let query = PriceQuery::default().with_savings_plan(Some(plan));
+        let region = collector
+            .get_region(&fixture, &fixture, &query, &diagnostics)
+            .await?;
+
+        let c7g = region.find_instance("c7g.16xlarge").unwrap();
+        assert_eq!(c7g.get_savings_plan_rate(), Some(3.0));
+
+        // no rate, but still spot and on-demand prices
+        let c7g = region.find_instance("c7g.8xlarge").unwrap();
+        assert_eq!(c7g.get_savings_plan_rate(), None);
+        assert_eq!(c7g.get_ondemand_price(), 2.0);
+        let entries = diagnostics.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].instance(), "c7g.8xlarge");
+        assert_eq!(entries[0].reason(), Reason::NotOffered);
 
-    fn average(&self, data: &[(DateTime, f64)]) -> f64 {
-        let sum: f64 = data.iter().map(|e| e.1).sum();
-        sum / data.len() as f64
+        Ok(())
     }
 }
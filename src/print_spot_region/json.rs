@@ -0,0 +1,299 @@
+use crate::diagnostics::Diagnostics;
+use crate::error::Result;
+use crate::normalize::PriceUnit;
+use crate::print_spot_region::spot_region::SpotRegion;
+use crate::statistics::{Metric, SpotStatistics};
+use serde::Serialize;
+
+// bump on incompatible changes of the schema in README.md
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+struct SpotPrices {
+    min: f64,
+    avg: f64,
+    max: f64,
+    last: f64,
+    p50: f64,
+    p90: f64,
+    p99: f64,
+    stddev: f64,
+    changes: u32,
+}
+
+impl SpotPrices {
+    fn new(statistics: &SpotStatistics) -> Self {
+        Self {
+            min: statistics.min(),
+            avg: statistics.avg(),
+            max: statistics.max(),
+            last: statistics.last(),
+            p50: statistics.percentile(0.5),
+            p90: statistics.percentile(0.9),
+            p99: statistics.percentile(0.99),
+            stddev: statistics.stddev(),
+            changes: statistics.changes(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ZoneReport {
+    availability_zone: String,
+    zone_id: Option<String>,
+    spot: SpotPrices,
+}
+
+#[derive(Debug, Serialize)]
+struct InstanceReport {
+    instance_type: String,
+    ondemand: f64,
+    savings_plan: Option<f64>,
+    spot: Option<SpotPrices>,
+    // by the metric of the report
+    cheapest_zone: Option<String>,
+    zone_spread: Option<f64>,
+    // the share of the on-demand price saved and spot divided by on-demand, by the metric
+    savings: Option<f64>,
+    spot_ratio: Option<f64>,
+    // the highest spot price divided by on-demand
+    spike_ratio: Option<f64>,
+    zones: Vec<ZoneReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct RegionReport {
+    region: String,
+    favorite: bool,
+    instances: Vec<InstanceReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct MissingReport {
+    region: Option<String>,
+    instance_type: String,
+    reason: String,
+    detail: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SpotRegionReport {
+    schema_version: u32,
+    unit: PriceUnit,
+    metric: Metric,
+    instances: Vec<String>,
+    price_changes: Vec<Option<f64>>,
+    regions: Vec<RegionReport>,
+    missing: Vec<MissingReport>,
+}
+
+/// the spot-region matrix as JSON
+pub(super) fn render(
+    regions: &[SpotRegion],
+    instances: &[String],
+    price_changes: &[Option<f64>],
+    favorite_regions: &[&str],
+    unit: PriceUnit,
+    metric: Metric,
+    diagnostics: &Diagnostics,
+) -> Result<String> {
+    let regions = regions
+        .iter()
+        .filter(|region| !region.is_empty())
+        .map(|region| RegionReport {
+            region: region.get_region().to_string(),
+            favorite: favorite_regions.contains(&region.get_region().as_ref()),
+            instances: instances
+                .iter()
+                .filter_map(|instance| region.find_instance(instance))
+                .map(|instance| InstanceReport {
+                    instance_type: instance.get_instance().as_str().to_string(),
+                    ondemand: instance.get_ondemand_price(),
+                    savings_plan: instance.get_savings_plan_rate(),
+                    spot: instance.get_spot_prices().map(SpotPrices::new),
+                    cheapest_zone: instance
+                        .get_cheapest_zone(metric)
+                        .map(|zone| zone.get_name().to_string()),
+                    zone_spread: instance.get_zone_spread(metric),
+                    savings: instance.get_savings(metric),
+                    spot_ratio: instance.get_spot_ratio(metric),
+                    spike_ratio: instance.get_spike_ratio(),
+                    zones: instance
+                        .get_zones()
+                        .iter()
+                        .map(|zone| ZoneReport {
+                            availability_zone: zone.get_name().to_string(),
+                            zone_id: zone.get_zone_id().map(|id| id.to_string()),
+                            spot: SpotPrices::new(zone.get_statistics()),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    let report = SpotRegionReport {
+        schema_version: SCHEMA_VERSION,
+        unit,
+        metric,
+        instances: instances.to_vec(),
+        price_changes: price_changes.to_vec(),
+        regions,
+        missing: diagnostics
+            .entries()
+            .iter()
+            .map(|entry| MissingReport {
+                region: entry.region().map(|r| r.to_string()),
+                instance_type: entry.instance().to_string(),
+                reason: entry.reason().to_string(),
+                detail: entry.detail().to_string(),
+            })
+            .collect(),
+    };
+
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::availability_zone::AvailabilityZone;
+    use crate::diagnostics::Reason;
+    use crate::instance::Instance;
+    use crate::provider::Zone;
+    use aws_sdk_ec2::primitives::DateTime;
+    use aws_sdk_ec2::types::InstanceType;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_render() -> Result<()> {
+        // hourly prices of two hours
+        let zone = |name: &str, id: &str, prices: &[f64]| {
+            let history = prices
+                .iter()
+                .zip(0..)
+                .map(|(&price, hour)| (DateTime::from_secs(hour * 3600), price))
+                .collect::<Vec<_>>();
+            let statistics = SpotStatistics::new(&history, None, DateTime::from_secs(7200));
+            AvailabilityZone::new(&Zone::new(name, Some(id)), statistics.unwrap())
+        };
+        let zones = [
+            zone("us-east-2a", "use2-az1", &[1.0, 2.0]),
+            zone("us-east-2b", "use2-az2", &[4.0, 2.0]),
+        ];
+        let mut region = SpotRegion::new("us-east-2");
+        region.add(
+            "c7g.16xlarge",
+            Instance::new("us-east-2", InstanceType::from("c7g.16xlarge"), &zones, 4.0)
+                .with_savings_plan_rate(Some(3.0)),
+        );
+        region.add(
+            "c7g.8xlarge",
+            Instance::new("us-east-2", InstanceType::from("c7g.8xlarge"), &[], 2.0),
+        );
+
+        let instances = ["c7g.16xlarge".to_string(), "c7g.8xlarge".to_string()];
+        let diagnostics = Diagnostics::new();
+        diagnostics.record(
+            Some("eu-north-1"),
+            "c7g.16xlarge",
+            Reason::NotOffered,
+            "no product: c7g.16xlarge",
+        );
+        let report = render(
+            &[region.clone()],
+            &instances,
+            &[None],
+            &["us-east-2"],
+            PriceUnit::Vcpu,
+            Metric::Avg,
+            &diagnostics,
+        )?;
+
+        let report: Value = serde_json::from_str(&report)?;
+        assert_eq!(
+            report,
+            json!({
+                "schema_version": 1,
+                "unit": "vcpu",
+                "metric": "avg",
+                "instances": ["c7g.16xlarge", "c7g.8xlarge"],
+                "price_changes": [null],
+                "regions": [{
+                    "region": "us-east-2",
+                    "favorite": true,
+                    "instances": [{
+                        "instance_type": "c7g.16xlarge",
+                        "ondemand": 4.0,
+                        "savings_plan": 3.0,
+                        "spot": {
+                            "min": 1.0, "avg": 2.25, "max": 4.0, "last": 2.0,
+                            "p50": 2.0, "p90": 4.0, "p99": 4.0, "stddev": 1.1875_f64.sqrt(),
+                            "changes": 2,
+                        },
+                        "cheapest_zone": "us-east-2a",
+                        "zone_spread": 2.0,
+                        "savings": 0.4375,
+                        "spot_ratio": 0.5625,
+                        "spike_ratio": 1.0,
+                        "zones": [{
+                            "availability_zone": "us-east-2a",
+                            "zone_id": "use2-az1",
+                            "spot": {
+                                "min": 1.0, "avg": 1.5, "max": 2.0, "last": 2.0,
+                                "p50": 1.0, "p90": 2.0, "p99": 2.0, "stddev": 0.5,
+                                "changes": 1,
+                            },
+                        }, {
+                            "availability_zone": "us-east-2b",
+                            "zone_id": "use2-az2",
+                            "spot": {
+                                "min": 2.0, "avg": 3.0, "max": 4.0, "last": 2.0,
+                                "p50": 2.0, "p90": 4.0, "p99": 4.0, "stddev": 1.0,
+                                "changes": 1,
+                            },
+                        }],
+                    }, {
+                        "instance_type": "c7g.8xlarge",
+                        "ondemand": 2.0,
+                        "savings_plan": null,
+                        "spot": null,
+                        "cheapest_zone": null,
+                        "zone_spread": null,
+                        "savings": null,
+                        "spot_ratio": null,
+                        "spike_ratio": null,
+                        "zones": [],
+                    }],
+                }],
+                "missing": [{
+                    "region": "eu-north-1",
+                    "instance_type": "c7g.16xlarge",
+                    "reason": "not offered in region",
+                    "detail": "no product: c7g.16xlarge",
+                }],
+            })
+        );
+
+        // the savings by the highest spot price
+        let report = render(
+            &[region],
+            &instances,
+            &[None],
+            &[],
+            PriceUnit::Vcpu,
+            Metric::Max,
+            &diagnostics,
+        )?;
+        let report: Value = serde_json::from_str(&report)?;
+        assert_eq!(report["metric"], "max");
+        let instance = &report["regions"][0]["instances"][0];
+        assert_eq!(instance["savings"], 0.0);
+        assert_eq!(instance["spot_ratio"], 1.0);
+        assert_eq!(instance["cheapest_zone"], "us-east-2a");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::chart::{span, Chart};
+use crate::diagnostics::Diagnostics;
+use crate::error::Result;
+use crate::pricing::PriceQuery;
+use crate::print_spot_region::print_spot_regions::collect;
+use crate::print_spot_region::spot_region::SpotRegion;
+use crate::provider::{InstanceCatalog, ProductPrices, SpotPriceHistory, TimeWindow, ZoneCatalog};
+use aws_sdk_ec2::types::InstanceType;
+
+// a line per zone of a single region, or per region with the mean of its zones,
+// with the lowest on-demand price as reference
+fn chart(regions: &[SpotRegion], instance: &str, width: u32, height: u32) -> Chart {
+    let instances = regions
+        .iter()
+        .filter_map(|region| Some((region.get_region(), region.find_instance(instance)?)))
+        .collect::<Vec<_>>();
+
+    let mut chart = Chart::new(width, height);
+
+    if let [(_, el)] = instances.as_slice() {
+        for zone in el.get_zones() {
+            chart = chart.with_series(zone.get_name(), &[zone.get_history()]);
+        }
+    } else {
+        for (region, el) in &instances {
+            let histories = el
+                .get_zones()
+                .iter()
+                .map(|zone| zone.get_history())
+                .collect::<Vec<_>>();
+            chart = chart.with_series(region, &histories);
+        }
+    }
+
+    let ondemand = instances
+        .iter()
+        .map(|(region, el)| (region, el.get_ondemand_price()))
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    match ondemand {
+        Some((region, price)) if instances.len() > 1 => {
+            chart.with_reference(&format!("on-demand in {region}"), price)
+        }
+        Some((_, price)) => chart.with_reference("on-demand", price),
+        None => chart,
+    }
+}
+
+/// print a text chart of the spot prices of an instance over the window
+///
+/// With one region the chart has a line per availability zone, with several regions a
+/// line per region with the mean price of its zones. The lowest on-demand price is drawn
+/// as a reference line. Returns why prices are missing from the chart.
+#[allow(clippy::too_many_arguments)]
+pub async fn print_chart<E: InstanceCatalog + ZoneCatalog + SpotPriceHistory, P: ProductPrices>(
+    ec2: &E,
+    pricing: &P,
+    instance: &InstanceType,
+    region_names: &[String],
+    query: &PriceQuery,
+    window: &TimeWindow,
+    width: u32,
+    height: u32,
+) -> Result<Diagnostics> {
+    let diagnostics = Diagnostics::new();
+    let (regions, _, _) = collect(
+        ec2,
+        pricing,
+        &[instance.clone()],
+        region_names,
+        query,
+        window,
+        &diagnostics,
+    )
+    .await?;
+
+    let histories = regions
+        .iter()
+        .filter_map(|region| region.find_instance(instance.as_str()))
+        .flat_map(|el| el.get_zones())
+        .map(|zone| zone.get_history())
+        .collect::<Vec<_>>();
+    let Some((start, end)) = span(&histories, window) else {
+        println!("no spot prices of {}", instance.as_str());
+        return Ok(diagnostics);
+    };
+
+    if query.unit().is_normalized() {
+        println!("{} (USD per {})", instance.as_str(), query.unit().label());
+    } else {
+        println!("{}", instance.as_str());
+    }
+    print!(
+        "{}",
+        chart(&regions, instance.as_str(), width, height).render(start, end)
+    );
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use aws_sdk_ec2::primitives::DateTime;
+    use pretty_assertions::assert_eq;
+
+    fn fixture() -> Fixture {
+        Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a", "us-east-2b"])
+            .with_zones("eu-north-1", &["eu-north-1a"])
+            .with_spot_prices("us-east-2", "us-east-2a", "c7g.16xlarge", &[(0, 1.0)])
+            .with_spot_prices("us-east-2", "us-east-2b", "c7g.16xlarge", &[(0, 3.0)])
+            .with_spot_prices("eu-north-1", "eu-north-1a", "c7g.16xlarge", &[(0, 1.5)])
+            .with_ondemand_price("c7g.16xlarge", "us-east-2", 4.0)
+            .with_ondemand_price("c7g.16xlarge", "eu-north-1", 3.0)
+    }
+
+    #[tokio::test]
+    async fn test_chart() -> Result<()> {
+        let fixture = fixture();
+        let window = TimeWindow::new(None, Some(DateTime::from_secs(3600)));
+        let (start, end) = (DateTime::from_secs(0), DateTime::from_secs(3600));
+
+        let diagnostics = Diagnostics::new();
+        let (regions, _, _) = collect(
+            &fixture,
+            &fixture,
+            &[InstanceType::from("c7g.16xlarge")],
+            &["us-east-2".to_string(), "eu-north-1".to_string()],
+            &PriceQuery::default(),
+            &window,
+            &diagnostics,
+        )
+        .await?;
+
+        // a line per region by on-demand price, us-east-2 at the mean of its zones
+        let rendered = chart(&regions, "c7g.16xlarge", 4, 3).render(start, end);
+        assert_eq!(
+            rendered.lines().take(3).collect::<Vec<_>>(),
+            ["   3.00000 |----", "           |++++", "   1.50000 |****",]
+        );
+        assert!(
+            rendered.ends_with("* eu-north-1   + us-east-2   - on-demand in eu-north-1 3.00000\n")
+        );
+
+        // a line per zone of a single region
+        let us_east_2 = regions
+            .iter()
+            .filter(|region| region.get_region() == "us-east-2")
+            .cloned()
+            .collect::<Vec<_>>();
+        let rendered = chart(&us_east_2, "c7g.16xlarge", 4, 2).render(start, end);
+        assert!(rendered.starts_with("   4.00000 |++++\n   1.00000 |****\n"));
+        assert!(rendered.ends_with("* us-east-2a   + us-east-2b   - on-demand 4.00000\n"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+use crate::diagnostics::Diagnostics;
+use crate::error::Result;
+use crate::output::{OutputFormat, Table};
+use crate::pricing::PriceQuery;
+use crate::print_spot_region::print_spot_regions::collect;
+use crate::provider::{InstanceCatalog, ProductPrices, SpotPriceHistory, TimeWindow, ZoneCatalog};
+use crate::reserved::ReservedOffering;
+use aws_sdk_ec2::types::InstanceType;
+use futures::future::join_all;
+use serde::Serialize;
+
+const HEADER: &[&str] = &[
+    "region",
+    "instance",
+    "lease",
+    "class",
+    "purchase option",
+    "upfront",
+    "hourly",
+    "effective",
+    "on-demand",
+    "spot avg",
+    "vs on-demand",
+    "vs spot",
+];
+
+#[derive(Debug, Serialize)]
+// a reserved offering next to the on-demand and average spot price
+struct Comparison {
+    region: String,
+    instance_type: String,
+    #[serde(flatten)]
+    offering: ReservedOffering,
+    effective_hourly_rate: f64,
+    ondemand: Option<f64>,
+    spot_avg: Option<f64>,
+    // the saved share of the price, negative if the reservation is more expensive
+    savings_vs_ondemand: Option<f64>,
+    savings_vs_spot: Option<f64>,
+}
+
+impl Comparison {
+    fn new(
+        region: &str,
+        instance: &str,
+        offering: ReservedOffering,
+        ondemand: Option<f64>,
+        spot_avg: Option<f64>,
+    ) -> Self {
+        let effective = offering.effective_hourly_rate();
+        let savings = |price: f64| 1.0 - effective / price;
+
+        Self {
+            region: region.to_string(),
+            instance_type: instance.to_string(),
+            effective_hourly_rate: effective,
+            ondemand,
+            spot_avg,
+            savings_vs_ondemand: ondemand.map(savings),
+            savings_vs_spot: spot_avg.map(savings),
+            offering,
+        }
+    }
+}
+
+/// compare the reserved offerings with the on-demand and spot prices
+async fn compare<E: InstanceCatalog + ZoneCatalog + SpotPriceHistory, P: ProductPrices>(
+    ec2: &E,
+    pricing: &P,
+    instances: &[InstanceType],
+    region_names: &[String],
+    query: &PriceQuery,
+    window: &TimeWindow,
+    diagnostics: &Diagnostics,
+) -> Result<Vec<Comparison>> {
+    let (regions, _, _) = collect(
+        ec2,
+        pricing,
+        instances,
+        region_names,
+        query,
+        window,
+        diagnostics,
+    )
+    .await?;
+
+    let lookups = regions
+        .iter()
+        .flat_map(|region| instances.iter().map(move |instance| (region, instance)))
+        .collect::<Vec<_>>();
+
+    let results = join_all(lookups.iter().map(|(region, instance)| async move {
+        pricing
+            .get_reserved_offerings(instance.as_str(), &region.get_region(), query)
+            .await
+    }))
+    .await;
+
+    let mut comparisons = Vec::new();
+
+    for ((region, instance), result) in lookups.iter().zip(results) {
+        let region_name = region.get_region();
+        match result {
+            Ok(offerings) => {
+                let prices = region.find_instance(instance.as_str());
+                let ondemand = prices.map(|p| p.get_ondemand_price());
+                let spot_avg = prices.and_then(|p| p.get_average_price());
+
+                // the fees in the unit of the other prices, unknown without them
+                let amount = match (prices, query.unit().is_normalized()) {
+                    (Some(prices), _) => prices.get_amount(),
+                    (None, false) => 1.0,
+                    (None, true) => continue,
+                };
+
+                for offering in offerings {
+                    comparisons.push(Comparison::new(
+                        &region_name,
+                        instance.as_str(),
+                        offering.per(amount),
+                        ondemand,
+                        spot_avg,
+                    ));
+                }
+            }
+            Err(err) => diagnostics.record_error(Some(&region_name), instance.as_str(), &err),
+        }
+    }
+
+    Ok(comparisons)
+}
+
+fn format_price(price: Option<f64>) -> String {
+    price.map(|p| format!("{p:.5}")).unwrap_or_default()
+}
+
+fn format_savings(savings: Option<f64>) -> String {
+    savings
+        .map(|s| format!("{:.1}%", 100.0 * s))
+        .unwrap_or_default()
+}
+
+fn table(comparisons: &[Comparison]) -> Table {
+    let mut table = Table::new(HEADER);
+
+    for c in comparisons {
+        table.push(vec![
+            c.region.clone(),
+            c.instance_type.clone(),
+            c.offering.lease_contract_length().to_string(),
+            c.offering.offering_class().to_string(),
+            c.offering.purchase_option().to_string(),
+            format!("{:.2}", c.offering.upfront_fee()),
+            format!("{:.5}", c.offering.hourly_fee()),
+            format!("{:.5}", c.effective_hourly_rate),
+            format_price(c.ondemand),
+            format_price(c.spot_avg),
+            format_savings(c.savings_vs_ondemand),
+            format_savings(c.savings_vs_spot),
+        ]);
+    }
+
+    table
+}
+
+/// print every reserved offering of instances in regions next to the on-demand and spot prices
+///
+/// Returns why offerings are missing from the table.
+pub async fn print_reserved<
+    E: InstanceCatalog + ZoneCatalog + SpotPriceHistory,
+    P: ProductPrices,
+>(
+    ec2: &E,
+    pricing: &P,
+    instances: &[InstanceType],
+    region_names: &[String],
+    query: &PriceQuery,
+    window: &TimeWindow,
+    output: OutputFormat,
+) -> Result<Diagnostics> {
+    let diagnostics = Diagnostics::new();
+    let comparisons = compare(
+        ec2,
+        pricing,
+        instances,
+        region_names,
+        query,
+        window,
+        &diagnostics,
+    )
+    .await?;
+
+    match output {
+        OutputFormat::Text => {
+            if query.unit().is_normalized() {
+                println!("USD per {}", query.unit().label());
+            }
+            print!("{}", table(&comparisons).to_text());
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&comparisons)?),
+        OutputFormat::Csv | OutputFormat::Markdown => {
+            print!("{}", table(&comparisons).render(output)?);
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use pretty_assertions::assert_eq;
+
+    const RESERVED: &str =
+        include_str!("../../testdata/price_list/c7g.16xlarge-us-east-2-reserved.json");
+
+    #[tokio::test]
+    async fn test_compare() -> Result<()> {
+        let fixture = Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a"])
+            .with_spot_prices("us-east-2", "us-east-2a", "c7g.16xlarge", &[(0, 1.0)])
+            .with_ondemand_price("c7g.16xlarge", "us-east-2", 2.5)
+            .with_product(RESERVED);
+
+        let diagnostics = Diagnostics::new();
+        let comparisons = compare(
+            &fixture,
+            &fixture,
+            &[InstanceType::from("c7g.16xlarge")],
+            &["us-east-2".to_string()],
+            &PriceQuery::default(),
+            &TimeWindow::default(),
+            &diagnostics,
+        )
+        .await?;
+
+        assert!(diagnostics.is_empty());
+
+        let table = table(&comparisons);
+        assert_eq!(table.rows().len(), 4);
+        assert_eq!(
+            table.rows()[0],
+            vec![
+                "us-east-2",
+                "c7g.16xlarge",
+                "1yr",
+                "standard",
+                "No Upfront",
+                "0.00",
+                "1.45700",
+                "1.45700",
+                "2.50000",
+                "1.00000",
+                "41.7%",
+                "-45.7%",
+            ]
+        );
+
+        Ok(())
+    }
+}
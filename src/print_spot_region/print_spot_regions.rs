@@ -1,52 +1,462 @@
-use crate::ec2::Ec2;
-use crate::get_region_config;
-use crate::get_zones;
-use crate::pricing::Pricing;
+use crate::chart::span;
+use crate::diagnostics::{Diagnostics, Reason};
+use crate::error::{Error, Result};
+use crate::normalize::PriceUnit;
+use crate::output::OutputFormat;
+use crate::pricing::{PriceQuery, Tenancy};
+use crate::print_instances::collect_specs;
 use crate::print_spot_region::data_collector::DataCollector;
+use crate::print_spot_region::json;
 use crate::print_spot_region::printer::Printer;
-use crate::print_spot_region::reorder::reorder;
+use crate::print_spot_region::reorder::{
+    reorder, sort_instances_by_savings, sort_regions_by_savings,
+};
 use crate::print_spot_region::spot_region::SpotRegion;
-use anyhow::Result;
+use crate::provider::{InstanceCatalog, ProductPrices, SpotPriceHistory, TimeWindow, ZoneCatalog};
 use aws_sdk_ec2::types::InstanceType;
+use futures::future::join_all;
+use std::fmt;
+use std::str::FromStr;
 
-/// print the Spot prices for an instance in all regions
-pub async fn print_spot_regions(
-    ec2: &Ec2,
-    pricing: &Pricing,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// what is ordered by the share of the on-demand price saved with spot
+pub enum SavingsSort {
+    /// the rows, by the mean savings of their instances
+    Regions,
+    /// the columns, by their mean savings over the regions
+    Instances,
+}
+
+impl FromStr for SavingsSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "regions" => Ok(SavingsSort::Regions),
+            "instances" => Ok(SavingsSort::Instances),
+            _ => Err(format!(
+                "unknown savings sort: {s}, expected regions or instances"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SavingsSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SavingsSort::Regions => f.pad("regions"),
+            SavingsSort::Instances => f.pad("instances"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// the columns, order and colors of the spot-region matrix
+pub struct MatrixOptions {
+    zone_columns: bool,
+    savings_columns: bool,
+    regions_by_savings: bool,
+    instances_by_savings: bool,
+    color: bool,
+    sparklines: bool,
+}
+
+impl MatrixOptions {
+    /// add the cheapest zone and the spread between the zones of each instance
+    pub fn with_zone_columns(self, zone_columns: bool) -> Self {
+        Self {
+            zone_columns,
+            ..self
+        }
+    }
+
+    /// add the savings, the spot to on-demand ratio and the spike ratio of each instance
+    pub fn with_savings_columns(self, savings_columns: bool) -> Self {
+        Self {
+            savings_columns,
+            ..self
+        }
+    }
+
+    /// order the regions or the instances by their mean savings, highest first
+    pub fn with_sort_by_savings(self, sort: SavingsSort) -> Self {
+        match sort {
+            SavingsSort::Regions => Self {
+                regions_by_savings: true,
+                ..self
+            },
+            SavingsSort::Instances => Self {
+                instances_by_savings: true,
+                ..self
+            },
+        }
+    }
+
+    /// color the cells of the text matrix by their savings
+    pub fn with_color(self, color: bool) -> Self {
+        Self { color, ..self }
+    }
+
+    /// add a sparkline of the spot prices over the window to each cell
+    pub fn with_sparklines(self, sparklines: bool) -> Self {
+        Self { sparklines, ..self }
+    }
+}
+
+/// collect the Spot prices for instances in the given regions
+///
+/// Returns the regions, the instances reordered by price and the relative price changes.
+/// The prices are divided by the unit of the query, which also ranks the instances.
+/// Fails for dedicated or host tenancy, spot prices are only for shared hosts.
+pub(super) async fn collect<
+    E: InstanceCatalog + ZoneCatalog + SpotPriceHistory,
+    P: ProductPrices,
+>(
+    ec2: &E,
+    pricing: &P,
     instances: &[InstanceType],
-    favorite_regions: &[&str],
-) -> Result<()> {
-    let mut regions: Vec<SpotRegion> = Vec::new();
+    region_names: &[String],
+    query: &PriceQuery,
+    window: &TimeWindow,
+    diagnostics: &Diagnostics,
+) -> Result<(Vec<SpotRegion>, Vec<String>, Vec<Option<f64>>)> {
+    if query.tenancy() != Tenancy::Shared {
+        return Err(Error::Unsupported(format!(
+            "spot prices compared with {} tenancy, spot instances run on shared hosts",
+            query.tenancy()
+        )));
+    }
+
+    // no regions given: all regions
+    let region_names: Vec<String> = if region_names.is_empty() {
+        ec2.get_regions().await?
+    } else {
+        region_names.to_vec()
+    };
+
+    // all regions at once, the providers bound the concurrent API calls
+    let regions: Vec<SpotRegion> = join_all(region_names.iter().map(|region| async move {
+        let result = async {
+            let zones = ec2.get_zones(region).await?;
+            let collector = DataCollector::new(region, &zones, instances, window);
+            collector.get_region(ec2, pricing, query, diagnostics).await
+        }
+        .await;
 
-    let region_names: Vec<String> = ec2.get_regions().await?;
+        // a failed region stays empty, the others are still reported
+        result.unwrap_or_else(|err| {
+            for instance in instances {
+                diagnostics.record_error(Some(region), instance.as_str(), &err);
+            }
+            SpotRegion::new(region)
+        })
+    }))
+    .await;
 
-    regions.try_reserve(region_names.len())?;
+    let regions = match query.unit() {
+        PriceUnit::Instance => regions,
+        unit => normalize(ec2, regions, instances, unit, diagnostics).await,
+    };
 
-    let mut local_instances: Vec<InstanceType> = Vec::new();
+    Ok(reorder(&regions, instances))
+}
+
+// divide the prices by the resources of the instances, dropping the ones without any
+async fn normalize<C: InstanceCatalog>(
+    catalog: &C,
+    regions: Vec<SpotRegion>,
+    instances: &[InstanceType],
+    unit: PriceUnit,
+    diagnostics: &Diagnostics,
+) -> Vec<SpotRegion> {
+    let specs = collect_specs(catalog, instances, diagnostics).await;
+
+    let mut amounts = Vec::new();
     for instance in instances {
-        local_instances.push(instance.clone());
+        let Some(specs) = specs.get(instance.as_str()) else {
+            // recorded while describing the instance
+            continue;
+        };
+        match specs.amount(unit) {
+            Some(amount) => amounts.push((instance, amount)),
+            None => diagnostics.record(
+                None,
+                instance.as_str(),
+                Reason::NoResource,
+                &format!("{} has no {unit}", instance.as_str()),
+            ),
+        }
     }
 
-    for region in region_names {
-        let config = get_region_config(&region).await;
-        let client = aws_sdk_ec2::Client::new(&config);
+    regions
+        .iter()
+        .map(|region| {
+            let mut normalized = SpotRegion::new(&region.get_region());
+            for (instance, amount) in &amounts {
+                if let Some(prices) = region.find_instance(instance.as_str()) {
+                    normalized.add(instance.as_str(), prices.clone().per(*amount));
+                }
+            }
+            normalized
+        })
+        .collect()
+}
+
+/// print the Spot prices for instances in the given regions, laid out by the options
+///
+/// Returns why prices are missing from the table.
+#[allow(clippy::too_many_arguments)]
+pub async fn print_spot_regions<
+    E: InstanceCatalog + ZoneCatalog + SpotPriceHistory,
+    P: ProductPrices,
+>(
+    ec2: &E,
+    pricing: &P,
+    instances: &[InstanceType],
+    region_names: &[String],
+    favorite_regions: &[&str],
+    query: &PriceQuery,
+    window: &TimeWindow,
+    output: OutputFormat,
+    options: &MatrixOptions,
+) -> Result<Diagnostics> {
+    let diagnostics = Diagnostics::new();
+    let (mut regions, mut instance_names, mut price_changes) = collect(
+        ec2,
+        pricing,
+        instances,
+        region_names,
+        query,
+        window,
+        &diagnostics,
+    )
+    .await?;
 
-        let zones = get_zones(&client, &region).await?;
-        let collector = DataCollector::new(&region, &zones, instances);
-        let region_data = collector.get_region(&client, pricing).await?;
-        regions.push(region_data);
+    if options.instances_by_savings {
+        (instance_names, price_changes) =
+            sort_instances_by_savings(&regions, &instance_names, query.metric());
     }
+    if options.regions_by_savings {
+        regions = sort_regions_by_savings(&regions, &instance_names, query.metric());
+    }
+    let regions_and_instances = (regions, instance_names, price_changes);
 
-    let regions_and_instances = reorder(&regions, instances);
+    // all sparklines span the same times
+    let sparklines = if options.sparklines {
+        let histories = regions_and_instances
+            .0
+            .iter()
+            .flat_map(|region| {
+                regions_and_instances
+                    .1
+                    .iter()
+                    .filter_map(|instance| region.find_instance(instance))
+            })
+            .flat_map(|el| el.get_zones())
+            .map(|zone| zone.get_history())
+            .collect::<Vec<_>>();
+        span(&histories, window)
+    } else {
+        None
+    };
 
     let printer = Printer::new(
         &regions_and_instances.0,
         &regions_and_instances.1,
         &regions_and_instances.2,
         favorite_regions,
-    );
+    )
+    .with_unit(query.unit())
+    .with_metric(query.metric())
+    .with_zone_columns(options.zone_columns)
+    .with_savings_columns(options.savings_columns)
+    .with_color(options.color)
+    .with_sparklines(sparklines);
+
+    match output {
+        OutputFormat::Text => printer.print(),
+        OutputFormat::Json => {
+            let report = json::render(
+                &regions_and_instances.0,
+                &regions_and_instances.1,
+                &regions_and_instances.2,
+                favorite_regions,
+                query.unit(),
+                query.metric(),
+                &diagnostics,
+            )?;
+
+            println!("{report}");
+        }
+        OutputFormat::Csv | OutputFormat::Markdown => {
+            print!("{}", printer.table().render(output)?);
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use crate::pricing::Term;
+    use pretty_assertions::assert_eq;
+
+    fn fixture() -> Fixture {
+        Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a"])
+            .with_instance("us-east-1", "c7g.16xlarge", 64)
+            .with_instance("us-east-1", "c7g.8xlarge", 32)
+            .with_spot_prices("us-east-2", "us-east-2a", "c7g.16xlarge", &[(0, 1.6)])
+            .with_spot_prices("us-east-2", "us-east-2a", "c7g.8xlarge", &[(0, 0.96)])
+            .with_ondemand_price("c7g.16xlarge", "us-east-2", 2.56)
+            .with_ondemand_price("c7g.8xlarge", "us-east-2", 1.6)
+    }
+
+    #[tokio::test]
+    async fn test_normalize() -> Result<()> {
+        let fixture = fixture();
+        let instances = [
+            InstanceType::from("c7g.16xlarge"),
+            InstanceType::from("c7g.8xlarge"),
+        ];
+
+        let diagnostics = Diagnostics::new();
+        let query = PriceQuery::default().with_unit(PriceUnit::Vcpu);
+        let (regions, instances_by_price, _) = collect(
+            &fixture,
+            &fixture,
+            &instances,
+            &["us-east-2".to_string()],
+            &query,
+            &TimeWindow::default(),
+            &diagnostics,
+        )
+        .await?;
+
+        assert!(diagnostics.is_empty());
+        // the smaller instance is more expensive per vCPU
+        assert_eq!(instances_by_price, vec!["c7g.8xlarge", "c7g.16xlarge"]);
+
+        let c7g = regions[0].find_instance("c7g.16xlarge").unwrap();
+        assert_eq!(c7g.get_ondemand_price(), 0.04);
+        assert_eq!(c7g.get_average_price(), Some(0.025));
+        assert_eq!(c7g.get_amount(), 64.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_normalize_without_resource() -> Result<()> {
+        let fixture = fixture();
+        let instances = [InstanceType::from("c7g.16xlarge")];
 
-    printer.print();
+        let diagnostics = Diagnostics::new();
+        let query = PriceQuery::default().with_unit(PriceUnit::Gpu);
+        let (regions, _, _) = collect(
+            &fixture,
+            &fixture,
+            &instances,
+            &["us-east-2".to_string()],
+            &query,
+            &TimeWindow::default(),
+            &diagnostics,
+        )
+        .await?;
 
-    Ok(())
+        assert!(regions[0].is_empty());
+        let entries = diagnostics.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].reason(), Reason::NoResource);
+        assert_eq!(entries[0].detail(), "c7g.16xlarge has no gpu");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reserved_term() -> Result<()> {
+        let fixture = Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a"])
+            .with_spot_prices("us-east-2", "us-east-2a", "c7g.16xlarge", &[(0, 0.9)])
+            .with_product(include_str!(
+                "../../testdata/price_list/c7g.16xlarge-us-east-2-reserved.json"
+            ));
+
+        let diagnostics = Diagnostics::new();
+        let query = PriceQuery::default().with_term(Term::Reserved);
+        let (regions, _, _) = collect(
+            &fixture,
+            &fixture,
+            &[InstanceType::from("c7g.16xlarge")],
+            &["us-east-2".to_string()],
+            &query,
+            &TimeWindow::default(),
+            &diagnostics,
+        )
+        .await?;
+
+        assert!(diagnostics.is_empty());
+        // the 3yr All Upfront fee spread over the hours of the term
+        let c7g = regions[0].find_instance("c7g.16xlarge").unwrap();
+        assert_eq!(c7g.get_ondemand_price(), 30756.0 / 26280.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dedicated_tenancy() {
+        let fixture = fixture();
+        let query = PriceQuery::default().with_tenancy(Tenancy::Dedicated);
+
+        let result = collect(
+            &fixture,
+            &fixture,
+            &[InstanceType::from("c7g.16xlarge")],
+            &["us-east-2".to_string()],
+            &query,
+            &TimeWindow::default(),
+            &Diagnostics::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_multi_thread() -> Result<()> {
+        // the providers' futures can move between the worker threads
+        let (regions, _, _) = tokio::spawn(async {
+            let fixture = fixture();
+            collect(
+                &fixture,
+                &fixture,
+                &[InstanceType::from("c7g.16xlarge")],
+                &["us-east-2".to_string()],
+                &PriceQuery::default(),
+                &TimeWindow::default(),
+                &Diagnostics::new(),
+            )
+            .await
+        })
+        .await
+        .expect("collecting the regions panicked")?;
+        assert_eq!(regions.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_matrix_options() {
+        assert_eq!("Regions".parse::<SavingsSort>(), Ok(SavingsSort::Regions));
+        assert_eq!(SavingsSort::Instances.to_string(), "instances");
+        assert!("zones".parse::<SavingsSort>().is_err());
+
+        let options = MatrixOptions::default()
+            .with_sort_by_savings(SavingsSort::Regions)
+            .with_sort_by_savings(SavingsSort::Instances);
+        assert!(options.regions_by_savings);
+        assert!(options.instances_by_savings);
+        assert!(!options.savings_columns);
+    }
 }
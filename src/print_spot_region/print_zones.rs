@@ -0,0 +1,219 @@
+use crate::diagnostics::Diagnostics;
+use crate::error::Result;
+use crate::output::{OutputFormat, Table};
+use crate::pricing::PriceQuery;
+use crate::print_spot_region::print_spot_regions::collect;
+use crate::print_spot_region::spot_region::SpotRegion;
+use crate::provider::{InstanceCatalog, ProductPrices, SpotPriceHistory, TimeWindow, ZoneCatalog};
+use crate::statistics::Metric;
+use aws_sdk_ec2::types::InstanceType;
+use serde::Serialize;
+
+const HEADER: &[&str] = &[
+    "region",
+    "instance",
+    "zone",
+    "zone id",
+    "min",
+    "avg",
+    "max",
+    "last",
+    "p50",
+    "p90",
+    "p99",
+    "stddev",
+    "changes",
+    "vs cheapest",
+];
+
+#[derive(Debug, Serialize)]
+// the spot statistics of an instance in one availability zone
+struct ZoneStatistics {
+    region: String,
+    instance_type: String,
+    availability_zone: String,
+    zone_id: Option<String>,
+    min: f64,
+    avg: f64,
+    max: f64,
+    last: f64,
+    p50: f64,
+    p90: f64,
+    p99: f64,
+    stddev: f64,
+    changes: u32,
+    // the metric divided by the one of the cheapest zone
+    vs_cheapest: Option<f64>,
+}
+
+// every zone of the instances in the regions, the cheapest zone by the metric first
+fn zone_statistics(
+    regions: &[SpotRegion],
+    instances: &[String],
+    metric: Metric,
+) -> Vec<ZoneStatistics> {
+    let mut rows = Vec::new();
+
+    for region in regions {
+        for instance in instances {
+            let Some(el) = region.find_instance(instance) else {
+                continue;
+            };
+
+            let mut zones = el.get_zones().iter().collect::<Vec<_>>();
+            zones.sort_by(|a, b| {
+                let a = a.get_statistics().get(metric);
+                a.total_cmp(&b.get_statistics().get(metric))
+            });
+            let cheapest = el
+                .get_cheapest_zone(metric)
+                .map(|zone| zone.get_statistics().get(metric));
+
+            for zone in zones {
+                let statistics = zone.get_statistics();
+                rows.push(ZoneStatistics {
+                    region: region.get_region().to_string(),
+                    instance_type: instance.clone(),
+                    availability_zone: zone.get_name().to_string(),
+                    zone_id: zone.get_zone_id().map(|id| id.to_string()),
+                    min: statistics.min(),
+                    avg: statistics.avg(),
+                    max: statistics.max(),
+                    last: statistics.last(),
+                    p50: statistics.percentile(0.5),
+                    p90: statistics.percentile(0.9),
+                    p99: statistics.percentile(0.99),
+                    stddev: statistics.stddev(),
+                    changes: statistics.changes(),
+                    vs_cheapest: cheapest
+                        .filter(|cheapest| *cheapest > 0.0)
+                        .map(|cheapest| statistics.get(metric) / cheapest),
+                });
+            }
+        }
+    }
+
+    rows
+}
+
+fn table(rows: &[ZoneStatistics]) -> Table {
+    let mut table = Table::new(HEADER);
+
+    for row in rows {
+        table.push(vec![
+            row.region.clone(),
+            row.instance_type.clone(),
+            row.availability_zone.clone(),
+            row.zone_id.clone().unwrap_or_default(),
+            format!("{:.5}", row.min),
+            format!("{:.5}", row.avg),
+            format!("{:.5}", row.max),
+            format!("{:.5}", row.last),
+            format!("{:.5}", row.p50),
+            format!("{:.5}", row.p90),
+            format!("{:.5}", row.p99),
+            format!("{:.5}", row.stddev),
+            row.changes.to_string(),
+            row.vs_cheapest
+                .map(|ratio| format!("{ratio:.2}"))
+                .unwrap_or_default(),
+        ]);
+    }
+
+    table
+}
+
+/// print the spot statistics of every availability zone of instances in regions
+///
+/// The zones of an instance are ordered by the metric of the query, cheapest first.
+/// Returns why zones are missing from the table.
+pub async fn print_zones<E: InstanceCatalog + ZoneCatalog + SpotPriceHistory, P: ProductPrices>(
+    ec2: &E,
+    pricing: &P,
+    instances: &[InstanceType],
+    region_names: &[String],
+    query: &PriceQuery,
+    window: &TimeWindow,
+    output: OutputFormat,
+) -> Result<Diagnostics> {
+    let diagnostics = Diagnostics::new();
+    let (regions, instance_names, _) = collect(
+        ec2,
+        pricing,
+        instances,
+        region_names,
+        query,
+        window,
+        &diagnostics,
+    )
+    .await?;
+    let rows = zone_statistics(&regions, &instance_names, query.metric());
+
+    match output {
+        OutputFormat::Text => {
+            if query.unit().is_normalized() {
+                println!("USD per {}", query.unit().label());
+            }
+            print!("{}", table(&rows).to_text());
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+        OutputFormat::Csv | OutputFormat::Markdown => {
+            print!("{}", table(&rows).render(output)?);
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use aws_sdk_ec2::primitives::DateTime;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_zone_statistics() -> Result<()> {
+        let fixture = Fixture::new()
+            .with_zone_ids(
+                "us-east-2",
+                &[("us-east-2a", "use2-az1"), ("us-east-2b", "use2-az2")],
+            )
+            .with_spot_prices(
+                "us-east-2",
+                "us-east-2a",
+                "c7g.16xlarge",
+                &[(0, 2.0), (1800, 3.0)],
+            )
+            .with_spot_prices("us-east-2", "us-east-2b", "c7g.16xlarge", &[(0, 1.0)])
+            .with_ondemand_price("c7g.16xlarge", "us-east-2", 4.0);
+
+        let diagnostics = Diagnostics::new();
+        let (regions, instances, _) = collect(
+            &fixture,
+            &fixture,
+            &[InstanceType::from("c7g.16xlarge")],
+            &["us-east-2".to_string()],
+            &PriceQuery::default(),
+            &TimeWindow::new(None, Some(DateTime::from_secs(3600))),
+            &diagnostics,
+        )
+        .await?;
+
+        let rows = zone_statistics(&regions, &instances, Metric::Avg);
+        assert_eq!(
+            table(&rows).render(OutputFormat::Csv)?,
+            "region,instance,zone,zone id,min,avg,max,last,p50,p90,p99,stddev,changes,vs cheapest\n\
+             us-east-2,c7g.16xlarge,us-east-2b,use2-az2,1.00000,1.00000,1.00000,1.00000,1.00000,1.00000,1.00000,0.00000,0,1.00\n\
+             us-east-2,c7g.16xlarge,us-east-2a,use2-az1,2.00000,2.50000,3.00000,3.00000,2.00000,3.00000,3.00000,0.50000,1,2.50\n"
+        );
+
+        // ordered by the lowest price
+        let rows = zone_statistics(&regions, &instances, Metric::Min);
+        assert_eq!(rows[0].availability_zone, "us-east-2b");
+        assert_eq!(rows[1].vs_cheapest, Some(2.0));
+
+        Ok(())
+    }
+}
@@ -1,23 +1,46 @@
+use crate::availability_zone::AvailabilityZone;
+use crate::chart::{sample, sparkline, SPARKLINE_WIDTH};
 use crate::get_f64_with_len;
 use crate::get_option_f64_with_len;
 use crate::get_string_with_len;
 use crate::get_string_with_len_and_padding;
+use crate::instance::Instance;
+use crate::normalize::PriceUnit;
+use crate::output::Table;
 use crate::print_spot_region::spot_region::SpotRegion;
+use crate::statistics::Metric;
+use aws_sdk_ec2::primitives::DateTime;
+
+// the savings from which a cell is green or yellow, red below
+const GREEN_SAVINGS: f64 = 0.6;
+const YELLOW_SAVINGS: f64 = 0.3;
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
 
 pub(crate) struct Printer {
     regions: Vec<SpotRegion>,
     instances: Vec<String>,
-    price_changes: Vec<f64>,
+    price_changes: Vec<Option<f64>>,
     region_width: usize,
     instance_width: usize,
     favorite_regions: Vec<String>,
+    unit: PriceUnit,
+    metric: Metric,
+    zone_columns: bool,
+    savings_columns: bool,
+    color: bool,
+    // the times the sparklines span, None without sparklines
+    sparklines: Option<(DateTime, DateTime)>,
 }
 
 impl Printer {
     pub(super) fn new(
         regions: &[SpotRegion],
         instances: &[String],
-        price_changes: &[f64],
+        price_changes: &[Option<f64>],
         favorite_regions: &[&str],
     ) -> Self {
         let is = instances.iter().map(|s| s.to_string()).collect::<Vec<_>>();
@@ -32,7 +55,62 @@ impl Printer {
             region_width: Self::get_widest_region(regions),
             instance_width: Self::get_widest_instance(instances),
             favorite_regions: fav_regions,
+            unit: PriceUnit::Instance,
+            metric: Metric::Avg,
+            zone_columns: false,
+            savings_columns: false,
+            color: false,
+            sparklines: None,
+        }
+    }
+
+    /// the unit the prices are divided by, named above the matrix
+    pub(super) fn with_unit(self, unit: PriceUnit) -> Self {
+        Self { unit, ..self }
+    }
+
+    /// the statistic of the spot prices in the cells, the average by default
+    pub(super) fn with_metric(self, metric: Metric) -> Self {
+        Self { metric, ..self }
+    }
+
+    /// add the cheapest zone and the spread between the zones of each instance
+    pub(super) fn with_zone_columns(self, zone_columns: bool) -> Self {
+        Self {
+            zone_columns,
+            ..self
+        }
+    }
+
+    /// add the savings, the spot to on-demand ratio and the spike ratio of each instance
+    pub(super) fn with_savings_columns(self, savings_columns: bool) -> Self {
+        Self {
+            savings_columns,
+            ..self
+        }
+    }
+
+    /// color the cells of the text matrix by their savings
+    pub(super) fn with_color(self, color: bool) -> Self {
+        Self { color, ..self }
+    }
+
+    /// add a sparkline of the spot prices from start to end to each cell
+    pub(super) fn with_sparklines(self, sparklines: Option<(DateTime, DateTime)>) -> Self {
+        Self { sparklines, ..self }
+    }
+
+    // what the cells hold unless it is the average spot price per instance-hour
+    fn caption(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if self.metric != Metric::Avg {
+            parts.push(format!("spot {}", self.metric));
+        }
+        if self.unit.is_normalized() {
+            parts.push(format!("USD per {}", self.unit.label()));
         }
+
+        (!parts.is_empty()).then(|| parts.join(", "))
     }
 
     pub(super) fn print(&self) {
@@ -43,6 +121,16 @@ impl Printer {
             .cloned()
             .collect::<Vec<_>>();
 
+        // the fixed-width matrix has no room for the zone and savings columns
+        if self.zone_columns || self.savings_columns {
+            print!("{}", self.build_table(self.color).to_text());
+            return;
+        }
+
+        if let Some(caption) = self.caption() {
+            println!("{caption}");
+        }
+
         self.print_line();
 
         self.print_regions(&fav_regions); // prices
@@ -62,12 +150,131 @@ impl Printer {
         self.print_line();
     }
 
+    /// the same matrix as a table, favorite regions first and without blank rows
+    pub(super) fn table(&self) -> Table {
+        self.build_table(false)
+    }
+
+    // the table, with colored price and savings cells for a terminal
+    fn build_table(&self, color: bool) -> Table {
+        let paint = |el: &Instance, cell: String| {
+            if color {
+                self.paint(el, &cell)
+            } else {
+                cell
+            }
+        };
+
+        let region = match self.caption() {
+            Some(caption) => format!("region ({caption})"),
+            None => "region".to_string(),
+        };
+        let mut header = vec![region];
+        for instance in &self.instances {
+            header.push(instance.clone());
+            if self.zone_columns {
+                header.push(format!("{instance} cheapest AZ"));
+                header.push(format!("{instance} AZ spread"));
+            }
+            if self.savings_columns {
+                header.push(format!("{instance} savings"));
+                header.push(format!("{instance} spot/on-demand"));
+                header.push(format!("{instance} spike"));
+            }
+            if self.sparklines.is_some() {
+                header.push(format!("{instance} trend"));
+            }
+        }
+        let header = header.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut table = Table::new(&header);
+
+        let (favorites, others): (Vec<&SpotRegion>, Vec<&SpotRegion>) = self
+            .regions
+            .iter()
+            .filter(|r| !r.is_empty())
+            .partition(|r| self.favorite_regions.contains(&r.get_region().to_string()));
+
+        for region in favorites.into_iter().chain(others) {
+            let mut row = vec![region.get_region().to_string()];
+            for instance in &self.instances {
+                let el = region.find_instance(instance);
+                row.push(
+                    el.map(|el| paint(el, format_prices(el, self.metric)))
+                        .unwrap_or_default(),
+                );
+                if self.zone_columns {
+                    row.push(
+                        el.and_then(|el| el.get_cheapest_zone(self.metric))
+                            .map(format_zone)
+                            .unwrap_or_default(),
+                    );
+                    row.push(
+                        el.and_then(|el| el.get_zone_spread(self.metric))
+                            .map(|spread| format!("{spread:.2}"))
+                            .unwrap_or_default(),
+                    );
+                }
+                if self.savings_columns {
+                    row.push(
+                        el.and_then(|el| {
+                            let savings = el.get_savings(self.metric)?;
+                            Some(paint(el, format!("{:.1}%", savings * 100.0)))
+                        })
+                        .unwrap_or_default(),
+                    );
+                    row.push(
+                        el.and_then(|el| el.get_spot_ratio(self.metric))
+                            .map(|ratio| format!("{ratio:.2}"))
+                            .unwrap_or_default(),
+                    );
+                    row.push(
+                        el.and_then(Instance::get_spike_ratio)
+                            .map(|ratio| format!("{ratio:.2}"))
+                            .unwrap_or_default(),
+                    );
+                }
+                if self.sparklines.is_some() {
+                    row.push(el.and_then(|el| self.sparkline(el)).unwrap_or_default());
+                }
+            }
+            table.push(row);
+        }
+
+        let mut row = vec!["price change".to_string()];
+        for change in &self.price_changes {
+            row.push(
+                change
+                    .map(|change| format!("{change:.5}"))
+                    .unwrap_or_default(),
+            );
+            if self.zone_columns {
+                row.extend([String::new(), String::new()]);
+            }
+            if self.savings_columns {
+                row.extend([String::new(), String::new(), String::new()]);
+            }
+            if self.sparklines.is_some() {
+                row.push(String::new());
+            }
+        }
+        table.push(row);
+
+        table
+    }
+
     fn get_widest_instance(instances: &[String]) -> usize {
-        instances.iter().map(|el| el.len()).max().unwrap()
+        instances
+            .iter()
+            .map(|el| el.len())
+            .max()
+            .unwrap_or_default()
     }
 
     fn get_widest_region(data: &[SpotRegion]) -> usize {
-        data.iter().map(|el| el.get_region().len()).max().unwrap()
+        data.iter()
+            .map(|el| el.get_region().len())
+            .max()
+            .unwrap_or_default()
     }
 
     fn print_line(&self) {
@@ -75,7 +282,9 @@ impl Printer {
             "|{}|",
             get_string_with_len_and_padding(
                 "-",
-                self.region_width + 2 + (self.instance_width + 3) * self.instances.len(),
+                self.region_width
+                    + 2
+                    + (self.instance_width + 3 + self.sparkline_width()) * self.instances.len(),
                 '-',
             )
         );
@@ -85,6 +294,7 @@ impl Printer {
         print!("| {} |", get_string_with_len("", self.region_width));
         for instance in &self.instances {
             print!(" {} |", get_string_with_len(instance, self.instance_width));
+            self.print_sparkline(None);
         }
         println!();
     }
@@ -106,33 +316,323 @@ impl Printer {
     fn print_instances(&self, region: &SpotRegion) {
         for instance in &self.instances {
             if let Some(el) = region.find_instance(instance) {
+                if let Some(savings_plan_rate) = el.get_savings_plan_rate() {
+                    let width = self.instance_width.saturating_sub(8) / 3; //2*` ` + 2*` / `
+                    let cell = format!(
+                        "  {} / {} / {}",
+                        get_option_f64_with_len(el.get_spot_metric(self.metric), width),
+                        get_f64_with_len(savings_plan_rate, width),
+                        get_f64_with_len(el.get_ondemand_price(), width)
+                    );
+                    let cell = get_string_with_len(&cell, self.instance_width + 1);
+                    print!("{} |", self.paint(el, &cell));
+                    self.print_sparkline(Some(el));
+                    continue;
+                }
+
                 let budget = self.instance_width - 2 - 2 - 1; //4*` ` + 1*/
+                let cell = format!(
+                    "  {} / {} ",
+                    get_option_f64_with_len(el.get_spot_metric(self.metric), budget / 2),
+                    get_f64_with_len(el.get_ondemand_price(), budget / 2)
+                );
                 if budget % 2 == 1 {
-                    print!(
-                        "  {} / {}   |",
-                        get_option_f64_with_len(el.get_average_price(), budget / 2),
-                        get_f64_with_len(el.get_ondemand_price(), budget / 2)
-                    )
+                    print!("{}  |", self.paint(el, &cell))
                 } else {
-                    print!(
-                        "  {} / {}  |",
-                        get_option_f64_with_len(el.get_average_price(), budget / 2),
-                        get_f64_with_len(el.get_ondemand_price(), budget / 2)
-                    )
+                    print!("{} |", self.paint(el, &cell))
                 }
+                self.print_sparkline(Some(el));
             } else {
                 // no instance found
                 print!(" {} |", get_string_with_len("", self.instance_width));
+                self.print_sparkline(None);
             }
         }
     }
 
+    // the cell in the color of its savings, unchanged without colors or savings
+    fn paint(&self, el: &Instance, cell: &str) -> String {
+        if !self.color {
+            return cell.to_string();
+        }
+
+        match el.get_savings(self.metric) {
+            Some(savings) => format!("{}{cell}{RESET}", savings_color(savings)),
+            None => cell.to_string(),
+        }
+    }
+
     fn print_price_changes(&self) {
         print!("| {} |", get_string_with_len("", self.region_width));
         for change in &self.price_changes {
-            print!("  {} |", get_f64_with_len(*change, self.instance_width - 1))
+            print!(
+                "  {} |",
+                get_option_f64_with_len(*change, self.instance_width - 1)
+            );
+            self.print_sparkline(None);
         }
         print!(" {} |", get_string_with_len("", self.instance_width));
+        self.print_sparkline(None);
         println!();
     }
+
+    // the trend of the mean spot price of all zones, None without sparklines or prices
+    fn sparkline(&self, el: &Instance) -> Option<String> {
+        let (start, end) = self.sparklines?;
+        let histories = el
+            .get_zones()
+            .iter()
+            .map(|zone| zone.get_history())
+            .collect::<Vec<_>>();
+        let samples = sample(&histories, start, end, SPARKLINE_WIDTH);
+
+        samples
+            .iter()
+            .any(Option::is_some)
+            .then(|| sparkline(&samples))
+    }
+
+    // the width a sparkline adds to a column of the text matrix
+    fn sparkline_width(&self) -> usize {
+        match self.sparklines {
+            Some(_) => usize::try_from(SPARKLINE_WIDTH).unwrap_or_default() + 3,
+            None => 0,
+        }
+    }
+
+    // the sparkline cell after a price cell, blank without an instance
+    fn print_sparkline(&self, el: Option<&Instance>) {
+        if self.sparklines.is_none() {
+            return;
+        }
+        let line = el
+            .and_then(|el| self.sparkline(el))
+            .unwrap_or_else(|| " ".repeat(self.sparkline_width() - 3));
+        print!(" {line} |");
+    }
+}
+
+/// spot / on-demand, or spot / Savings Plan / on-demand with a selected plan,
+/// with the metric of the spot prices
+pub(super) fn format_prices(el: &Instance, metric: Metric) -> String {
+    let spot = match el.get_spot_metric(metric) {
+        Some(changes) if metric == Metric::Changes => format!("{changes}"),
+        Some(price) => format!("{price:.5}"),
+        None => "-".to_string(),
+    };
+
+    match el.get_savings_plan_rate() {
+        Some(rate) => format!("{spot} / {rate:.5} / {:.5}", el.get_ondemand_price()),
+        None => format!("{spot} / {:.5}", el.get_ondemand_price()),
+    }
+}
+
+/// the terminal color of a share saved with spot: green from 60%, yellow from 30%, red below
+pub(super) fn savings_color(savings: f64) -> &'static str {
+    if savings >= GREEN_SAVINGS {
+        GREEN
+    } else if savings >= YELLOW_SAVINGS {
+        YELLOW
+    } else {
+        RED
+    }
+}
+
+/// the name of a zone with its zone ID, e.g. `us-east-2a (use2-az1)`
+pub(super) fn format_zone(zone: &AvailabilityZone) -> String {
+    match zone.get_zone_id() {
+        Some(id) => format!("{} ({id})", zone.get_name()),
+        None => zone.get_name().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::Result;
+    use crate::output::OutputFormat;
+    use crate::provider::Zone;
+    use crate::statistics::SpotStatistics;
+    use aws_sdk_ec2::types::InstanceType;
+    use pretty_assertions::assert_eq;
+
+    fn region(name: &str, spot: Option<f64>, ondemand: f64) -> SpotRegion {
+        let zones = spot
+            .map(|price| {
+                let history = [(DateTime::from_secs(0), price)];
+                let statistics = SpotStatistics::new(&history, None, DateTime::from_secs(3600));
+                vec![AvailabilityZone::new(
+                    &Zone::new("a", None),
+                    statistics.unwrap(),
+                )]
+            })
+            .unwrap_or_default();
+
+        let mut region = SpotRegion::new(name);
+        region.add(
+            "c7g.16xlarge",
+            Instance::new(name, InstanceType::from("c7g.16xlarge"), &zones, ondemand),
+        );
+        region
+    }
+
+    #[test]
+    fn test_table() -> Result<()> {
+        let regions = [
+            region("us-west-2", Some(1.0), 2.0),
+            region("us-east-2", None, 2.5),
+            SpotRegion::new("eu-north-1"),
+        ];
+        let instances = ["c7g.16xlarge".to_string(), "c7g.8xlarge".to_string()];
+        let printer = Printer::new(&regions, &instances, &[Some(2.0)], &["us-east-2"]);
+
+        assert_eq!(
+            printer.table().render(OutputFormat::Csv)?,
+            "region,c7g.16xlarge,c7g.8xlarge\n\
+             us-east-2,- / 2.50000,\n\
+             us-west-2,1.00000 / 2.00000,\n\
+             price change,2.00000,\n"
+        );
+
+        let printer = printer.with_unit(PriceUnit::Vcpu);
+        assert_eq!(printer.table().header()[0], "region (USD per vCPU-hour)");
+
+        let printer = printer.with_metric(Metric::P90);
+        assert_eq!(
+            printer.table().header()[0],
+            "region (spot p90, USD per vCPU-hour)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zone_columns() -> Result<()> {
+        let zone = |name: &str, id: &str, price: f64| {
+            let history = [(DateTime::from_secs(0), price)];
+            let statistics = SpotStatistics::new(&history, None, DateTime::from_secs(3600));
+            AvailabilityZone::new(&Zone::new(name, Some(id)), statistics.unwrap())
+        };
+        let zones = [
+            zone("us-east-2a", "use2-az1", 1.0),
+            zone("us-east-2b", "use2-az2", 1.5),
+        ];
+        let mut region = SpotRegion::new("us-east-2");
+        region.add(
+            "c7g.16xlarge",
+            Instance::new("us-east-2", InstanceType::from("c7g.16xlarge"), &zones, 2.0),
+        );
+
+        let instances = ["c7g.16xlarge".to_string()];
+        let printer = Printer::new(&[region], &instances, &[], &[]).with_zone_columns(true);
+
+        assert_eq!(
+            printer.table().render(OutputFormat::Csv)?,
+            "region,c7g.16xlarge,c7g.16xlarge cheapest AZ,c7g.16xlarge AZ spread\n\
+             us-east-2,1.25000 / 2.00000,us-east-2a (use2-az1),1.50\n\
+             price change,,,\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_savings_columns() -> Result<()> {
+        let regions = [
+            region("us-west-2", Some(0.5), 2.0),
+            region("us-east-2", None, 2.5),
+        ];
+        let instances = ["c7g.16xlarge".to_string()];
+        let printer = Printer::new(&regions, &instances, &[], &[]).with_savings_columns(true);
+
+        assert_eq!(
+            printer.table().render(OutputFormat::Csv)?,
+            "region,c7g.16xlarge,c7g.16xlarge savings,c7g.16xlarge spot/on-demand,c7g.16xlarge spike\n\
+             us-west-2,0.50000 / 2.00000,75.0%,0.25,0.25\n\
+             us-east-2,- / 2.50000,,,\n\
+             price change,,,,\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_savings_color() {
+        assert_eq!(savings_color(0.75), GREEN);
+        assert_eq!(savings_color(0.6), GREEN);
+        assert_eq!(savings_color(0.3), YELLOW);
+        assert_eq!(savings_color(0.1), RED);
+        assert_eq!(savings_color(-0.2), RED);
+
+        let region = region("us-west-2", Some(1.0), 2.0);
+        let el = region.find_instance("c7g.16xlarge").unwrap();
+        let instances = ["c7g.16xlarge".to_string()];
+        let printer = Printer::new(&[region.clone()], &instances, &[], &[]);
+        assert_eq!(printer.paint(el, "1 / 2"), "1 / 2");
+
+        let printer = printer.with_color(true);
+        assert_eq!(printer.paint(el, "1 / 2"), "\x1b[33m1 / 2\x1b[0m");
+
+        // the table for a terminal, but never the one of other formats
+        let printer = printer.with_savings_columns(true);
+        let row = format!(
+            "us-west-2  {YELLOW}1.00000 / 2.00000{RESET}  {YELLOW}50.0%{RESET}{}0.50{}0.50",
+            " ".repeat(17),
+            " ".repeat(25)
+        );
+        assert_eq!(
+            printer.build_table(true).to_text().lines().nth(1),
+            Some(row.as_str())
+        );
+        assert!(!printer.table().to_text().contains('\x1b'));
+    }
+
+    #[test]
+    fn test_sparklines() -> Result<()> {
+        let history = [
+            (DateTime::from_secs(0), 1.0),
+            (DateTime::from_secs(1800), 2.0),
+        ];
+        let statistics = SpotStatistics::new(&history, None, DateTime::from_secs(3600));
+        let zone = AvailabilityZone::new(&Zone::new("us-east-2a", None), statistics.unwrap())
+            .with_history(&history);
+        let mut region = SpotRegion::new("us-east-2");
+        region.add(
+            "c7g.16xlarge",
+            Instance::new(
+                "us-east-2",
+                InstanceType::from("c7g.16xlarge"),
+                &[zone],
+                4.0,
+            ),
+        );
+
+        let instances = ["c7g.16xlarge".to_string()];
+        let span = (DateTime::from_secs(0), DateTime::from_secs(3600));
+        let printer = Printer::new(&[region], &instances, &[], &[]).with_sparklines(Some(span));
+
+        assert_eq!(
+            printer.table().render(OutputFormat::Csv)?,
+            "region,c7g.16xlarge,c7g.16xlarge trend\n\
+             us-east-2,1.50000 / 4.00000,▁▁▁▁▁███████\n\
+             price change,,\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_prices() {
+        let region = region("us-west-2", Some(1.0), 2.0);
+        let el = region.find_instance("c7g.16xlarge").unwrap();
+        assert_eq!(format_prices(el, Metric::Avg), "1.00000 / 2.00000");
+        assert_eq!(format_prices(el, Metric::Changes), "0 / 2.00000");
+
+        let el = el.clone().with_savings_plan_rate(Some(1.5));
+        assert_eq!(
+            format_prices(&el, Metric::Avg),
+            "1.00000 / 1.50000 / 2.00000"
+        );
+    }
 }
@@ -1,12 +1,14 @@
 use crate::instance::Instance;
 use crate::print_spot_region::spot_region::SpotRegion;
+use crate::statistics::Metric;
 use aws_sdk_ec2::types::InstanceType;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 pub(crate) fn reorder(
     regions: &[SpotRegion],
     instances: &[InstanceType],
-) -> (Vec<SpotRegion>, Vec<String>, Vec<f64>) {
+) -> (Vec<SpotRegion>, Vec<String>, Vec<Option<f64>>) {
     let prices: HashMap<String, Option<f64>> = get_average_instance_prices(regions, instances);
 
     let mut reordered_instances = prices.iter().collect::<Vec<_>>();
@@ -51,7 +53,7 @@ fn extract_instance_data(
 fn find_completes_instance(
     regions: &[SpotRegion],
     instance_types: &[InstanceType],
-) -> InstanceType {
+) -> Option<InstanceType> {
     let mut instances: HashMap<InstanceType, u32> = HashMap::new();
 
     for instance in instance_types {
@@ -72,11 +74,14 @@ fn find_completes_instance(
 
     counts.sort_unstable_by_key(|p| p.1);
 
-    counts.pop().unwrap().0
+    counts.pop().map(|count| count.0)
 }
 
 fn reorder_regions(regions: &[SpotRegion], instances: &[InstanceType]) -> Vec<SpotRegion> {
-    let most_complete = find_completes_instance(regions, instances);
+    // no prices at all, e.g. no instance has GPUs to normalize by
+    let Some(most_complete) = find_completes_instance(regions, instances) else {
+        return regions.to_vec();
+    };
     let instance_data = extract_instance_data(most_complete, regions);
 
     let mut data: Vec<(usize, f64)> = Vec::new();
@@ -93,7 +98,7 @@ fn reorder_regions(regions: &[SpotRegion], instances: &[InstanceType]) -> Vec<Sp
         }
     }
 
-    data.sort_unstable_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    data.sort_unstable_by(|a, b| a.1.total_cmp(&b.1));
 
     let rows = data.iter().map(|a| a.0).collect::<Vec<usize>>();
 
@@ -128,41 +133,21 @@ fn get_average_instance_prices(
     averages
 }
 
+// the average price of each instance divided by the one of the next instance,
+// None where a price is unknown or zero
 fn find_price_changes(
     instances: &[String],
     average_prices: &HashMap<String, Option<f64>>,
-) -> Vec<f64> {
-    let mut changes = Vec::with_capacity(instances.len());
-
-    for index in 0..instances.len() - 1 {
-        let i0 = instances[index].clone();
-        let i1 = instances[index + 1].clone();
-
-        if let (Some(p0), Some(p1)) = (average_prices.get(&i0), average_prices.get(&i1)) {
-            if let Some(pt1) = p1 {
-                // has spot pricej
-                if *pt1 == 0.0 {
-                    println!("{i0} {i1}");
-                    changes.push(0.0);
-                }
-                //} else {
-            }
-            if let Some(pt1) = p1 {
-                if let Some(pt0) = p0 {
-                    changes.push(pt0 / pt1);
-                }
-            }
-
-            //if *p1 == 0.0 {
-            //    println!("{} {}", i0, i1);
-            //    changes.push(0.0);
-            //} else {
-            //    changes.push(p0 / p1);
-            //}
-        }
-    }
-
-    changes
+) -> Vec<Option<f64>> {
+    let price = |instance: &String| average_prices.get(instance).copied().flatten();
+
+    instances
+        .windows(2)
+        .map(|pair| {
+            let (p0, p1) = (price(&pair[0])?, price(&pair[1])?);
+            (p1 > 0.0).then(|| p0 / p1)
+        })
+        .collect()
 }
 
 fn reorder_data_by(rows: &[usize], regions: &[SpotRegion]) -> Vec<SpotRegion> {
@@ -174,3 +159,144 @@ fn reorder_data_by(rows: &[usize], regions: &[SpotRegion]) -> Vec<SpotRegion> {
 
     result
 }
+
+/// the regions with the highest mean savings of their instances first
+pub(crate) fn sort_regions_by_savings(
+    regions: &[SpotRegion],
+    instances: &[String],
+    metric: Metric,
+) -> Vec<SpotRegion> {
+    let mut sorted = regions
+        .iter()
+        .map(|region| {
+            let savings = instances
+                .iter()
+                .filter_map(|instance| region.find_instance(instance))
+                .filter_map(|el| el.get_savings(metric));
+            (mean(savings), region)
+        })
+        .collect::<Vec<_>>();
+
+    sorted.sort_by(|a, b| highest_first(a.0, b.0));
+
+    sorted
+        .into_iter()
+        .map(|(_, region)| region.clone())
+        .collect()
+}
+
+/// the instances with the highest mean savings over the regions first,
+/// with the price changes between them
+pub(crate) fn sort_instances_by_savings(
+    regions: &[SpotRegion],
+    instances: &[String],
+    metric: Metric,
+) -> (Vec<String>, Vec<Option<f64>>) {
+    let mut sorted = instances
+        .iter()
+        .map(|instance| {
+            let savings = regions
+                .iter()
+                .filter_map(|region| region.find_instance(instance))
+                .filter_map(|el| el.get_savings(metric));
+            (mean(savings), instance.clone())
+        })
+        .collect::<Vec<_>>();
+
+    sorted.sort_by(|a, b| highest_first(a.0, b.0));
+
+    let sorted = sorted
+        .into_iter()
+        .map(|(_, instance)| instance)
+        .collect::<Vec<_>>();
+
+    let instance_types = sorted
+        .iter()
+        .map(|instance| InstanceType::from(instance.as_str()))
+        .collect::<Vec<_>>();
+    let prices = get_average_instance_prices(regions, &instance_types);
+    let price_changes = find_price_changes(&sorted, &prices);
+
+    (sorted, price_changes)
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0.0), |(sum, count), value| (sum + value, count + 1.0));
+    (count > 0.0).then(|| sum / count)
+}
+
+// descending, unknown values last
+fn highest_first(a: Option<f64>, b: Option<f64>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::availability_zone::AvailabilityZone;
+    use crate::provider::Zone;
+    use crate::statistics::SpotStatistics;
+    use aws_sdk_ec2::primitives::DateTime;
+    use pretty_assertions::assert_eq;
+
+    fn region(name: &str, prices: &[(&str, f64, f64)]) -> SpotRegion {
+        let mut region = SpotRegion::new(name);
+        for &(instance, spot, ondemand) in prices {
+            let history = [(DateTime::from_secs(0), spot)];
+            let statistics = SpotStatistics::new(&history, None, DateTime::from_secs(3600));
+            let zone = AvailabilityZone::new(&Zone::new("a", None), statistics.unwrap());
+            region.add(
+                instance,
+                Instance::new(name, InstanceType::from(instance), &[zone], ondemand),
+            );
+        }
+        region
+    }
+
+    #[test]
+    fn test_sort_by_savings() {
+        let regions = [
+            region(
+                "us-east-2",
+                &[("c7g.16xlarge", 1.0, 2.0), ("c7g.8xlarge", 0.2, 1.0)],
+            ),
+            region("eu-north-1", &[("c7g.16xlarge", 0.5, 2.0)]),
+            SpotRegion::new("ap-south-2"),
+        ];
+        let instances = ["c7g.16xlarge".to_string(), "c7g.8xlarge".to_string()];
+
+        // 75% in eu-north-1, 65% in us-east-2
+        let sorted = sort_regions_by_savings(&regions, &instances, Metric::Avg);
+        let names = sorted.iter().map(|r| r.get_region()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["eu-north-1", "us-east-2", "ap-south-2"]);
+
+        // 80% for c7g.8xlarge, 62.5% for c7g.16xlarge
+        let (sorted, price_changes) = sort_instances_by_savings(&regions, &instances, Metric::Avg);
+        assert_eq!(sorted, vec!["c7g.8xlarge", "c7g.16xlarge"]);
+        assert_eq!(price_changes, vec![Some(0.5)]);
+    }
+
+    #[test]
+    fn test_price_changes() {
+        let prices = HashMap::from([
+            ("a".to_string(), Some(4.0)),
+            ("b".to_string(), Some(2.0)),
+            ("c".to_string(), None),
+            ("d".to_string(), Some(0.0)),
+            ("e".to_string(), Some(1.0)),
+        ]);
+        let instances = ["a", "b", "c", "d", "e"].map(String::from);
+
+        // unknown next to an instance without prices or a zero price
+        assert_eq!(
+            find_price_changes(&instances, &prices),
+            vec![Some(2.0), None, None, Some(0.0)]
+        );
+    }
+}
@@ -0,0 +1,497 @@
+use crate::availability_zone::AvailabilityZone;
+use crate::diagnostics::Diagnostics;
+use crate::error::Result;
+use crate::output::Table;
+use crate::pricing::PriceQuery;
+use crate::print_instances::instance_table;
+use crate::print_spot_region::print_spot_regions::collect;
+use crate::print_spot_region::printer::format_prices;
+use crate::print_spot_region::spot_region::SpotRegion;
+use crate::provider::{InstanceCatalog, ProductPrices, SpotPriceHistory, TimeWindow, ZoneCatalog};
+use crate::statistics::Metric;
+use aws_sdk_ec2::primitives::{DateTime, DateTimeFormat};
+use aws_sdk_ec2::types::InstanceType;
+use std::fmt::Write;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: right; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th[data-order="asc"]::after { content: " \25B2"; }
+th[data-order="desc"]::after { content: " \25BC"; }
+td:first-child, th:first-child { text-align: left; }
+tr.favorite td { background: #fff3c4; font-weight: bold; }
+tfoot td { font-style: italic; }
+input.filter { margin-bottom: 0.5em; }
+details { margin-bottom: 0.5em; }
+summary { cursor: pointer; font-weight: bold; }
+.charts { display: flex; flex-wrap: wrap; gap: 1em; }
+.chart { border: 1px solid #ccc; }
+.chart text { font-size: 10px; fill: #444; }
+"#;
+
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable").forEach(table => {
+  table.tHead.querySelectorAll("th").forEach((th, column) => {
+    th.addEventListener("click", () => {
+      const ascending = th.dataset.order !== "asc";
+      table.tHead.querySelectorAll("th").forEach(other => delete other.dataset.order);
+      th.dataset.order = ascending ? "asc" : "desc";
+      const value = row => {
+        const cell = row.cells[column];
+        return cell ? (cell.dataset.value ?? cell.textContent) : "";
+      };
+      const body = table.tBodies[0];
+      Array.from(body.rows)
+        .sort((a, b) => {
+          const x = value(a), y = value(b);
+          const nx = parseFloat(x), ny = parseFloat(y);
+          const order = isNaN(nx) || isNaN(ny) ? x.localeCompare(y) : nx - ny;
+          return ascending ? order : -order;
+        })
+        .forEach(row => body.appendChild(row));
+    });
+  });
+});
+document.querySelectorAll("input.filter").forEach(input => {
+  input.addEventListener("input", () => {
+    const text = input.value.toLowerCase();
+    const table = document.getElementById(input.dataset.table);
+    Array.from(table.tBodies[0].rows).forEach(row => {
+      row.hidden = !row.textContent.toLowerCase().includes(text);
+    });
+  });
+});
+"#;
+
+const COLORS: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b",
+];
+
+const WIDTH: f64 = 480.0;
+const HEIGHT: f64 = 200.0;
+const MARGIN: f64 = 40.0;
+
+/// a self-contained HTML report of the spot prices and instances
+///
+/// The page has no external dependencies, the charts are inline SVG.
+pub async fn render_report<E, P>(
+    ec2: &E,
+    pricing: &P,
+    instances: &[InstanceType],
+    region_names: &[String],
+    favorite_regions: &[&str],
+    query: &PriceQuery,
+    window: &TimeWindow,
+) -> Result<String>
+where
+    E: InstanceCatalog + ZoneCatalog + SpotPriceHistory,
+    P: ProductPrices,
+{
+    let diagnostics = Diagnostics::new();
+    let (regions, instance_names, price_changes) = collect(
+        ec2,
+        pricing,
+        instances,
+        region_names,
+        query,
+        window,
+        &diagnostics,
+    )
+    .await?;
+    let instance_table = instance_table(ec2, instances, &diagnostics).await;
+
+    let spot = match query.metric() {
+        Metric::Avg => "the average spot price".to_string(),
+        metric => format!("the {metric} of the spot prices"),
+    };
+
+    let mut html = String::new();
+
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>EC2 spot price report</title>")?;
+    writeln!(html, "<style>{STYLE}</style>\n</head>\n<body>")?;
+    writeln!(html, "<h1>EC2 spot price report</h1>")?;
+    writeln!(
+        html,
+        "<p>Generated {}, spot prices from {} to {}. Cells show {} / the on-demand price in USD per {}.</p>",
+        format_time(&DateTime::from(std::time::SystemTime::now())),
+        window
+            .start()
+            .map(|t| format_time(&t))
+            .unwrap_or_else(|| "the oldest price".to_string()),
+        window
+            .end()
+            .map(|t| format_time(&t))
+            .unwrap_or_else(|| "now".to_string()),
+        spot,
+        query.unit().label(),
+    )?;
+
+    writeln!(html, "<h2>Spot prices by region</h2>")?;
+    write_spot_table(
+        &mut html,
+        &regions,
+        &instance_names,
+        &price_changes,
+        favorite_regions,
+        query.metric(),
+    )?;
+
+    writeln!(html, "<h2>Instances</h2>")?;
+    write_table(&mut html, "instances", &instance_table)?;
+
+    if !diagnostics.is_empty() {
+        writeln!(html, "<h2>Missing prices</h2>")?;
+        write_table(&mut html, "missing", &missing_table(&diagnostics))?;
+    }
+
+    writeln!(html, "<h2>Spot price history</h2>")?;
+    write_charts(
+        &mut html,
+        &regions,
+        &instance_names,
+        favorite_regions,
+        window,
+    )?;
+
+    writeln!(html, "<script>{SCRIPT}</script>\n</body>\n</html>")?;
+
+    Ok(html)
+}
+
+fn write_filter(html: &mut String, id: &str) -> Result<()> {
+    writeln!(
+        html,
+        "<input class=\"filter\" type=\"search\" placeholder=\"filter\" data-table=\"{id}\">"
+    )?;
+    Ok(())
+}
+
+fn write_spot_table(
+    html: &mut String,
+    regions: &[SpotRegion],
+    instances: &[String],
+    price_changes: &[Option<f64>],
+    favorite_regions: &[&str],
+    metric: Metric,
+) -> Result<()> {
+    write_filter(html, "spot-regions")?;
+    writeln!(html, "<table id=\"spot-regions\" class=\"sortable\">")?;
+
+    write!(html, "<thead><tr><th>region</th>")?;
+    for instance in instances {
+        write!(html, "<th>{}</th>", escape(instance))?;
+    }
+    writeln!(html, "</tr></thead>\n<tbody>")?;
+
+    for region in regions.iter().filter(|r| !r.is_empty()) {
+        let name = region.get_region();
+        if favorite_regions.contains(&name.as_ref()) {
+            write!(html, "<tr class=\"favorite\">")?;
+        } else {
+            write!(html, "<tr>")?;
+        }
+        write!(html, "<td>{}</td>", escape(&name))?;
+
+        for instance in instances {
+            match region.find_instance(instance) {
+                Some(el) => match el.get_spot_metric(metric) {
+                    Some(spot) => write!(
+                        html,
+                        "<td data-value=\"{spot}\">{}</td>",
+                        format_prices(el, metric)
+                    )?,
+                    None => write!(html, "<td>{}</td>", format_prices(el, metric))?,
+                },
+                None => write!(html, "<td></td>")?,
+            }
+        }
+        writeln!(html, "</tr>")?;
+    }
+
+    write!(html, "</tbody>\n<tfoot><tr><td>price change</td>")?;
+    for change in price_changes {
+        match change {
+            Some(change) => write!(html, "<td>{change:.5}</td>")?,
+            None => write!(html, "<td></td>")?,
+        }
+    }
+    writeln!(html, "</tr></tfoot>\n</table>")?;
+
+    Ok(())
+}
+
+fn write_table(html: &mut String, id: &str, table: &Table) -> Result<()> {
+    write_filter(html, id)?;
+    writeln!(html, "<table id=\"{id}\" class=\"sortable\">")?;
+
+    write!(html, "<thead><tr>")?;
+    for cell in table.header() {
+        write!(html, "<th>{}</th>", escape(cell))?;
+    }
+    writeln!(html, "</tr></thead>\n<tbody>")?;
+
+    for row in table.rows() {
+        write!(html, "<tr>")?;
+        for cell in row {
+            write!(html, "<td>{}</td>", escape(cell))?;
+        }
+        writeln!(html, "</tr>")?;
+    }
+
+    writeln!(html, "</tbody>\n</table>")?;
+
+    Ok(())
+}
+
+// why cells of the tables are empty
+fn missing_table(diagnostics: &Diagnostics) -> Table {
+    let mut table = Table::new(&["region", "instance", "reason", "detail"]);
+
+    for entry in diagnostics.entries() {
+        table.push(vec![
+            entry.region().unwrap_or("-").to_string(),
+            entry.instance().to_string(),
+            entry.reason().to_string(),
+            entry.detail().to_string(),
+        ]);
+    }
+
+    table
+}
+
+fn write_charts(
+    html: &mut String,
+    regions: &[SpotRegion],
+    instances: &[String],
+    favorite_regions: &[&str],
+    window: &TimeWindow,
+) -> Result<()> {
+    for region in regions.iter().filter(|r| !r.is_empty()) {
+        let name = region.get_region();
+
+        // only the favorite regions are expanded
+        let open = if favorite_regions.contains(&name.as_ref()) {
+            " open"
+        } else {
+            ""
+        };
+        writeln!(
+            html,
+            "<details{open}><summary>{}</summary>\n<div class=\"charts\">",
+            escape(&name)
+        )?;
+
+        for instance in instances {
+            let Some(el) = region.find_instance(instance) else {
+                continue;
+            };
+            if let Some(svg) = chart(instance, el.get_zones(), window)? {
+                writeln!(html, "{svg}")?;
+            }
+        }
+
+        writeln!(html, "</div>\n</details>")?;
+    }
+
+    Ok(())
+}
+
+// a step chart of the spot prices of an instance, one line per availability zone
+fn chart(
+    instance: &str,
+    zones: &[AvailabilityZone],
+    window: &TimeWindow,
+) -> Result<Option<String>> {
+    let histories = zones
+        .iter()
+        .filter(|zone| !zone.get_history().is_empty())
+        .collect::<Vec<_>>();
+    if histories.is_empty() {
+        return Ok(None);
+    }
+
+    let points = histories.iter().flat_map(|zone| zone.get_history());
+
+    let first = points
+        .clone()
+        .map(|(time, _)| time.as_secs_f64())
+        .fold(f64::INFINITY, f64::min);
+    let last = points
+        .clone()
+        .map(|(time, _)| time.as_secs_f64())
+        .fold(f64::NEG_INFINITY, f64::max);
+    let low = points
+        .clone()
+        .map(|(_, price)| *price)
+        .fold(f64::INFINITY, f64::min);
+    let high = points
+        .map(|(_, price)| *price)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    // the first price may predate the window
+    let start = window.start().map(|t| t.as_secs_f64()).unwrap_or(first);
+    let end = window.end().map(|t| t.as_secs_f64()).unwrap_or(last);
+    let end = if end > start { end } else { start + 1.0 };
+
+    let padding = (high - low).max(high * 0.1).max(1e-6) * 0.1;
+    let low = (low - padding).max(0.0);
+    let high = high + padding;
+
+    let x = |time: &DateTime| {
+        let time = time.as_secs_f64().clamp(start, end);
+        MARGIN + (time - start) / (end - start) * (WIDTH - 2.0 * MARGIN)
+    };
+    let y = |price: f64| HEIGHT - MARGIN - (price - low) / (high - low) * (HEIGHT - 2.0 * MARGIN);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg class=\"chart\" width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"0 0 {WIDTH} {HEIGHT}\">"
+    )?;
+    writeln!(
+        svg,
+        "<text x=\"{MARGIN}\" y=\"15\" font-weight=\"bold\">{}</text>",
+        escape(instance)
+    )?;
+    writeln!(
+        svg,
+        "<rect x=\"{MARGIN}\" y=\"{MARGIN}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#ccc\"/>",
+        WIDTH - 2.0 * MARGIN,
+        HEIGHT - 2.0 * MARGIN
+    )?;
+    writeln!(
+        svg,
+        "<text x=\"2\" y=\"{}\">{high:.4}</text>\n<text x=\"2\" y=\"{}\">{low:.4}</text>",
+        MARGIN + 4.0,
+        HEIGHT - MARGIN
+    )?;
+    writeln!(
+        svg,
+        "<text x=\"{MARGIN}\" y=\"{}\">{}</text>\n<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        HEIGHT - MARGIN + 14.0,
+        format_date(start),
+        WIDTH - MARGIN,
+        HEIGHT - MARGIN + 14.0,
+        format_date(end)
+    )?;
+
+    let mut legend = MARGIN;
+    for (index, zone) in histories.iter().enumerate() {
+        let color = COLORS[index % COLORS.len()];
+
+        let mut line = Vec::new();
+        let mut previous: Option<f64> = None;
+        for (time, price) in zone.get_history() {
+            if let Some(previous) = previous {
+                line.push(format!("{:.1},{:.1}", x(time), y(previous)));
+            }
+            line.push(format!("{:.1},{:.1}", x(time), y(*price)));
+            previous = Some(*price);
+        }
+        if let Some(previous) = previous {
+            line.push(format!("{:.1},{:.1}", WIDTH - MARGIN, y(previous)));
+        }
+
+        writeln!(
+            svg,
+            "<polyline fill=\"none\" stroke=\"{color}\" points=\"{}\"><title>{}</title></polyline>",
+            line.join(" "),
+            escape(zone.get_name())
+        )?;
+
+        writeln!(
+            svg,
+            "<text x=\"{legend}\" y=\"{}\" fill=\"{color}\">{}</text>",
+            HEIGHT - 6.0,
+            escape(zone.get_name())
+        )?;
+        legend += 70.0;
+    }
+
+    svg.push_str("</svg>");
+
+    Ok(Some(svg))
+}
+
+fn format_time(time: &DateTime) -> String {
+    time.fmt(DateTimeFormat::DateTime)
+        .unwrap_or_else(|_| time.secs().to_string())
+}
+
+// e.g. `2024-06-01`
+fn format_date(secs: f64) -> String {
+    format_time(&DateTime::from_secs_f64(secs))
+        .chars()
+        .take(10)
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_render_report() -> Result<()> {
+        let fixture = Fixture::new()
+            .with_zones("us-east-2", &["us-east-2a", "us-east-2b"])
+            .with_zones("eu-north-1", &["eu-north-1a"])
+            .with_spot_prices(
+                "us-east-2",
+                "us-east-2a",
+                "c7g.16xlarge",
+                &[(0, 1.0), (3600, 2.0)],
+            )
+            .with_spot_prices("us-east-2", "us-east-2b", "c7g.16xlarge", &[(0, 3.0)])
+            .with_spot_prices("eu-north-1", "eu-north-1a", "c7g.16xlarge", &[(0, 0.5)])
+            .with_ondemand_price("c7g.16xlarge", "us-east-2", 4.0)
+            .with_ondemand_price("c7g.16xlarge", "eu-north-1", 3.0)
+            .with_zones("eu-west-1", &["eu-west-1a"])
+            .with_ondemand_price("c7g.16xlarge", "eu-west-1", 3.5);
+
+        let html = render_report(
+            &fixture,
+            &fixture,
+            &[InstanceType::from("c7g.16xlarge")],
+            &[],
+            &["us-east-2"],
+            &PriceQuery::default(),
+            &TimeWindow::default(),
+        )
+        .await?;
+
+        assert!(html.contains("<tr class=\"favorite\"><td>us-east-2</td>"));
+        assert!(html.contains("<tr><td>eu-north-1</td>"));
+        assert!(html.contains("<details open><summary>us-east-2</summary>"));
+        assert_eq!(html.matches("<polyline").count(), 3);
+        assert!(
+            html.contains("<tr><td>eu-west-1</td><td>c7g.16xlarge</td><td>no spot history</td>")
+        );
+
+        // no external resources
+        assert!(!html.contains("://"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::{Error, Result};
+use crate::pricing::{
+    LicenseModel, OperatingSystem, PreInstalledSoftware, PriceQuery, Tenancy, Term,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// a named watch list of instances and regions
+pub struct Profile {
+    #[serde(default)]
+    instances: Vec<String>,
+    #[serde(default)]
+    families: Vec<String>,
+    #[serde(default)]
+    regions: Vec<String>,
+    #[serde(default)]
+    favorite_regions: Vec<String>,
+    #[serde(default)]
+    operating_system: OperatingSystem,
+    #[serde(default)]
+    software: PreInstalledSoftware,
+    #[serde(default)]
+    license_model: LicenseModel,
+    #[serde(default)]
+    tenancy: Tenancy,
+    #[serde(default)]
+    term: Term,
+}
+
+impl Profile {
+    /// the instance types, e.g. `hpc7g.16xlarge`
+    pub fn instances(&self) -> &[String] {
+        &self.instances
+    }
+
+    /// the instance families, e.g. `c7g`
+    pub fn families(&self) -> &[String] {
+        &self.families
+    }
+
+    /// the analyzed regions, all regions if empty
+    pub fn regions(&self) -> &[String] {
+        &self.regions
+    }
+
+    /// the regions printed on top
+    pub fn favorite_regions(&self) -> &[String] {
+        &self.favorite_regions
+    }
+
+    /// the price lookup described by the profile
+    pub fn price_query(&self) -> PriceQuery {
+        PriceQuery::new(self.operating_system, self.tenancy, self.term)
+            .with_software(self.software)
+            .with_license_model(self.license_model)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// all profiles of a config file, each one a top-level table
+pub struct Profiles(HashMap<String, Profile>);
+
+impl Profiles {
+    /// load the profiles from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("cannot read {}: {e}", path.display())))?;
+
+        Self::parse(&content)
+    }
+
+    /// parse profiles from TOML
+    pub fn parse(content: &str) -> Result<Self> {
+        let profiles: HashMap<String, Profile> =
+            toml::from_str(content).map_err(|e| Error::Config(e.to_string()))?;
+
+        Ok(Profiles(profiles))
+    }
+
+    /// get a profile by name
+    pub fn get(&self, name: &str) -> Result<&Profile> {
+        self.0
+            .get(name)
+            .ok_or_else(|| Error::Config(format!("unknown profile: {name}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let profiles = Profiles::parse(
+            r#"
+            [hpc]
+            instances = ["hpc7g.16xlarge", "hpc7a.96xlarge"]
+            families = ["c7gn"]
+            favorite_regions = ["us-east-2"]
+
+            [memory]
+            instances = ["x2iedn.32xlarge"]
+            operating_system = "RHEL"
+            tenancy = "Dedicated"
+            term = "reserved"
+
+            [windows]
+            operating_system = "windows"
+            software = "SQL Std"
+            license_model = "byol"
+            "#,
+        )?;
+
+        let hpc = profiles.get("hpc")?;
+        assert_eq!(hpc.instances(), ["hpc7g.16xlarge", "hpc7a.96xlarge"]);
+        assert_eq!(hpc.families(), ["c7gn"]);
+        assert!(hpc.regions().is_empty());
+        assert_eq!(hpc.price_query(), PriceQuery::default());
+
+        let memory = profiles.get("memory")?;
+        assert_eq!(
+            memory.price_query(),
+            PriceQuery::new(OperatingSystem::Rhel, Tenancy::Dedicated, Term::Reserved)
+        );
+
+        let windows = profiles.get("windows")?;
+        assert_eq!(
+            windows.price_query(),
+            PriceQuery::default()
+                .with_operating_system(OperatingSystem::Windows)
+                .with_software(PreInstalledSoftware::SqlStandard)
+                .with_license_model(LicenseModel::Byol)
+        );
+
+        assert!(profiles.get("gpu").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        assert!(Profiles::parse("[hpc]\ninstance = []").is_err());
+    }
+}
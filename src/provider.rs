@@ -0,0 +1,524 @@
+use crate::error::{Error, Result};
+use crate::pricing::{
+    get_ondemand_filters, get_reservation_filters, get_reserved_offering_filters, ondemand_price,
+    reservation_price, reserved_offerings, OperatingSystem, PriceQuery, Tenancy, Term,
+};
+use crate::reserved::ReservedOffering;
+use crate::savings_plans::{savings_plan_rate, SavingsPlan, SavingsPlanFilter, SavingsPlanRate};
+use aws_sdk_ec2::primitives::DateTime;
+use aws_sdk_ec2::types::InstanceType;
+use aws_sdk_ec2::types::InstanceTypeInfo;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// a DescribeInstanceTypes filter, e.g. `bare-metal` = `false`
+pub struct Ec2Filter {
+    name: String,
+    values: Vec<String>,
+}
+
+impl Ec2Filter {
+    /// create a filter matching any of the values
+    pub fn new(name: &str, values: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    /// the name of the filter
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the accepted values
+    pub fn values(&self) -> &[String] {
+        &self.values
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// a GetProducts `TermMatch` filter, e.g. `tenancy` = `Shared`
+pub struct ProductFilter {
+    field: String,
+    value: String,
+}
+
+impl ProductFilter {
+    /// create a filter matching the value exactly
+    pub fn new(field: &str, value: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// the attribute name
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// the attribute value
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// a time range of spot prices, unbounded sides reach as far as the data
+pub struct TimeWindow {
+    start: Option<DateTime>,
+    end: Option<DateTime>,
+}
+
+impl TimeWindow {
+    /// create a window from `start` to `end`
+    pub fn new(start: Option<DateTime>, end: Option<DateTime>) -> Self {
+        Self { start, end }
+    }
+
+    /// the beginning of the window
+    pub fn start(&self) -> Option<DateTime> {
+        self.start
+    }
+
+    /// the end of the window
+    pub fn end(&self) -> Option<DateTime> {
+        self.end
+    }
+
+    /// whether a point in time lies in the window
+    pub fn contains(&self, time: &DateTime) -> bool {
+        let before = matches!(self.start, Some(start) if *time < start);
+        let after = matches!(self.end, Some(end) if end < *time);
+
+        !before && !after
+    }
+
+    /// the prices in the window, preceded by the price in effect at its start
+    pub fn clip(&self, prices: &[(DateTime, f64)]) -> Vec<(DateTime, f64)> {
+        let mut clipped = Vec::new();
+
+        if let Some(start) = self.start {
+            if let Some(previous) = prices.iter().rev().find(|(time, _)| *time < start) {
+                clipped.push(*previous);
+            }
+        }
+
+        clipped.extend(prices.iter().filter(|(time, _)| self.contains(time)));
+
+        clipped
+    }
+
+    /// the window of `length` before `end`, or before now without an end
+    pub fn last(length: WindowLength, end: Option<DateTime>) -> Self {
+        let end = end.unwrap_or_else(|| DateTime::from(SystemTime::now()));
+        let start = DateTime::from_secs(end.secs().saturating_sub(length.secs()));
+
+        Self::new(Some(start), Some(end))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// the length of a window of spot prices, e.g. `24h` or `7d`
+pub struct WindowLength {
+    hours: u32,
+}
+
+impl WindowLength {
+    /// a window of whole hours
+    pub fn hours(hours: u32) -> Self {
+        Self { hours }
+    }
+
+    /// a window of whole days
+    pub fn days(days: u32) -> Self {
+        Self {
+            hours: days.saturating_mul(24),
+        }
+    }
+
+    /// the length in seconds
+    pub fn secs(&self) -> i64 {
+        i64::from(self.hours) * 60 * 60
+    }
+}
+
+impl FromStr for WindowLength {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("invalid window: {s}, expected hours or days, e.g. 24h, 7d or 90d");
+
+        let (count, days) = match (s.strip_suffix('h'), s.strip_suffix('d')) {
+            (Some(count), _) => (count, false),
+            (_, Some(count)) => (count, true),
+            _ => return Err(invalid()),
+        };
+        let count = count.parse::<u32>().map_err(|_| invalid())?;
+        if count == 0 {
+            return Err(invalid());
+        }
+
+        if days {
+            Ok(WindowLength::days(count))
+        } else {
+            Ok(WindowLength::hours(count))
+        }
+    }
+}
+
+impl fmt::Display for WindowLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.hours % 24 == 0 {
+            f.pad(&format!("{}d", self.hours / 24))
+        } else {
+            f.pad(&format!("{}h", self.hours))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "ZoneRecord")]
+/// an availability zone, e.g. `us-east-2a` with the zone ID `use2-az1`
+///
+/// Zone names are mapped to locations per account, zone IDs are the same in all accounts.
+pub struct Zone {
+    name: String,
+    id: Option<String>,
+}
+
+impl Zone {
+    /// create a zone, the ID is unknown in old recordings
+    pub fn new(name: &str, id: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            id: id.map(|id| id.to_string()),
+        }
+    }
+
+    /// the name of the zone in this account, e.g. `us-east-2a`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the zone ID, e.g. `use2-az1`
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+// recordings without zone IDs hold only the names
+enum ZoneRecord {
+    Name(String),
+    Zone { name: String, id: Option<String> },
+}
+
+impl From<ZoneRecord> for Zone {
+    fn from(record: ZoneRecord) -> Self {
+        match record {
+            ZoneRecord::Name(name) => Self { name, id: None },
+            ZoneRecord::Zone { name, id } => Self { name, id },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// selects discovered regions by patterns with a trailing `*`, e.g. `eu-*`
+pub struct RegionFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl RegionFilter {
+    /// keep the regions matching any include pattern, all if there are none,
+    /// and drop the ones matching an exclude pattern
+    pub fn new(include: &[String], exclude: &[String]) -> Self {
+        Self {
+            include: include.to_vec(),
+            exclude: exclude.to_vec(),
+        }
+    }
+
+    /// whether a region passes the filter
+    pub fn matches(&self, region: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => region.starts_with(prefix),
+            None => region == pattern,
+        };
+
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+
+    /// the regions passing the filter
+    pub fn apply(&self, regions: &[String]) -> Vec<String> {
+        regions
+            .iter()
+            .filter(|region| self.matches(region))
+            .cloned()
+            .collect()
+    }
+}
+
+/// the catalog of instance types
+pub trait InstanceCatalog: Sync {
+    /// describe all instance types in a region matching the filters
+    fn describe_instance_types(
+        &self,
+        region: &str,
+        filters: &[Ec2Filter],
+    ) -> impl Future<Output = Result<Vec<InstanceTypeInfo>>> + Send;
+
+    /// describe one instance type in a region
+    fn describe_instance(
+        &self,
+        region: &str,
+        instance: &InstanceType,
+    ) -> impl Future<Output = Result<InstanceTypeInfo>> + Send {
+        async move {
+            let filters = [Ec2Filter::new("instance-type", &[instance.as_str()])];
+
+            self.describe_instance_types(region, &filters)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::NotOffered(format!("{} in {region}", instance.as_str())))
+        }
+    }
+
+    /// get all current types of instances
+    fn get_instance_types(
+        &self,
+        region: &str,
+    ) -> impl Future<Output = Result<Vec<InstanceTypeInfo>>> + Send {
+        async move { self.describe_instance_types(region, &[]).await }
+    }
+
+    /// get all instances with EFA support
+    fn get_instance_types_efa(
+        &self,
+        region: &str,
+    ) -> impl Future<Output = Result<Vec<InstanceTypeInfo>>> + Send {
+        async move {
+            let filters = [
+                Ec2Filter::new("network-info.efa-supported", &["true"]),
+                Ec2Filter::new("instance-storage-supported", &["false"]),
+                Ec2Filter::new("bare-metal", &["false"]),
+            ];
+
+            self.describe_instance_types(region, &filters).await
+        }
+    }
+
+    /// get all instance types of an instance family, e.g. `c7g`
+    fn get_instance_types_of_family(
+        &self,
+        region: &str,
+        family: &str,
+    ) -> impl Future<Output = Result<Vec<InstanceType>>> + Send {
+        async move {
+            let pattern = format!("{family}.*");
+            let filters = [Ec2Filter::new("instance-type", &[pattern.as_str()])];
+
+            Ok(self
+                .describe_instance_types(region, &filters)
+                .await?
+                .iter()
+                .filter_map(|info| info.instance_type().cloned())
+                .collect::<Vec<_>>())
+        }
+    }
+}
+
+/// the history of spot prices
+pub trait SpotPriceHistory: Sync {
+    /// the spot prices of an instance in an availability zone, oldest first
+    ///
+    /// `product_description` is the operating system, e.g. `Linux/UNIX` or `Windows`.
+    ///
+    /// The first price may predate the window: it is the price in effect at its start.
+    fn get_spot_price_history(
+        &self,
+        region: &str,
+        availability_zone: &str,
+        instance: &InstanceType,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> impl Future<Output = Result<Vec<(DateTime, f64)>>> + Send;
+}
+
+/// the catalog of regions and availability zones
+pub trait ZoneCatalog: Sync {
+    /// get the codes of all regions enabled for the account
+    fn get_regions(&self) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// get all availability zones of a region with their zone IDs
+    fn get_zones(&self, region: &str) -> impl Future<Output = Result<Vec<Zone>>> + Send;
+
+    /// fail with [`Error::UnknownRegion`] for regions not enabled for the account
+    fn check_regions(&self, regions: &[String]) -> impl Future<Output = Result<()>> + Send {
+        async move {
+            let known = self.get_regions().await?;
+
+            match regions.iter().find(|region| !known.contains(region)) {
+                Some(region) => Err(Error::UnknownRegion(region.to_string())),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+/// the price list of products
+pub trait ProductPrices: Sync {
+    /// all price list documents (JSON) matching the filters
+    fn get_products(
+        &self,
+        filters: &[ProductFilter],
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// the Compute and EC2 Instance Savings Plan rates matching the filter
+    fn get_savings_plan_rates(
+        &self,
+        filter: &SavingsPlanFilter,
+    ) -> impl Future<Output = Result<Vec<SavingsPlanRate>>> + Send;
+
+    /// the price for instance give a region and a query
+    fn get_query_price(
+        &self,
+        instance: &str,
+        region: &str,
+        query: &PriceQuery,
+    ) -> impl Future<Output = Result<f64>> + Send {
+        async move {
+            match query.term() {
+                Term::OnDemand => {
+                    let filters = get_ondemand_filters(instance, region, query)?;
+                    let products = self.get_products(&filters).await?;
+                    ondemand_price(instance, &products)
+                }
+                Term::Reserved => {
+                    let filters = get_reservation_filters(instance, region, query)?;
+                    let products = self.get_products(&filters).await?;
+                    reservation_price(instance, &products)
+                }
+            }
+        }
+    }
+
+    /// every reserved offering for instance given a region and a query
+    fn get_reserved_offerings(
+        &self,
+        instance: &str,
+        region: &str,
+        query: &PriceQuery,
+    ) -> impl Future<Output = Result<Vec<ReservedOffering>>> + Send {
+        async move {
+            let filters = get_reserved_offering_filters(instance, region, query)?;
+            let products = self.get_products(&filters).await?;
+            reserved_offerings(instance, &products)
+        }
+    }
+
+    /// the hourly rate for instance under a Savings Plan given a region and a query
+    fn get_savings_plan_rate(
+        &self,
+        instance: &str,
+        region: &str,
+        query: &PriceQuery,
+        plan: &SavingsPlan,
+    ) -> impl Future<Output = Result<f64>> + Send {
+        async move {
+            let filter = SavingsPlanFilter::new(instance, region, query)?;
+            let rates = self.get_savings_plan_rates(&filter).await?;
+            savings_plan_rate(instance, &rates, plan)
+        }
+    }
+
+    /// the OnDemand price for instance give a region
+    fn get_ondemand_price(
+        &self,
+        instance: &str,
+        region: &str,
+    ) -> impl Future<Output = Result<f64>> + Send {
+        async move {
+            self.get_query_price(instance, region, &PriceQuery::default())
+                .await
+        }
+    }
+
+    /// the effective hourly Reservation price for instance give a region
+    fn get_reservation_price(
+        &self,
+        instance: &str,
+        region: &str,
+    ) -> impl Future<Output = Result<f64>> + Send {
+        async move {
+            let query = PriceQuery::new(OperatingSystem::Linux, Tenancy::Dedicated, Term::Reserved);
+            self.get_query_price(instance, region, &query).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_region_filter() {
+        let regions = ["ca-west-1", "eu-north-1", "eu-south-2", "us-east-1"]
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(RegionFilter::default().apply(&regions), regions);
+
+        let filter = RegionFilter::new(
+            &["eu-*".to_string(), "ca-west-1".to_string()],
+            &["eu-south-*".to_string()],
+        );
+        assert_eq!(filter.apply(&regions), vec!["ca-west-1", "eu-north-1"]);
+
+        let filter = RegionFilter::new(&[], &["us-*".to_string()]);
+        assert!(!filter.matches("us-east-1"));
+        assert!(filter.matches("ap-southeast-5"));
+    }
+
+    #[test]
+    fn test_window_length() {
+        assert_eq!("24h".parse::<WindowLength>(), Ok(WindowLength::hours(24)));
+        assert_eq!("7d".parse::<WindowLength>(), Ok(WindowLength::days(7)));
+        assert_eq!(WindowLength::hours(24).to_string(), "1d");
+        assert_eq!(WindowLength::hours(36).to_string(), "36h");
+        assert!("0d".parse::<WindowLength>().is_err());
+        assert!("7w".parse::<WindowLength>().is_err());
+        assert!("d".parse::<WindowLength>().is_err());
+
+        let end = DateTime::from_secs(30 * 24 * 60 * 60);
+        let window = TimeWindow::last(WindowLength::days(7), Some(end));
+        assert_eq!(window.start(), Some(DateTime::from_secs(23 * 24 * 60 * 60)));
+        assert_eq!(window.end(), Some(end));
+    }
+
+    #[test]
+    fn test_zone_record() -> Result<()> {
+        let zone = Zone::new("us-east-2a", Some("use2-az1"));
+        let json = serde_json::to_string(&zone)?;
+        assert_eq!(serde_json::from_str::<Zone>(&json)?, zone);
+
+        // recorded before zone IDs
+        let zones: Vec<Zone> = serde_json::from_str(r#"["us-east-2a"]"#)?;
+        assert_eq!(zones, vec![Zone::new("us-east-2a", None)]);
+
+        Ok(())
+    }
+}
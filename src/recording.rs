@@ -0,0 +1,755 @@
+use crate::error::{Error, Result};
+use crate::pricing::OperatingSystem;
+use crate::provider::{
+    Ec2Filter, InstanceCatalog, ProductFilter, ProductPrices, SpotPriceHistory, TimeWindow, Zone,
+    ZoneCatalog,
+};
+use crate::savings_plans::{SavingsPlanFilter, SavingsPlanRate};
+use aws_sdk_ec2::primitives::DateTime;
+use aws_sdk_ec2::types::{
+    ArchitectureType, EbsInfo, EbsOptimizedInfo, EfaInfo, EphemeralNvmeSupport, GpuDeviceInfo,
+    GpuDeviceMemoryInfo, GpuInfo, InstanceStorageInfo, InstanceType, InstanceTypeInfo, MemoryInfo,
+    NetworkInfo, ProcessorInfo, VCpuInfo,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// the layout of a recording:
+//
+// recorded_at.json
+// regions.json
+// availability_zones/<region>.json
+// describe_instance_types/<region>/<filters>.json
+// spot_price_history/<region>/<zone>/<instance>[_product=<description>].json
+// products/<filters>.json
+// savings_plan_rates/<region>/<instance>_<filters>.json
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct GpuRecord {
+    manufacturer: Option<String>,
+    name: Option<String>,
+    count: Option<i32>,
+    memory_mib: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// the parts of an `InstanceTypeInfo` used by the analyzer
+struct InstanceTypeRecord {
+    instance_type: Option<String>,
+    bare_metal: Option<bool>,
+    architectures: Vec<String>,
+    default_vcpus: Option<i32>,
+    default_cores: Option<i32>,
+    memory_mib: Option<i64>,
+    network_performance: Option<String>,
+    efa_supported: Option<bool>,
+    maximum_efa_interfaces: Option<i32>,
+    ebs_maximum_bandwidth_mbps: Option<i32>,
+    gpus: Vec<GpuRecord>,
+    instance_storage_supported: Option<bool>,
+    instance_storage_gb: Option<i64>,
+    nvme_support: Option<String>,
+}
+
+impl From<&InstanceTypeInfo> for InstanceTypeRecord {
+    fn from(info: &InstanceTypeInfo) -> Self {
+        let network = info.network_info();
+        Self {
+            instance_type: info.instance_type().map(|i| i.as_str().to_string()),
+            bare_metal: info.bare_metal(),
+            architectures: info
+                .processor_info()
+                .map(|p| {
+                    p.supported_architectures()
+                        .iter()
+                        .map(|a| a.as_str().to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            default_vcpus: info.v_cpu_info().and_then(|v| v.default_v_cpus()),
+            default_cores: info.v_cpu_info().and_then(|v| v.default_cores()),
+            memory_mib: info.memory_info().and_then(|m| m.size_in_mib()),
+            network_performance: network
+                .and_then(|n| n.network_performance())
+                .map(|n| n.to_string()),
+            efa_supported: network.and_then(|n| n.efa_supported()),
+            maximum_efa_interfaces: network
+                .and_then(|n| n.efa_info())
+                .and_then(|e| e.maximum_efa_interfaces()),
+            ebs_maximum_bandwidth_mbps: info
+                .ebs_info()
+                .and_then(|e| e.ebs_optimized_info())
+                .and_then(|e| e.maximum_bandwidth_in_mbps()),
+            gpus: info
+                .gpu_info()
+                .map(|g| {
+                    g.gpus()
+                        .iter()
+                        .map(|gpu| GpuRecord {
+                            manufacturer: gpu.manufacturer().map(|m| m.to_string()),
+                            name: gpu.name().map(|n| n.to_string()),
+                            count: gpu.count(),
+                            memory_mib: gpu.memory_info().and_then(|m| m.size_in_mib()),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            instance_storage_supported: info.instance_storage_supported(),
+            instance_storage_gb: info
+                .instance_storage_info()
+                .and_then(|s| s.total_size_in_gb()),
+            nvme_support: info
+                .instance_storage_info()
+                .and_then(|s| s.nvme_support())
+                .map(|n| n.as_str().to_string()),
+        }
+    }
+}
+
+impl From<&InstanceTypeRecord> for InstanceTypeInfo {
+    fn from(record: &InstanceTypeRecord) -> Self {
+        let gpus = record
+            .gpus
+            .iter()
+            .map(|gpu| {
+                GpuDeviceInfo::builder()
+                    .set_manufacturer(gpu.manufacturer.clone())
+                    .set_name(gpu.name.clone())
+                    .set_count(gpu.count)
+                    .memory_info(
+                        GpuDeviceMemoryInfo::builder()
+                            .set_size_in_mib(gpu.memory_mib)
+                            .build(),
+                    )
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        let efa_info = record
+            .maximum_efa_interfaces
+            .map(|max| EfaInfo::builder().maximum_efa_interfaces(max).build());
+
+        let storage =
+            (record.instance_storage_gb.is_some() || record.nvme_support.is_some()).then(|| {
+                InstanceStorageInfo::builder()
+                    .set_total_size_in_gb(record.instance_storage_gb)
+                    .set_nvme_support(
+                        record
+                            .nvme_support
+                            .as_deref()
+                            .map(EphemeralNvmeSupport::from),
+                    )
+                    .build()
+            });
+
+        InstanceTypeInfo::builder()
+            .set_instance_type(record.instance_type.as_deref().map(InstanceType::from))
+            .set_bare_metal(record.bare_metal)
+            .processor_info(
+                ProcessorInfo::builder()
+                    .set_supported_architectures(Some(
+                        record
+                            .architectures
+                            .iter()
+                            .map(|a| ArchitectureType::from(a.as_str()))
+                            .collect(),
+                    ))
+                    .build(),
+            )
+            .v_cpu_info(
+                VCpuInfo::builder()
+                    .set_default_v_cpus(record.default_vcpus)
+                    .set_default_cores(record.default_cores)
+                    .build(),
+            )
+            .memory_info(
+                MemoryInfo::builder()
+                    .set_size_in_mib(record.memory_mib)
+                    .build(),
+            )
+            .network_info(
+                NetworkInfo::builder()
+                    .set_network_performance(record.network_performance.clone())
+                    .set_efa_supported(record.efa_supported)
+                    .set_efa_info(efa_info)
+                    .build(),
+            )
+            .ebs_info(
+                EbsInfo::builder()
+                    .ebs_optimized_info(
+                        EbsOptimizedInfo::builder()
+                            .set_maximum_bandwidth_in_mbps(record.ebs_maximum_bandwidth_mbps)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .set_gpu_info(
+                (!gpus.is_empty()).then(|| GpuInfo::builder().set_gpus(Some(gpus)).build()),
+            )
+            .set_instance_storage_supported(record.instance_storage_supported)
+            .set_instance_storage_info(storage)
+            .build()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InstanceTypesRecord {
+    filters: Vec<Ec2Filter>,
+    instance_types: Vec<InstanceTypeRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SpotPriceRecord {
+    // seconds since the epoch
+    timestamp: i64,
+    price: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProductsRecord {
+    filters: Vec<ProductFilter>,
+    price_list: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavingsPlanRatesRecord {
+    filter: SavingsPlanFilter,
+    rates: Vec<SavingsPlanRate>,
+}
+
+// file systems reject names of more than 255 bytes
+const MAX_FILE_NAME: usize = 200;
+
+// a file name for arbitrary API arguments
+fn get_file_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.=,".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    if name.len() <= MAX_FILE_NAME {
+        return format!("{name}.json");
+    }
+
+    // the start stays readable, the hash of the whole key keeps it unique
+    format!("{}-{:016x}.json", &name[..MAX_FILE_NAME - 17], fnv1a(key))
+}
+
+// FNV-1a, unlike the hasher of std stable across Rust versions
+fn fnv1a(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn recorded_at_path(dir: &Path) -> PathBuf {
+    dir.join("recorded_at.json")
+}
+
+fn regions_path(dir: &Path) -> PathBuf {
+    dir.join("regions.json")
+}
+
+fn zones_path(dir: &Path, region: &str) -> PathBuf {
+    dir.join("availability_zones").join(get_file_name(region))
+}
+
+fn instance_types_path(dir: &Path, region: &str, filters: &[Ec2Filter]) -> PathBuf {
+    let key = filters
+        .iter()
+        .map(|f| format!("{}={}", f.name(), f.values().join(",")))
+        .collect::<Vec<_>>()
+        .join("_");
+
+    dir.join("describe_instance_types")
+        .join(region)
+        .join(get_file_name(if key.is_empty() { "all" } else { &key }))
+}
+
+fn spot_prices_path(
+    dir: &Path,
+    region: &str,
+    zone: &str,
+    instance: &InstanceType,
+    product_description: &str,
+    window: &TimeWindow,
+) -> PathBuf {
+    let mut key = instance.as_str().to_string();
+    // Linux keeps the names of older recordings
+    if product_description != OperatingSystem::Linux.spot_product_description() {
+        key.push_str(&format!("_product={product_description}"));
+    }
+    if let Some(start) = window.start() {
+        key.push_str(&format!("_from={}", start.secs()));
+    }
+    if let Some(end) = window.end() {
+        key.push_str(&format!("_to={}", end.secs()));
+    }
+
+    dir.join("spot_price_history")
+        .join(region)
+        .join(zone)
+        .join(get_file_name(&key))
+}
+
+// a file for the price list documents matching the filters
+pub(crate) fn products_path(dir: &Path, filters: &[ProductFilter]) -> PathBuf {
+    let key = filters
+        .iter()
+        .map(|f| format!("{}={}", f.field(), f.value()))
+        .collect::<Vec<_>>()
+        .join("_");
+
+    dir.join("products").join(get_file_name(&key))
+}
+
+// a file for the Savings Plan rates matching the filter
+pub(crate) fn savings_plan_rates_path(dir: &Path, filter: &SavingsPlanFilter) -> PathBuf {
+    let key = format!(
+        "{}_{}_{}",
+        filter.instance(),
+        filter.product_description(),
+        filter.tenancy()
+    );
+
+    dir.join("savings_plan_rates")
+        .join(filter.region())
+        .join(get_file_name(&key))
+}
+
+fn write<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(value)?)?;
+
+    Ok(())
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| Error::Replay(format!("not recorded: {}: {e}", path.display())))?;
+
+    Ok(serde_json::from_str(&content)?)
+}
+
+#[derive(Debug)]
+/// a data source that records every response of another one into a directory
+pub struct Recorder<T> {
+    inner: T,
+    dir: PathBuf,
+}
+
+impl<T> Recorder<T> {
+    /// record the responses of `inner` into `dir`
+    pub fn new(inner: T, dir: &Path) -> Self {
+        Self {
+            inner,
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// record the time windows given by their length end at, the time of the recording
+    pub fn record_time(&self, now: DateTime) -> Result<()> {
+        write(&recorded_at_path(&self.dir), &now.secs())
+    }
+}
+
+impl<T: InstanceCatalog> InstanceCatalog for Recorder<T> {
+    async fn describe_instance_types(
+        &self,
+        region: &str,
+        filters: &[Ec2Filter],
+    ) -> Result<Vec<InstanceTypeInfo>> {
+        let instances = self.inner.describe_instance_types(region, filters).await?;
+
+        let record = InstanceTypesRecord {
+            filters: filters.to_vec(),
+            instance_types: instances.iter().map(InstanceTypeRecord::from).collect(),
+        };
+        write(&instance_types_path(&self.dir, region, filters), &record)?;
+
+        Ok(instances)
+    }
+}
+
+impl<T: SpotPriceHistory> SpotPriceHistory for Recorder<T> {
+    async fn get_spot_price_history(
+        &self,
+        region: &str,
+        availability_zone: &str,
+        instance: &InstanceType,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> Result<Vec<(DateTime, f64)>> {
+        let prices = self
+            .inner
+            .get_spot_price_history(
+                region,
+                availability_zone,
+                instance,
+                product_description,
+                window,
+            )
+            .await?;
+
+        let record = prices
+            .iter()
+            .map(|(time, price)| SpotPriceRecord {
+                timestamp: time.secs(),
+                price: *price,
+            })
+            .collect::<Vec<_>>();
+        write(
+            &spot_prices_path(
+                &self.dir,
+                region,
+                availability_zone,
+                instance,
+                product_description,
+                window,
+            ),
+            &record,
+        )?;
+
+        Ok(prices)
+    }
+}
+
+impl<T: ZoneCatalog> ZoneCatalog for Recorder<T> {
+    async fn get_regions(&self) -> Result<Vec<String>> {
+        let regions = self.inner.get_regions().await?;
+        write(&regions_path(&self.dir), &regions)?;
+
+        Ok(regions)
+    }
+
+    async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+        let zones = self.inner.get_zones(region).await?;
+        write(&zones_path(&self.dir, region), &zones)?;
+
+        Ok(zones)
+    }
+}
+
+impl<T: ProductPrices> ProductPrices for Recorder<T> {
+    async fn get_products(&self, filters: &[ProductFilter]) -> Result<Vec<String>> {
+        let products = self.inner.get_products(filters).await?;
+
+        let record = ProductsRecord {
+            filters: filters.to_vec(),
+            price_list: products.clone(),
+        };
+        write(&products_path(&self.dir, filters), &record)?;
+
+        Ok(products)
+    }
+
+    async fn get_savings_plan_rates(
+        &self,
+        filter: &SavingsPlanFilter,
+    ) -> Result<Vec<SavingsPlanRate>> {
+        let rates = self.inner.get_savings_plan_rates(filter).await?;
+
+        let record = SavingsPlanRatesRecord {
+            filter: filter.clone(),
+            rates: rates.clone(),
+        };
+        write(&savings_plan_rates_path(&self.dir, filter), &record)?;
+
+        Ok(rates)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// a data source serving the responses of a recording
+pub struct Replay {
+    dir: PathBuf,
+}
+
+impl Replay {
+    /// serve the responses recorded into `dir`
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// the time of the recording, none for recordings made before it was recorded
+    pub fn recorded_time(&self) -> Result<Option<DateTime>> {
+        let path = recorded_at_path(&self.dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(DateTime::from_secs(read(&path)?)))
+    }
+}
+
+impl InstanceCatalog for Replay {
+    async fn describe_instance_types(
+        &self,
+        region: &str,
+        filters: &[Ec2Filter],
+    ) -> Result<Vec<InstanceTypeInfo>> {
+        let path = instance_types_path(&self.dir, region, filters);
+        let record: InstanceTypesRecord = read(&path)?;
+
+        if record.filters != filters {
+            return Err(Error::Replay(format!(
+                "recording mismatch: {}",
+                path.display()
+            )));
+        }
+
+        Ok(record
+            .instance_types
+            .iter()
+            .map(InstanceTypeInfo::from)
+            .collect())
+    }
+}
+
+impl SpotPriceHistory for Replay {
+    async fn get_spot_price_history(
+        &self,
+        region: &str,
+        availability_zone: &str,
+        instance: &InstanceType,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> Result<Vec<(DateTime, f64)>> {
+        let path = spot_prices_path(
+            &self.dir,
+            region,
+            availability_zone,
+            instance,
+            product_description,
+            window,
+        );
+        let record: Vec<SpotPriceRecord> = read(&path)?;
+
+        Ok(record
+            .iter()
+            .map(|p| (DateTime::from_secs(p.timestamp), p.price))
+            .collect())
+    }
+}
+
+impl ZoneCatalog for Replay {
+    async fn get_regions(&self) -> Result<Vec<String>> {
+        read(&regions_path(&self.dir))
+    }
+
+    async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+        read(&zones_path(&self.dir, region))
+    }
+
+    // recordings made before regions were checked have no regions to check against
+    async fn check_regions(&self, regions: &[String]) -> Result<()> {
+        if !regions_path(&self.dir).exists() {
+            return Ok(());
+        }
+
+        let known = self.get_regions().await?;
+        match regions.iter().find(|region| !known.contains(region)) {
+            Some(region) => Err(Error::UnknownRegion(region.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ProductPrices for Replay {
+    async fn get_products(&self, filters: &[ProductFilter]) -> Result<Vec<String>> {
+        let path = products_path(&self.dir, filters);
+        let record: ProductsRecord = read(&path)?;
+
+        if record.filters != filters {
+            return Err(Error::Replay(format!(
+                "recording mismatch: {}",
+                path.display()
+            )));
+        }
+
+        Ok(record.price_list)
+    }
+
+    async fn get_savings_plan_rates(
+        &self,
+        filter: &SavingsPlanFilter,
+    ) -> Result<Vec<SavingsPlanRate>> {
+        let path = savings_plan_rates_path(&self.dir, filter);
+        let record: SavingsPlanRatesRecord = read(&path)?;
+
+        if record.filter != *filter {
+            return Err(Error::Replay(format!(
+                "recording mismatch: {}",
+                path.display()
+            )));
+        }
+
+        Ok(record.rates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            get_file_name("c7g.16xlarge_from=0"),
+            "c7g.16xlarge_from=0.json"
+        );
+
+        let long = |value: &str| {
+            let filters = (0..20)
+                .map(|index| ProductFilter::new(&format!("attribute{index}"), value))
+                .collect::<Vec<_>>();
+            products_path(Path::new("/"), &filters)
+        };
+        let name = |path: &PathBuf| path.file_name().unwrap().len();
+        let (a, b) = (long("Linux"), long("Linux/UNIX"));
+        assert!(name(&a) <= 255 && name(&b) <= 255);
+        assert_ne!(a, b);
+        assert_eq!(a, long("Linux"));
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() -> Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("aws_ec2_analyzer_record_{}", std::process::id()));
+
+        let info = InstanceTypeInfo::builder()
+            .instance_type(InstanceType::from("hpc7g.16xlarge"))
+            .bare_metal(false)
+            .processor_info(
+                ProcessorInfo::builder()
+                    .supported_architectures(ArchitectureType::Arm64)
+                    .build(),
+            )
+            .v_cpu_info(VCpuInfo::builder().default_cores(64).build())
+            .memory_info(MemoryInfo::builder().size_in_mib(131072).build())
+            .build();
+
+        let fixture = Fixture::new()
+            .with_zones("us-east-1", &["us-east-1a"])
+            .with_instance_type("us-east-1", info)
+            .with_spot_prices("us-east-1", "us-east-1a", "hpc7g.16xlarge", &[(60, 1.5)])
+            .with_ondemand_price("hpc7g.16xlarge", "us-east-1", 1.6832);
+
+        // nothing to check against without recorded regions
+        let unknown = ["eu-west-1".to_string()];
+        Replay::new(&dir).check_regions(&unknown).await?;
+
+        let recorder = Recorder::new(fixture.clone(), &dir);
+        let instance = InstanceType::from("hpc7g.16xlarge");
+
+        let regions = recorder.get_regions().await?;
+        let zones = recorder.get_zones("us-east-1").await?;
+        let described = recorder.describe_instance("us-east-1", &instance).await?;
+        let spot = recorder
+            .get_spot_price_history(
+                "us-east-1",
+                "us-east-1a",
+                &instance,
+                "Linux/UNIX",
+                &TimeWindow::default(),
+            )
+            .await?;
+        let price = recorder
+            .get_ondemand_price("hpc7g.16xlarge", "us-east-1")
+            .await?;
+
+        let replay = Replay::new(&dir);
+
+        assert_eq!(replay.get_regions().await?, regions);
+        replay.check_regions(&regions).await?;
+        assert!(matches!(
+            replay.check_regions(&unknown).await,
+            Err(Error::UnknownRegion(_))
+        ));
+        assert_eq!(replay.get_zones("us-east-1").await?, zones);
+        assert_eq!(
+            InstanceTypeRecord::from(&replay.describe_instance("us-east-1", &instance).await?),
+            InstanceTypeRecord::from(&described)
+        );
+        assert_eq!(
+            replay
+                .get_spot_price_history(
+                    "us-east-1",
+                    "us-east-1a",
+                    &instance,
+                    "Linux/UNIX",
+                    &TimeWindow::default()
+                )
+                .await?,
+            spot
+        );
+        assert_eq!(
+            replay
+                .get_ondemand_price("hpc7g.16xlarge", "us-east-1")
+                .await?,
+            price
+        );
+
+        assert!(replay.get_zones("eu-west-1").await.is_err());
+
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nvme_support() -> Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("aws_ec2_analyzer_nvme_{}", std::process::id()));
+
+        // the disks of a d3 are HDDs
+        let info = InstanceTypeInfo::builder()
+            .instance_type(InstanceType::from("d3.xlarge"))
+            .instance_storage_supported(true)
+            .instance_storage_info(
+                InstanceStorageInfo::builder()
+                    .total_size_in_gb(5940)
+                    .nvme_support(EphemeralNvmeSupport::Unsupported)
+                    .build(),
+            )
+            .build();
+        let fixture = Fixture::new().with_instance_type("us-east-1", info.clone());
+        let instance = InstanceType::from("d3.xlarge");
+
+        Recorder::new(fixture, &dir)
+            .describe_instance("us-east-1", &instance)
+            .await?;
+        let replayed = Replay::new(&dir)
+            .describe_instance("us-east-1", &instance)
+            .await?;
+
+        assert_eq!(
+            InstanceTypeRecord::from(&replayed),
+            InstanceTypeRecord::from(&info)
+        );
+        assert_eq!(
+            replayed
+                .instance_storage_info()
+                .and_then(|s| s.nvme_support()),
+            Some(&EphemeralNvmeSupport::Unsupported)
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::{Error, Result};
+use crate::price_list::OfferTerm;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// the length of a reservation
+pub enum LeaseLength {
+    /// one year
+    #[serde(rename = "1yr")]
+    OneYear,
+    /// three years
+    #[serde(rename = "3yr")]
+    ThreeYears,
+}
+
+impl LeaseLength {
+    /// the hours of the lease, AWS bills 365 days a year
+    pub fn hours(self) -> f64 {
+        match self {
+            LeaseLength::OneYear => 365.0 * 24.0,
+            LeaseLength::ThreeYears => 3.0 * 365.0 * 24.0,
+        }
+    }
+}
+
+impl FromStr for LeaseLength {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "1yr" => Ok(LeaseLength::OneYear),
+            "3yr" => Ok(LeaseLength::ThreeYears),
+            _ => Err(Error::MalformedPriceList(format!(
+                "lease contract length {s}"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for LeaseLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeaseLength::OneYear => f.pad("1yr"),
+            LeaseLength::ThreeYears => f.pad("3yr"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// whether a reservation can be exchanged for other instance types
+pub enum OfferingClass {
+    /// bound to the instance type
+    Standard,
+    /// exchangeable, at a higher price
+    Convertible,
+}
+
+impl FromStr for OfferingClass {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "standard" => Ok(OfferingClass::Standard),
+            "convertible" => Ok(OfferingClass::Convertible),
+            _ => Err(Error::MalformedPriceList(format!("offering class {s}"))),
+        }
+    }
+}
+
+impl fmt::Display for OfferingClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OfferingClass::Standard => f.pad("standard"),
+            OfferingClass::Convertible => f.pad("convertible"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// how a reservation is paid
+pub enum PurchaseOption {
+    /// only an hourly fee
+    #[serde(rename = "No Upfront")]
+    NoUpfront,
+    /// an upfront fee and a lower hourly fee
+    #[serde(rename = "Partial Upfront")]
+    PartialUpfront,
+    /// only an upfront fee
+    #[serde(rename = "All Upfront")]
+    AllUpfront,
+}
+
+impl FromStr for PurchaseOption {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "No Upfront" => Ok(PurchaseOption::NoUpfront),
+            "Partial Upfront" => Ok(PurchaseOption::PartialUpfront),
+            "All Upfront" => Ok(PurchaseOption::AllUpfront),
+            _ => Err(Error::MalformedPriceList(format!("purchase option {s}"))),
+        }
+    }
+}
+
+impl fmt::Display for PurchaseOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PurchaseOption::NoUpfront => f.pad("No Upfront"),
+            PurchaseOption::PartialUpfront => f.pad("Partial Upfront"),
+            PurchaseOption::AllUpfront => f.pad("All Upfront"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// a reserved offering of an instance type in a region, prices in USD
+pub struct ReservedOffering {
+    lease_contract_length: LeaseLength,
+    offering_class: OfferingClass,
+    purchase_option: PurchaseOption,
+    upfront_fee: f64,
+    hourly_fee: f64,
+}
+
+impl ReservedOffering {
+    /// create an offering
+    pub fn new(
+        lease_contract_length: LeaseLength,
+        offering_class: OfferingClass,
+        purchase_option: PurchaseOption,
+        upfront_fee: f64,
+        hourly_fee: f64,
+    ) -> Self {
+        Self {
+            lease_contract_length,
+            offering_class,
+            purchase_option,
+            upfront_fee,
+            hourly_fee,
+        }
+    }
+
+    /// the offering of a reserved term of the price list
+    pub fn from_term(term: &OfferTerm) -> Result<Self> {
+        let attribute = |name: &str| {
+            term.term_attribute(name).ok_or_else(|| {
+                Error::MalformedPriceList(format!("no {name} in term {}", term.offer_term_code()))
+            })
+        };
+
+        Ok(Self::new(
+            attribute("LeaseContractLength")?.parse()?,
+            attribute("OfferingClass")?.parse()?,
+            attribute("PurchaseOption")?.parse()?,
+            term.upfront_fee()?,
+            term.hourly_price()?,
+        ))
+    }
+
+    /// the length of the reservation
+    pub fn lease_contract_length(&self) -> LeaseLength {
+        self.lease_contract_length
+    }
+
+    /// the class of the reservation
+    pub fn offering_class(&self) -> OfferingClass {
+        self.offering_class
+    }
+
+    /// how the reservation is paid
+    pub fn purchase_option(&self) -> PurchaseOption {
+        self.purchase_option
+    }
+
+    /// the fee paid once at the start of the lease
+    pub fn upfront_fee(&self) -> f64 {
+        self.upfront_fee
+    }
+
+    /// the fee paid per hour of the lease
+    pub fn hourly_fee(&self) -> f64 {
+        self.hourly_fee
+    }
+
+    /// the hourly fee plus the upfront fee spread over the lease
+    pub fn effective_hourly_rate(&self) -> f64 {
+        self.hourly_fee + self.upfront_fee / self.lease_contract_length.hours()
+    }
+
+    /// the fees divided by the units of the instance, e.g. 64 vCPUs
+    pub fn per(self, amount: f64) -> Self {
+        Self {
+            upfront_fee: self.upfront_fee / amount,
+            hourly_fee: self.hourly_fee / amount,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::error::{Error, Result};
+use crate::pricing::PriceQuery;
+use crate::reserved::{LeaseLength, PurchaseOption};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// the kind of a Savings Plan
+pub enum SavingsPlanType {
+    /// any instance family, region and operating system
+    Compute,
+    /// one instance family in one region, at a lower rate
+    #[serde(rename = "EC2Instance")]
+    Ec2Instance,
+}
+
+impl fmt::Display for SavingsPlanType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SavingsPlanType::Compute => f.pad("compute"),
+            SavingsPlanType::Ec2Instance => f.pad("ec2-instance"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// a Savings Plan offering: kind, term and payment option
+pub struct SavingsPlan {
+    plan_type: SavingsPlanType,
+    lease_length: LeaseLength,
+    payment_option: PurchaseOption,
+}
+
+impl SavingsPlan {
+    /// create a Savings Plan offering
+    pub fn new(
+        plan_type: SavingsPlanType,
+        lease_length: LeaseLength,
+        payment_option: PurchaseOption,
+    ) -> Self {
+        Self {
+            plan_type,
+            lease_length,
+            payment_option,
+        }
+    }
+
+    /// the kind of the plan
+    pub fn plan_type(&self) -> SavingsPlanType {
+        self.plan_type
+    }
+
+    /// the term of the plan
+    pub fn lease_length(&self) -> LeaseLength {
+        self.lease_length
+    }
+
+    /// how the commitment is paid
+    pub fn payment_option(&self) -> PurchaseOption {
+        self.payment_option
+    }
+}
+
+impl FromStr for SavingsPlan {
+    type Err = String;
+
+    /// parse a plan like `compute-1yr-no-upfront` or `ec2-instance-3yr-all-upfront`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "unknown savings plan: {s}, expected <compute|ec2-instance>-<1yr|3yr>-<no|partial|all>-upfront"
+            )
+        };
+
+        let (plan_type, rest) = if let Some(rest) = s.strip_prefix("compute-") {
+            (SavingsPlanType::Compute, rest)
+        } else if let Some(rest) = s.strip_prefix("ec2-instance-") {
+            (SavingsPlanType::Ec2Instance, rest)
+        } else {
+            return Err(error());
+        };
+
+        let (lease_length, payment_option) = rest.split_once('-').ok_or_else(error)?;
+        let lease_length = lease_length.parse::<LeaseLength>().map_err(|_| error())?;
+        let payment_option = match payment_option {
+            "no-upfront" => PurchaseOption::NoUpfront,
+            "partial-upfront" => PurchaseOption::PartialUpfront,
+            "all-upfront" => PurchaseOption::AllUpfront,
+            _ => return Err(error()),
+        };
+
+        Ok(Self::new(plan_type, lease_length, payment_option))
+    }
+}
+
+impl fmt::Display for SavingsPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let payment_option = match self.payment_option {
+            PurchaseOption::NoUpfront => "no-upfront",
+            PurchaseOption::PartialUpfront => "partial-upfront",
+            PurchaseOption::AllUpfront => "all-upfront",
+        };
+
+        f.pad(&format!(
+            "{}-{}-{payment_option}",
+            self.plan_type, self.lease_length
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// the hourly rate of an instance type under a Savings Plan, in USD
+pub struct SavingsPlanRate {
+    plan: SavingsPlan,
+    rate: f64,
+}
+
+impl SavingsPlanRate {
+    /// create a rate
+    pub fn new(plan: SavingsPlan, rate: f64) -> Self {
+        Self { plan, rate }
+    }
+
+    /// the plan of the rate
+    pub fn plan(&self) -> SavingsPlan {
+        self.plan
+    }
+
+    /// the hourly rate, the upfront payments are part of the commitment
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// the Savings Plan rates of an instance type in a region
+pub struct SavingsPlanFilter {
+    instance: String,
+    region: String,
+    product_description: String,
+    tenancy: String,
+    // missing in recordings made before
+    #[serde(default)]
+    operation: String,
+}
+
+impl SavingsPlanFilter {
+    /// the rates for the image and tenancy of the query
+    ///
+    /// Fails with [`Error::Unsupported`] for images AWS does not offer.
+    pub fn new(instance: &str, region: &str, query: &PriceQuery) -> Result<Self> {
+        // Savings Plans name the operating systems after the spot product descriptions
+        Ok(Self {
+            instance: instance.to_string(),
+            region: region.to_string(),
+            product_description: query.product_description(),
+            tenancy: query.tenancy().to_string().to_lowercase(),
+            operation: query.operation()?.to_string(),
+        })
+    }
+
+    /// the instance type, e.g. `c7g.16xlarge`
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    /// the region code, e.g. `us-east-2`
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// the operating system, e.g. `Linux/UNIX`
+    pub fn product_description(&self) -> &str {
+        &self.product_description
+    }
+
+    /// the tenancy, e.g. `shared`
+    pub fn tenancy(&self) -> &str {
+        &self.tenancy
+    }
+
+    /// the `operation` of the image, e.g. `RunInstances:0002`
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    /// whether a usage type is the one of running the instance with the tenancy
+    ///
+    /// E.g. `USE2-BoxUsage:c7g.16xlarge`, but not `USE2-UnusedBox:c7g.16xlarge` of unused
+    /// capacity reservations. Usage types in us-east-1 have no region prefix.
+    pub fn matches_usage_type(&self, usage_type: &str) -> bool {
+        let usage = match self.tenancy.as_str() {
+            "dedicated" => "DedicatedUsage",
+            "host" => "HostUsage",
+            _ => "BoxUsage",
+        };
+
+        usage_type
+            .split_once(':')
+            .is_some_and(|(prefix, instance)| {
+                instance == self.instance
+                    && prefix
+                        .strip_suffix(usage)
+                        .is_some_and(|region| region.is_empty() || region.ends_with('-'))
+            })
+    }
+}
+
+/// the rate of the plan
+pub(crate) fn savings_plan_rate(
+    instance: &str,
+    rates: &[SavingsPlanRate],
+    plan: &SavingsPlan,
+) -> Result<f64> {
+    rates
+        .iter()
+        .find(|rate| rate.plan() == *plan)
+        .map(SavingsPlanRate::rate)
+        .ok_or_else(|| Error::NotOffered(format!("{instance} with a {plan} savings plan")))
+}
+
+/// the term of a Savings Plan with a duration in seconds
+pub(crate) fn lease_length(seconds: i64) -> Result<LeaseLength> {
+    const YEAR: i64 = 365 * 24 * 60 * 60;
+
+    match seconds {
+        YEAR => Ok(LeaseLength::OneYear),
+        seconds if seconds == 3 * YEAR => Ok(LeaseLength::ThreeYears),
+        seconds => Err(Error::MalformedResponse(format!(
+            "savings plan of {seconds} seconds"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse() {
+        let plan: SavingsPlan = "ec2-instance-3yr-partial-upfront".parse().unwrap();
+        assert_eq!(
+            plan,
+            SavingsPlan::new(
+                SavingsPlanType::Ec2Instance,
+                LeaseLength::ThreeYears,
+                PurchaseOption::PartialUpfront
+            )
+        );
+        assert_eq!(plan.to_string(), "ec2-instance-3yr-partial-upfront");
+
+        let plan: SavingsPlan = "compute-1yr-no-upfront".parse().unwrap();
+        assert_eq!(plan.to_string(), "compute-1yr-no-upfront");
+
+        assert!("compute-2yr-no-upfront".parse::<SavingsPlan>().is_err());
+        assert!("instance-1yr-no-upfront".parse::<SavingsPlan>().is_err());
+        assert!("compute-1yr".parse::<SavingsPlan>().is_err());
+    }
+
+    #[test]
+    fn test_usage_type() -> Result<()> {
+        let filter = SavingsPlanFilter::new("c7g.16xlarge", "us-east-2", &PriceQuery::default())?;
+        assert_eq!(filter.operation(), "RunInstances");
+
+        assert!(filter.matches_usage_type("USE2-BoxUsage:c7g.16xlarge"));
+        assert!(filter.matches_usage_type("BoxUsage:c7g.16xlarge"));
+        assert!(!filter.matches_usage_type("USE2-UnusedBox:c7g.16xlarge"));
+        assert!(!filter.matches_usage_type("USE2-DedicatedUsage:c7g.16xlarge"));
+        assert!(!filter.matches_usage_type("USE2-BoxUsage:c7g.8xlarge"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_savings_plan_rate() -> Result<()> {
+        let compute = "compute-1yr-no-upfront".parse::<SavingsPlan>().unwrap();
+        let instance = "ec2-instance-1yr-no-upfront"
+            .parse::<SavingsPlan>()
+            .unwrap();
+        let rates = [
+            SavingsPlanRate::new(compute, 1.7),
+            SavingsPlanRate::new(instance, 1.4),
+        ];
+
+        assert_eq!(savings_plan_rate("c7g.16xlarge", &rates, &instance)?, 1.4);
+
+        let three_years = "compute-3yr-no-upfront".parse::<SavingsPlan>().unwrap();
+        assert!(matches!(
+            savings_plan_rate("c7g.16xlarge", &rates, &three_years),
+            Err(Error::NotOffered(_))
+        ));
+
+        assert_eq!(lease_length(94_608_000)?, LeaseLength::ThreeYears);
+        assert!(lease_length(1).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+use crate::error::Result;
+use crate::provider::{
+    Ec2Filter, InstanceCatalog, SpotPriceHistory, TimeWindow, Zone, ZoneCatalog,
+};
+use aws_sdk_ec2::primitives::DateTime;
+use aws_sdk_ec2::types::InstanceType;
+use aws_sdk_ec2::types::InstanceTypeInfo;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use tokio::sync::Mutex;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS spot_prices (
+    region TEXT NOT NULL,
+    availability_zone TEXT NOT NULL,
+    instance_type TEXT NOT NULL,
+    product_description TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    price REAL NOT NULL,
+    PRIMARY KEY (region, availability_zone, instance_type, product_description, timestamp)
+)";
+
+#[derive(Debug)]
+/// a local database of spot prices, which keeps them beyond AWS's 90 days
+///
+/// Every lookup only fetches the prices newer than the latest stored one.
+pub struct SpotStore<S> {
+    inner: S,
+    connection: Mutex<Connection>,
+}
+
+impl<S> SpotStore<S> {
+    /// open or create the SQLite database at `path` in front of `inner`
+    pub fn open(inner: S, path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Self::with_connection(inner, Connection::open(path)?)
+    }
+
+    /// a store only living in memory
+    pub fn in_memory(inner: S) -> Result<Self> {
+        Self::with_connection(inner, Connection::open_in_memory()?)
+    }
+
+    fn with_connection(inner: S, connection: Connection) -> Result<Self> {
+        connection.execute(SCHEMA, [])?;
+
+        Ok(Self {
+            inner,
+            connection: Mutex::new(connection),
+        })
+    }
+
+    async fn get_latest(
+        &self,
+        region: &str,
+        zone: &str,
+        instance: &str,
+        product_description: &str,
+    ) -> Result<Option<i64>> {
+        let connection = self.connection.lock().await;
+
+        let latest = connection
+            .query_row(
+                "SELECT MAX(timestamp) FROM spot_prices
+                 WHERE region = ?1 AND availability_zone = ?2
+                 AND instance_type = ?3 AND product_description = ?4",
+                params![region, zone, instance, product_description],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .optional()?
+            .flatten();
+
+        Ok(latest)
+    }
+
+    async fn insert(
+        &self,
+        region: &str,
+        zone: &str,
+        instance: &str,
+        product_description: &str,
+        prices: &[(DateTime, f64)],
+    ) -> Result<()> {
+        let mut connection = self.connection.lock().await;
+
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR IGNORE INTO spot_prices
+                 (region, availability_zone, instance_type, product_description, timestamp, price)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+
+            for (time, price) in prices {
+                statement.execute(params![
+                    region,
+                    zone,
+                    instance,
+                    product_description,
+                    time.secs(),
+                    price
+                ])?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    async fn select(
+        &self,
+        region: &str,
+        zone: &str,
+        instance: &str,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> Result<Vec<(DateTime, f64)>> {
+        let connection = self.connection.lock().await;
+
+        let start = window.start().map(|t| t.secs()).unwrap_or(i64::MIN);
+        let end = window.end().map(|t| t.secs()).unwrap_or(i64::MAX);
+
+        // the price in effect at the start of the window
+        let mut prices = connection
+            .query_row(
+                "SELECT timestamp, price FROM spot_prices
+                 WHERE region = ?1 AND availability_zone = ?2
+                 AND instance_type = ?3 AND product_description = ?4
+                 AND timestamp < ?5
+                 ORDER BY timestamp DESC LIMIT 1",
+                params![region, zone, instance, product_description, start],
+                |row| Ok((DateTime::from_secs(row.get(0)?), row.get::<_, f64>(1)?)),
+            )
+            .optional()?
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let mut statement = connection.prepare(
+            "SELECT timestamp, price FROM spot_prices
+             WHERE region = ?1 AND availability_zone = ?2
+             AND instance_type = ?3 AND product_description = ?4
+             AND timestamp >= ?5 AND timestamp <= ?6
+             ORDER BY timestamp",
+        )?;
+
+        let rows = statement.query_map(
+            params![region, zone, instance, product_description, start, end],
+            |row| Ok((DateTime::from_secs(row.get(0)?), row.get::<_, f64>(1)?)),
+        )?;
+
+        for row in rows {
+            prices.push(row?);
+        }
+
+        Ok(prices)
+    }
+}
+
+impl<S: SpotPriceHistory> SpotPriceHistory for SpotStore<S> {
+    async fn get_spot_price_history(
+        &self,
+        region: &str,
+        availability_zone: &str,
+        instance: &InstanceType,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> Result<Vec<(DateTime, f64)>> {
+        let name = instance.as_str();
+
+        // only what is missing
+        let latest = self
+            .get_latest(region, availability_zone, name, product_description)
+            .await?;
+        let missing = TimeWindow::new(latest.map(DateTime::from_secs), None);
+
+        let prices = self
+            .inner
+            .get_spot_price_history(
+                region,
+                availability_zone,
+                instance,
+                product_description,
+                &missing,
+            )
+            .await?;
+        self.insert(
+            region,
+            availability_zone,
+            name,
+            product_description,
+            &prices,
+        )
+        .await?;
+
+        self.select(region, availability_zone, name, product_description, window)
+            .await
+    }
+}
+
+impl<S: InstanceCatalog> InstanceCatalog for SpotStore<S> {
+    async fn describe_instance_types(
+        &self,
+        region: &str,
+        filters: &[Ec2Filter],
+    ) -> Result<Vec<InstanceTypeInfo>> {
+        self.inner.describe_instance_types(region, filters).await
+    }
+}
+
+impl<S: ZoneCatalog> ZoneCatalog for SpotStore<S> {
+    async fn get_regions(&self) -> Result<Vec<String>> {
+        self.inner.get_regions().await
+    }
+
+    async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+        self.inner.get_zones(region).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixture::Fixture;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_accumulate() -> Result<()> {
+        let instance = InstanceType::from("c7g.16xlarge");
+        let all = TimeWindow::default();
+
+        let old = Fixture::new().with_spot_prices(
+            "us-east-2",
+            "us-east-2a",
+            "c7g.16xlarge",
+            &[(100, 1.0), (200, 2.0)],
+        );
+        let store = SpotStore::in_memory(old)?;
+        store
+            .get_spot_price_history("us-east-2", "us-east-2a", &instance, "Linux/UNIX", &all)
+            .await?;
+
+        // AWS forgot the oldest price
+        let connection = store.connection.into_inner().unwrap();
+        let new = Fixture::new().with_spot_prices(
+            "us-east-2",
+            "us-east-2a",
+            "c7g.16xlarge",
+            &[(200, 2.0), (300, 3.0)],
+        );
+        let store = SpotStore::with_connection(new, connection)?;
+
+        let prices = store
+            .get_spot_price_history("us-east-2", "us-east-2a", &instance, "Linux/UNIX", &all)
+            .await?;
+        assert_eq!(
+            prices,
+            vec![
+                (DateTime::from_secs(100), 1.0),
+                (DateTime::from_secs(200), 2.0),
+                (DateTime::from_secs(300), 3.0),
+            ]
+        );
+
+        let window = TimeWindow::new(Some(DateTime::from_secs(250)), None);
+        let prices = store
+            .get_spot_price_history("us-east-2", "us-east-2a", &instance, "Linux/UNIX", &window)
+            .await?;
+        assert_eq!(
+            prices,
+            vec![
+                (DateTime::from_secs(200), 2.0),
+                (DateTime::from_secs(300), 3.0),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+use aws_sdk_ec2::primitives::DateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// a statistic of the spot prices in a window
+pub enum Metric {
+    /// the lowest price
+    Min,
+    /// the time-weighted average price
+    #[default]
+    Avg,
+    /// the highest price
+    Max,
+    /// the latest price
+    Last,
+    /// the price not exceeded half of the time
+    P50,
+    /// the price not exceeded 90% of the time
+    P90,
+    /// the price not exceeded 99% of the time
+    P99,
+    /// the time-weighted standard deviation, how volatile the price is
+    Stddev,
+    /// how often the price changed
+    Changes,
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "min" => Ok(Metric::Min),
+            "avg" | "average" => Ok(Metric::Avg),
+            "max" => Ok(Metric::Max),
+            "last" => Ok(Metric::Last),
+            "p50" | "median" => Ok(Metric::P50),
+            "p90" => Ok(Metric::P90),
+            "p99" => Ok(Metric::P99),
+            "stddev" => Ok(Metric::Stddev),
+            "changes" => Ok(Metric::Changes),
+            _ => Err(format!(
+                "unknown metric: {s}, expected min, avg, max, last, p50, p90, p99, stddev or changes"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Metric::Min => f.pad("min"),
+            Metric::Avg => f.pad("avg"),
+            Metric::Max => f.pad("max"),
+            Metric::Last => f.pad("last"),
+            Metric::P50 => f.pad("p50"),
+            Metric::P90 => f.pad("p90"),
+            Metric::P99 => f.pad("p99"),
+            Metric::Stddev => f.pad("stddev"),
+            Metric::Changes => f.pad("changes"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// time-weighted statistics of spot prices, each price holding until the next change
+pub struct SpotStatistics {
+    // the prices with the seconds they were in effect, by time
+    samples: Vec<(f64, f64)>,
+    last: f64,
+    changes: u32,
+}
+
+impl SpotStatistics {
+    /// the statistics of a history sorted by time, whose last price holds until `end`
+    ///
+    /// Prices in effect before `start` only count from `start` on. None for an empty history.
+    pub fn new(
+        history: &[(DateTime, f64)],
+        start: Option<DateTime>,
+        end: DateTime,
+    ) -> Option<Self> {
+        let &(_, last) = history.last()?;
+        let start = start.map_or(f64::NEG_INFINITY, |start| start.as_secs_f64());
+        let end = end.as_secs_f64();
+
+        let untils = history
+            .iter()
+            .skip(1)
+            .map(|(time, _)| time.as_secs_f64())
+            .chain(std::iter::once(end));
+
+        let mut samples = history
+            .iter()
+            .zip(untils)
+            .map(|(&(time, price), until)| {
+                let from = time.as_secs_f64().max(start);
+                (price, (until.min(end) - from).max(0.0))
+            })
+            .collect::<Vec<_>>();
+
+        // a history without duration, e.g. a single price at the end, counts every price once
+        if samples.iter().all(|&(_, seconds)| seconds <= 0.0) {
+            samples.iter_mut().for_each(|sample| sample.1 = 1.0);
+        } else {
+            samples.retain(|&(_, seconds)| seconds > 0.0);
+        }
+
+        let changes = samples
+            .windows(2)
+            .filter(|pair| pair[0].0 != pair[1].0)
+            .count();
+
+        Some(Self {
+            samples,
+            last,
+            changes: u32::try_from(changes).unwrap_or(u32::MAX),
+        })
+    }
+
+    /// the statistics of several histories as one, e.g. all zones of an instance
+    ///
+    /// The last price is the highest last price. None without statistics.
+    pub fn merge(statistics: &[&SpotStatistics]) -> Option<Self> {
+        let last = statistics
+            .iter()
+            .map(|statistics| statistics.last)
+            .max_by(f64::total_cmp)?;
+
+        Some(Self {
+            samples: statistics
+                .iter()
+                .flat_map(|statistics| statistics.samples.iter().copied())
+                .collect(),
+            last,
+            changes: statistics
+                .iter()
+                .fold(0, |sum, statistics| sum.saturating_add(statistics.changes)),
+        })
+    }
+
+    /// all prices divided by the units of the instance
+    pub fn per(self, amount: f64) -> Self {
+        Self {
+            samples: self
+                .samples
+                .iter()
+                .map(|&(price, seconds)| (price / amount, seconds))
+                .collect(),
+            last: self.last / amount,
+            ..self
+        }
+    }
+
+    /// the value of a metric, changes as a number
+    pub fn get(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Min => self.min(),
+            Metric::Avg => self.avg(),
+            Metric::Max => self.max(),
+            Metric::Last => self.last(),
+            Metric::P50 => self.percentile(0.5),
+            Metric::P90 => self.percentile(0.9),
+            Metric::P99 => self.percentile(0.99),
+            Metric::Stddev => self.stddev(),
+            Metric::Changes => f64::from(self.changes),
+        }
+    }
+
+    /// the lowest price
+    pub fn min(&self) -> f64 {
+        self.prices().min_by(f64::total_cmp).unwrap_or_default()
+    }
+
+    /// the time-weighted average price
+    pub fn avg(&self) -> f64 {
+        let sum: f64 = self
+            .samples
+            .iter()
+            .map(|&(price, seconds)| price * seconds)
+            .sum();
+
+        sum / self.duration()
+    }
+
+    /// the highest price
+    pub fn max(&self) -> f64 {
+        self.prices().max_by(f64::total_cmp).unwrap_or_default()
+    }
+
+    /// the latest price
+    pub fn last(&self) -> f64 {
+        self.last
+    }
+
+    /// the lowest price not exceeded `share` of the time, e.g. 0.9 for p90
+    pub fn percentile(&self, share: f64) -> f64 {
+        let mut samples = self.samples.clone();
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let threshold = share * self.duration();
+        let mut seconds = 0.0;
+        for &(price, duration) in &samples {
+            seconds += duration;
+            if seconds >= threshold {
+                return price;
+            }
+        }
+
+        self.max()
+    }
+
+    /// the time-weighted standard deviation of the price
+    pub fn stddev(&self) -> f64 {
+        let avg = self.avg();
+        let sum: f64 = self
+            .samples
+            .iter()
+            .map(|&(price, seconds)| (price - avg).powi(2) * seconds)
+            .sum();
+
+        (sum / self.duration()).sqrt()
+    }
+
+    /// how often the price changed
+    pub fn changes(&self) -> u32 {
+        self.changes
+    }
+
+    fn prices(&self) -> impl Iterator<Item = f64> + '_ {
+        self.samples.iter().map(|&(price, _)| price)
+    }
+
+    fn duration(&self) -> f64 {
+        self.samples.iter().map(|&(_, seconds)| seconds).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn history(prices: &[(i64, f64)]) -> Vec<(DateTime, f64)> {
+        prices
+            .iter()
+            .map(|&(secs, price)| (DateTime::from_secs(secs), price))
+            .collect()
+    }
+
+    #[test]
+    fn test_time_weighted() {
+        // 1.0 for one hour, 3.0 for three hours
+        let history = history(&[(0, 1.0), (3600, 3.0)]);
+        let statistics = SpotStatistics::new(&history, None, DateTime::from_secs(14400)).unwrap();
+
+        assert_eq!(statistics.min(), 1.0);
+        assert_eq!(statistics.avg(), 2.5);
+        assert_eq!(statistics.max(), 3.0);
+        assert_eq!(statistics.last(), 3.0);
+        assert_eq!(statistics.get(Metric::P50), 3.0);
+        assert_eq!(statistics.percentile(0.25), 1.0);
+        assert_eq!(statistics.stddev(), 0.75_f64.sqrt());
+        assert_eq!(statistics.get(Metric::Changes), 1.0);
+    }
+
+    #[test]
+    fn test_window() {
+        // the price in effect at the start only counts from the start
+        let history = history(&[(0, 2.0), (7200, 1.0), (9000, 1.0)]);
+        let statistics = SpotStatistics::new(
+            &history,
+            Some(DateTime::from_secs(3600)),
+            DateTime::from_secs(10800),
+        )
+        .unwrap();
+
+        assert_eq!(statistics.avg(), 1.5);
+        assert_eq!(statistics.get(Metric::P50), 1.0);
+        assert_eq!(statistics.get(Metric::P99), 2.0);
+        assert_eq!(statistics.changes(), 1);
+
+        // a single price at the end of the window
+        let history = [(DateTime::from_secs(10800), 2.0)];
+        let statistics = SpotStatistics::new(&history, None, DateTime::from_secs(10800)).unwrap();
+        assert_eq!(statistics.avg(), 2.0);
+
+        assert_eq!(SpotStatistics::new(&[], None, DateTime::from_secs(0)), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let end = DateTime::from_secs(7200);
+        let a = SpotStatistics::new(&history(&[(0, 1.0), (3600, 2.0)]), None, end).unwrap();
+        let b = SpotStatistics::new(&history(&[(0, 3.0)]), None, end).unwrap();
+
+        let statistics = SpotStatistics::merge(&[&a, &b]).unwrap();
+        assert_eq!(statistics.avg(), 2.25);
+        assert_eq!(statistics.last(), 3.0);
+        assert_eq!(statistics.changes(), 1);
+
+        let statistics = statistics.per(2.0);
+        assert_eq!(statistics.max(), 1.5);
+        assert_eq!(statistics.changes(), 1);
+
+        assert_eq!(SpotStatistics::merge(&[]), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        for metric in [
+            Metric::Min,
+            Metric::Avg,
+            Metric::Max,
+            Metric::Last,
+            Metric::P50,
+            Metric::P90,
+            Metric::P99,
+            Metric::Stddev,
+            Metric::Changes,
+        ] {
+            assert_eq!(metric.to_string().parse::<Metric>(), Ok(metric));
+        }
+        assert!("p75".parse::<Metric>().is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use crate::error::Result;
+use crate::provider::{
+    Ec2Filter, InstanceCatalog, ProductFilter, ProductPrices, SpotPriceHistory, TimeWindow, Zone,
+    ZoneCatalog,
+};
+use crate::savings_plans::{SavingsPlanFilter, SavingsPlanRate};
+use aws_sdk_ec2::primitives::DateTime;
+use aws_sdk_ec2::types::InstanceType;
+use aws_sdk_ec2::types::InstanceTypeInfo;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+/// how fast a service is called and how throttled calls are retried
+pub struct RetryPolicy {
+    rate: f64,
+    burst: f64,
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    budget: u32,
+}
+
+impl RetryPolicy {
+    /// at most `rate` calls per second on average, with bursts of up to `burst` calls
+    pub fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            rate,
+            burst,
+            max_attempts: 6,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(20),
+            budget: 500,
+        }
+    }
+
+    /// the number of attempts of a single call, including the first one
+    pub fn with_max_attempts(self, max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..self
+        }
+    }
+
+    /// the delay before the first retry, doubled for every further one up to `max_delay`
+    pub fn with_backoff(self, base_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            base_delay,
+            max_delay,
+            ..self
+        }
+    }
+
+    /// the number of retries of all calls together
+    pub fn with_budget(self, budget: u32) -> Self {
+        Self { budget, ..self }
+    }
+
+    // exponential backoff with "equal jitter": half fixed, half random
+    fn delay(&self, retry: u32) -> Duration {
+        // doubled at most 31 times, the delay saturates instead of overflowing
+        let factor = 1_u32 << retry.min(31);
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+
+        delay.mul_f64(0.5 + fastrand::f64() / 2.0)
+    }
+}
+
+#[derive(Debug)]
+// a token bucket whose rate is halved on throttling and slowly recovers
+struct Bucket {
+    max_rate: f64,
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(rate: f64, burst: f64) -> Self {
+        let rate = rate.max(0.01);
+        let burst = burst.max(1.0);
+
+        Self {
+            max_rate: rate,
+            rate,
+            burst,
+            tokens: burst,
+            last: Instant::now(),
+        }
+    }
+
+    // take a token or tell how long to wait for one
+    fn take(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+
+        Some(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+    }
+
+    fn throttled(&mut self) {
+        self.rate = (self.rate / 2.0).max(self.max_rate / 64.0);
+        self.tokens = self.tokens.min(0.0);
+    }
+
+    fn succeeded(&mut self) {
+        self.rate = (self.rate + self.max_rate / 20.0).min(self.max_rate);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// a call which was retried or failed
+pub struct RetryRecord {
+    call: String,
+    retries: u32,
+    failed: bool,
+}
+
+impl RetryRecord {
+    /// the service, the operation and its arguments
+    pub fn call(&self) -> &str {
+        &self.call
+    }
+
+    /// the number of retries
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// whether the call ultimately failed
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+}
+
+#[derive(Debug, Default)]
+/// the calls of all services which were retried or failed
+pub struct RetryLog {
+    records: Mutex<Vec<RetryRecord>>,
+}
+
+impl RetryLog {
+    /// create an empty log to be shared by the services
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    fn push(&self, record: RetryRecord) {
+        self.records.lock().unwrap().push(record);
+    }
+
+    /// all retried or failed calls
+    pub fn records(&self) -> Vec<RetryRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// a human readable summary, empty if nothing was retried
+    pub fn summary(&self) -> String {
+        let records = self.records();
+        if records.is_empty() {
+            return String::new();
+        }
+
+        let retries: u32 = records.iter().map(|r| r.retries).sum();
+        let failed = records.iter().filter(|r| r.failed).count();
+        let mut summary = format!(
+            "{} calls retried {retries} times, {failed} failed\n",
+            records.len()
+        );
+
+        for record in &records {
+            let outcome = if record.failed { "failed" } else { "ok" };
+            summary.push_str(&format!(
+                "  {outcome:<6} {:>2} retries  {}\n",
+                record.retries, record.call
+            ));
+        }
+
+        summary
+    }
+}
+
+#[derive(Debug)]
+/// a provider which paces the calls to its inner provider and retries throttled ones
+pub struct Throttled<T> {
+    inner: T,
+    service: String,
+    policy: RetryPolicy,
+    bucket: Mutex<Bucket>,
+    budget: AtomicU32,
+    log: Arc<RetryLog>,
+}
+
+impl<T> Throttled<T> {
+    /// pace and retry the calls of `service`, e.g. `ec2`, recording retries in `log`
+    pub fn new(inner: T, service: &str, policy: RetryPolicy, log: &Arc<RetryLog>) -> Self {
+        Self {
+            inner,
+            service: service.to_string(),
+            bucket: Mutex::new(Bucket::new(policy.rate, policy.burst)),
+            budget: AtomicU32::new(policy.budget),
+            policy,
+            log: log.clone(),
+        }
+    }
+
+    async fn acquire(&self) {
+        loop {
+            let wait = self.bucket.lock().unwrap().take();
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => return,
+            }
+        }
+    }
+
+    // spend one retry of the budget, if any is left
+    fn spend_budget(&self) -> bool {
+        self.budget
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |budget| {
+                budget.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    async fn call<R, F, Fut>(&self, call: &str, f: F) -> Result<R>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        let mut retries = 0;
+
+        loop {
+            self.acquire().await;
+
+            match f().await {
+                Ok(result) => {
+                    self.bucket.lock().unwrap().succeeded();
+                    if retries > 0 {
+                        self.log.push(RetryRecord {
+                            call: format!("{} {call}", self.service),
+                            retries,
+                            failed: false,
+                        });
+                    }
+                    return Ok(result);
+                }
+                Err(err) => {
+                    let throttled = err.is_throttling();
+                    if throttled {
+                        self.bucket.lock().unwrap().throttled();
+                    }
+
+                    if !throttled || retries + 1 >= self.policy.max_attempts || !self.spend_budget()
+                    {
+                        if retries > 0 || throttled {
+                            self.log.push(RetryRecord {
+                                call: format!("{} {call}", self.service),
+                                retries,
+                                failed: true,
+                            });
+                        }
+                        return Err(err);
+                    }
+
+                    tokio::time::sleep(self.policy.delay(retries)).await;
+                    retries += 1;
+                }
+            }
+        }
+    }
+}
+
+impl<T: InstanceCatalog> InstanceCatalog for Throttled<T> {
+    async fn describe_instance_types(
+        &self,
+        region: &str,
+        filters: &[Ec2Filter],
+    ) -> Result<Vec<InstanceTypeInfo>> {
+        let call = format!("DescribeInstanceTypes {region}");
+        self.call(&call, move || {
+            self.inner.describe_instance_types(region, filters)
+        })
+        .await
+    }
+}
+
+impl<T: SpotPriceHistory> SpotPriceHistory for Throttled<T> {
+    async fn get_spot_price_history(
+        &self,
+        region: &str,
+        availability_zone: &str,
+        instance: &InstanceType,
+        product_description: &str,
+        window: &TimeWindow,
+    ) -> Result<Vec<(DateTime, f64)>> {
+        let call = format!(
+            "DescribeSpotPriceHistory {availability_zone} {} {product_description}",
+            instance.as_str()
+        );
+        self.call(&call, move || {
+            self.inner.get_spot_price_history(
+                region,
+                availability_zone,
+                instance,
+                product_description,
+                window,
+            )
+        })
+        .await
+    }
+}
+
+impl<T: ZoneCatalog> ZoneCatalog for Throttled<T> {
+    async fn get_regions(&self) -> Result<Vec<String>> {
+        self.call("DescribeRegions", move || self.inner.get_regions())
+            .await
+    }
+
+    async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+        let call = format!("DescribeAvailabilityZones {region}");
+        self.call(&call, move || self.inner.get_zones(region)).await
+    }
+}
+
+impl<T: ProductPrices> ProductPrices for Throttled<T> {
+    async fn get_products(&self, filters: &[ProductFilter]) -> Result<Vec<String>> {
+        let call = format!(
+            "GetProducts {}",
+            filters
+                .iter()
+                .map(|filter| format!("{}={}", filter.field(), filter.value()))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        self.call(&call, move || self.inner.get_products(filters))
+            .await
+    }
+
+    async fn get_savings_plan_rates(
+        &self,
+        filter: &SavingsPlanFilter,
+    ) -> Result<Vec<SavingsPlanRate>> {
+        let call = format!(
+            "DescribeSavingsPlansOfferingRates {} {}",
+            filter.instance(),
+            filter.region()
+        );
+        self.call(&call, move || self.inner.get_savings_plan_rates(filter))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::Error;
+    use pretty_assertions::assert_eq;
+
+    // fails with the given errors first, then succeeds
+    struct Flaky {
+        errors: Mutex<Vec<&'static str>>,
+    }
+
+    impl Flaky {
+        fn new(errors: &[&'static str]) -> Self {
+            Self {
+                errors: Mutex::new(errors.iter().rev().copied().collect()),
+            }
+        }
+    }
+
+    impl ZoneCatalog for Flaky {
+        async fn get_regions(&self) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_zones(&self, region: &str) -> Result<Vec<Zone>> {
+            match self.errors.lock().unwrap().pop() {
+                Some(code) => Err(Error::sdk(Some(code), "service error")),
+                None => Ok(vec![Zone::new(&format!("{region}a"), None)]),
+            }
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(1000.0, 100.0)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(2))
+    }
+
+    #[tokio::test]
+    async fn test_retry() -> Result<()> {
+        let log = RetryLog::new();
+        let flaky = Flaky::new(&["RequestLimitExceeded", "RequestLimitExceeded"]);
+        let throttled = Throttled::new(flaky, "ec2", policy(), &log);
+
+        assert_eq!(
+            throttled.get_zones("us-east-2").await?,
+            vec![Zone::new("us-east-2a", None)]
+        );
+        assert_eq!(
+            log.records(),
+            vec![RetryRecord {
+                call: "ec2 DescribeAvailabilityZones us-east-2".to_string(),
+                retries: 2,
+                failed: false,
+            }]
+        );
+
+        // other errors are not retried
+        let throttled = Throttled::new(
+            Flaky::new(&["UnauthorizedOperation"]),
+            "ec2",
+            policy(),
+            &log,
+        );
+        assert!(throttled.get_zones("us-east-2").await.is_err());
+        assert_eq!(log.records().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new(1.0, 1.0)
+            .with_backoff(Duration::from_millis(200), Duration::from_secs(20));
+
+        // half fixed, half random
+        let first = policy.delay(0);
+        assert!(Duration::from_millis(100) <= first && first <= Duration::from_millis(200));
+
+        for retry in [64, 1000, u32::MAX] {
+            let delay = policy.delay(retry);
+            assert!(Duration::from_secs(10) <= delay && delay <= Duration::from_secs(20));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_budget() -> Result<()> {
+        let log = RetryLog::new();
+        let flaky = Flaky::new(&["ThrottlingException"; 10]);
+        let throttled = Throttled::new(flaky, "pricing", policy().with_budget(3), &log);
+
+        assert!(throttled.get_zones("us-east-2").await.is_err());
+        assert_eq!(log.records()[0].retries(), 3);
+        assert!(log.records()[0].failed());
+
+        // the budget is spent
+        assert!(throttled.get_zones("us-east-2").await.is_err());
+        assert_eq!(log.records()[1].retries(), 0);
+
+        assert!(log
+            .summary()
+            .starts_with("2 calls retried 3 times, 2 failed"));
+
+        Ok(())
+    }
+}